@@ -6,9 +6,9 @@ struct Handler;
 
 impl EventHandler for Handler {
     // This method is called when the bot is ready to receive events
-    async fn on_ready(&self, _context: Context) {
-        // The context includes the user that the bot is logged in as
-        // println!("Ready as {}", context.user().username);
+    async fn on_ready(&self, _context: Context, ready: ReadyEvent) {
+        // The ready event includes the user that the bot is logged in as
+        println!("Ready as {}", ready.user.username);
     }
 
     // This method is called when a message is sent
@@ -0,0 +1,107 @@
+//! Procedural macros for adapt-rs. These are re-exported by the `adapt` crate when the `macros`
+//! feature is enabled and should not be depended on directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, Span};
+use quote::{format_ident, quote, quote_spanned};
+use syn::{parse_macro_input, spanned::Spanned, Error, FnArg, Item, ItemMod, Pat, Result};
+
+fn expand(struct_name: Option<Ident>, mut module: ItemMod) -> Result<proc_macro2::TokenStream> {
+    let struct_name = struct_name.unwrap_or_else(|| Ident::new("Handler", Span::call_site()));
+    let Some((_, items)) = &mut module.content else {
+        return Err(Error::new(
+            module.span(),
+            "#[event_handler] must be applied to an inline module",
+        ));
+    };
+
+    let mut methods = Vec::new();
+    for item in items.iter() {
+        let Item::Fn(function) = item else {
+            continue;
+        };
+        if function.sig.asyncness.is_none() {
+            continue;
+        }
+
+        let mut params = Vec::new();
+        let mut args = Vec::new();
+        for (i, input) in function.sig.inputs.iter().enumerate() {
+            let FnArg::Typed(typed) = input else {
+                return Err(Error::new(
+                    input.span(),
+                    "event handler functions must be free functions",
+                ));
+            };
+            let arg = match &*typed.pat {
+                Pat::Ident(ident) => ident.ident.clone(),
+                _ => format_ident!("arg{i}"),
+            };
+            let ty = &typed.ty;
+            params.push(quote!(#arg: #ty));
+            args.push(arg);
+        }
+
+        // The method is resolved by a macro generated alongside `EventHandler`, so that every
+        // event is supported and unknown names are rejected
+        let function = &function.sig.ident;
+        methods.push(quote_spanned! {function.span()=>
+            ::adapt::__event_handler_method!(#function (#(#params),*) #function (#(#args),*));
+        });
+    }
+
+    items.push(syn::parse_quote! {
+        /// An event handler which calls the event handler functions of this module.
+        #[derive(Copy, Clone, Debug, Default)]
+        pub struct #struct_name;
+    });
+    items.push(syn::parse_quote! {
+        impl ::adapt::ws::EventHandler for #struct_name {
+            #(#methods)*
+        }
+    });
+
+    Ok(quote!(#module))
+}
+
+/// Aggregates the async functions within a module into an `EventHandler` implementation.
+///
+/// Async functions are matched to events by their name, which is either the name of the event
+/// (e.g. `message_create`) or the name of the `EventHandler` method (e.g. `on_message`). Their
+/// parameters must match the parameters of the corresponding method, and an async function whose
+/// name matches no event is rejected. Other items in the module, including functions which are
+/// not async, are left untouched.
+///
+/// A unit struct named `Handler` is generated within the module, implementing `EventHandler`. A
+/// different name can be given as an argument, e.g. `#[event_handler(Bot)]`.
+///
+/// # Example
+/// ```ignore
+/// use adapt::prelude::*;
+///
+/// #[adapt::event_handler]
+/// mod handlers {
+///     use adapt::{models::Message, WithCtx};
+///
+///     async fn message_create(message: WithCtx<Message>) {
+///         println!("Received message: {}", message.content);
+///     }
+/// }
+///
+/// # fn run(client: Client) {
+/// client.add_handler(handlers::Handler);
+/// # }
+/// ```
+#[proc_macro_attribute]
+pub fn event_handler(args: TokenStream, input: TokenStream) -> TokenStream {
+    let struct_name = if args.is_empty() {
+        None
+    } else {
+        Some(parse_macro_input!(args as Ident))
+    };
+    let module = parse_macro_input!(input as ItemMod);
+
+    expand(struct_name, module)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,6 @@
+#[test]
+fn event_handler() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/handlers.rs");
+    t.compile_fail("tests/ui/unknown_event.rs");
+}
@@ -0,0 +1,28 @@
+use adapt::ws::EventHandler;
+
+#[adapt::event_handler]
+mod handlers {
+    use adapt::models::{DmChannel, Message, PartialMessage};
+    use adapt::ws::{CloseInfo, ReadyEvent};
+    use adapt::{Context, WithCtx};
+
+    async fn ready(_ctx: Context, _ready: ReadyEvent) {}
+
+    async fn on_message(message: WithCtx<Message>) {
+        reply(&message);
+    }
+
+    async fn message_delete(_message: WithCtx<PartialMessage>, _old: Option<Message>) {}
+
+    async fn dm_channel_create(_channel: WithCtx<DmChannel>) {}
+
+    async fn disconnected(_ctx: Context, _close: Option<CloseInfo>) {}
+
+    fn reply(_message: &WithCtx<Message>) {}
+}
+
+fn assert_handler(_: impl EventHandler) {}
+
+fn main() {
+    assert_handler(handlers::Handler);
+}
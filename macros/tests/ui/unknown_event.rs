@@ -0,0 +1,8 @@
+#[adapt::event_handler]
+mod handlers {
+    use adapt::{models::Message, WithCtx};
+
+    async fn on_mesage(_message: WithCtx<Message>) {}
+}
+
+fn main() {}
@@ -0,0 +1,264 @@
+use super::{check_range, ValidationError};
+use crate::models::{ChannelId, Id};
+use essence::{
+    http::channel::{CreateGuildChannelInfo, CreateGuildChannelPayload, EditChannelPayload},
+    models::{ChannelType, PermissionOverwrite},
+    Maybe,
+};
+
+/// Builds a new guild channel.
+///
+/// # Example
+/// ```no_run
+/// use adapt::essence::models::ChannelType;
+/// # use adapt::{models::GuildId, WithCtx};
+///
+/// # async fn run(guild: WithCtx<GuildId>) -> adapt::Result<()> {
+/// let channel = guild
+///     .create_channel(|c| c.name("general").kind(ChannelType::Text).topic("Say hi!"))
+///     .await?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[must_use = "this builder does nothing unless it is passed to a request"]
+pub struct ChannelBuilder {
+    name: String,
+    kind: ChannelType,
+    topic: Option<String>,
+    user_limit: Option<u16>,
+    parent_id: Option<ChannelId>,
+    position: Option<u16>,
+    overwrites: Vec<PermissionOverwrite>,
+}
+
+impl Default for ChannelBuilder {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            kind: ChannelType::Text,
+            topic: None,
+            user_limit: None,
+            parent_id: None,
+            position: None,
+            overwrites: Vec::new(),
+        }
+    }
+}
+
+impl ChannelBuilder {
+    /// The minimum and maximum length of a channel name, in characters.
+    pub const NAME_LENGTH: (usize, usize) = (1, 32);
+    /// The maximum length of a channel topic, in characters.
+    pub const MAX_TOPIC_LENGTH: usize = 1024;
+
+    /// Creates a new text channel builder with the given name.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self::default().name(name)
+    }
+
+    /// Sets the name of the channel.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Sets the type of the channel. Defaults to [`ChannelType::Text`]. Only guild channel types
+    /// are allowed.
+    pub const fn kind(mut self, kind: ChannelType) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Sets the topic of the channel. Only applicable to text and announcement channels.
+    pub fn topic(mut self, topic: impl Into<String>) -> Self {
+        self.topic = Some(topic.into());
+        self
+    }
+
+    /// Sets the maximum number of users that can connect to the channel at once, where `0` means
+    /// unlimited. Only applicable to voice channels.
+    pub const fn user_limit(mut self, user_limit: u16) -> Self {
+        self.user_limit = Some(user_limit);
+        self
+    }
+
+    /// Places the channel in the given category.
+    pub const fn parent(mut self, category_id: ChannelId) -> Self {
+        self.parent_id = Some(category_id);
+        self
+    }
+
+    /// Sets the position of the channel among the channels in its category (or among the channels
+    /// without a category). By default, the channel is placed last.
+    pub const fn position(mut self, position: u16) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Adds a permission overwrite to the channel.
+    pub fn overwrite(mut self, overwrite: PermissionOverwrite) -> Self {
+        self.overwrites.push(overwrite);
+        self
+    }
+
+    /// Returns the requested position of the channel, if any.
+    #[must_use]
+    pub const fn requested_position(&self) -> Option<u16> {
+        self.position
+    }
+
+    /// Returns the category the channel will be placed in, if any.
+    #[must_use]
+    pub const fn requested_parent(&self) -> Option<ChannelId> {
+        self.parent_id
+    }
+
+    /// Validates the channel and converts it into a raw [`CreateGuildChannelPayload`].
+    ///
+    /// The position of the channel is not part of the payload; see [`Self::requested_position`].
+    pub fn build(self) -> Result<CreateGuildChannelPayload, ValidationError> {
+        let (min, max) = Self::NAME_LENGTH;
+        check_range("name", self.name.chars().count(), min, max)?;
+        if let Some(topic) = &self.topic {
+            check_range("topic", topic.chars().count(), 0, Self::MAX_TOPIC_LENGTH)?;
+        }
+
+        let info = match self.kind {
+            ChannelType::Text => CreateGuildChannelInfo::Text { topic: self.topic },
+            ChannelType::Announcement => CreateGuildChannelInfo::Announcement { topic: self.topic },
+            ChannelType::Voice | ChannelType::Category if self.topic.is_some() => {
+                return Err(ValidationError::Conflict(
+                    "only text and announcement channels can have a topic",
+                ));
+            }
+            ChannelType::Voice => CreateGuildChannelInfo::Voice {
+                user_limit: self.user_limit.unwrap_or(0),
+            },
+            ChannelType::Category => CreateGuildChannelInfo::Category,
+            _ => {
+                return Err(ValidationError::Conflict(
+                    "guild channels cannot be DM or group channels",
+                ));
+            }
+        };
+        if self.user_limit.is_some() && self.kind != ChannelType::Voice {
+            return Err(ValidationError::Conflict(
+                "only voice channels can have a user limit",
+            ));
+        }
+        if self.parent_id.is_some() && self.kind == ChannelType::Category {
+            return Err(ValidationError::Conflict(
+                "categories cannot be placed in other categories",
+            ));
+        }
+
+        Ok(CreateGuildChannelPayload {
+            name: self.name,
+            info,
+            overwrites: (!self.overwrites.is_empty()).then_some(self.overwrites),
+            parent_id: self.parent_id.map(|id| id.get()),
+        })
+    }
+}
+
+/// Builds an edit to a channel. Only the fields that are set are changed.
+///
+/// # Example
+/// ```no_run
+/// # use adapt::{models::ChannelId, WithCtx};
+///
+/// # async fn run(channel: WithCtx<ChannelId>) -> adapt::Result<()> {
+/// let channel = channel
+///     .edit(|c| c.name("off-topic").topic("Anything goes"))
+///     .await?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[must_use = "this builder does nothing unless it is passed to a request"]
+pub struct EditChannelBuilder {
+    name: Option<String>,
+    topic: Maybe<String>,
+    user_limit: Option<u16>,
+}
+
+impl EditChannelBuilder {
+    /// Creates a new, empty channel edit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the channel.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the topic of the channel. Only applicable to text and announcement channels.
+    pub fn topic(mut self, topic: impl Into<String>) -> Self {
+        self.topic = Maybe::Value(topic.into());
+        self
+    }
+
+    /// Removes the topic of the channel.
+    pub fn clear_topic(mut self) -> Self {
+        self.topic = Maybe::Null;
+        self
+    }
+
+    /// Sets the maximum number of users that can connect to the channel at once, where `0` means
+    /// unlimited. Only applicable to voice channels.
+    pub const fn user_limit(mut self, user_limit: u16) -> Self {
+        self.user_limit = Some(user_limit);
+        self
+    }
+
+    /// Validates the edit and converts it into a raw [`EditChannelPayload`].
+    pub fn build(self) -> Result<EditChannelPayload, ValidationError> {
+        if let Some(name) = &self.name {
+            let (min, max) = ChannelBuilder::NAME_LENGTH;
+            check_range("name", name.chars().count(), min, max)?;
+        }
+        if let Maybe::Value(topic) = &self.topic {
+            let length = topic.chars().count();
+            check_range("topic", length, 0, ChannelBuilder::MAX_TOPIC_LENGTH)?;
+        }
+
+        Ok(EditChannelPayload {
+            name: self.name,
+            topic: self.topic,
+            icon: Maybe::Absent,
+            user_limit: self.user_limit,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_channel() {
+        assert!(ChannelBuilder::default().build().is_err());
+        assert!(ChannelBuilder::new("general").topic("hi").build().is_ok());
+        assert!(ChannelBuilder::new("voice")
+            .kind(ChannelType::Voice)
+            .topic("hi")
+            .build()
+            .is_err());
+        assert!(ChannelBuilder::new("dm")
+            .kind(ChannelType::Dm)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn validate_channel_edit() {
+        let payload = EditChannelBuilder::new().name("general").build().unwrap();
+        assert_eq!(payload.name.as_deref(), Some("general"));
+        assert!(EditChannelBuilder::new().name("").build().is_err());
+        assert!(EditChannelBuilder::new()
+            .topic("a".repeat(ChannelBuilder::MAX_TOPIC_LENGTH + 1))
+            .build()
+            .is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use super::{check_range, ImageData, ValidationError};
+use essence::{
+    http::guild::{CreateGuildPayload, EditGuildPayload},
+    Maybe,
+};
+
+/// Builds a new guild.
+///
+/// # Example
+/// ```no_run
+/// use adapt::builder::ImageData;
+/// # use adapt::Context;
+///
+/// # async fn run(ctx: Context) -> adapt::Result<()> {
+/// let icon = ImageData::from_path("icon.png").await?;
+/// let guild = ctx
+///     .create_guild(|g| g.name("My Guild").description("A cool place").icon(icon))
+///     .await?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[must_use = "this builder does nothing unless it is passed to a request"]
+pub struct GuildBuilder {
+    name: String,
+    description: Option<String>,
+    icon: Option<ImageData>,
+    banner: Option<ImageData>,
+    public: bool,
+}
+
+impl GuildBuilder {
+    /// The minimum and maximum length of a guild name, in characters.
+    pub const NAME_LENGTH: (usize, usize) = (2, 100);
+    /// The maximum length of a guild description, in characters.
+    pub const MAX_DESCRIPTION_LENGTH: usize = 1024;
+
+    /// Creates a new guild builder with the given name.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self::default().name(name)
+    }
+
+    /// Sets the name of the guild.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Sets the description of the guild.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Sets the icon of the guild.
+    pub fn icon(mut self, icon: ImageData) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    /// Sets the banner of the guild.
+    pub fn banner(mut self, banner: ImageData) -> Self {
+        self.banner = Some(banner);
+        self
+    }
+
+    /// Sets whether the guild is public, meaning it can be discovered by anyone. Defaults to
+    /// `false`.
+    ///
+    /// # Note
+    /// The REST API does not currently provide endpoints to browse, search or join public guilds,
+    /// so they can still only be joined through invites, such as a
+    /// [vanity invite](crate::models::Guild::vanity).
+    pub const fn public(mut self, public: bool) -> Self {
+        self.public = public;
+        self
+    }
+
+    /// Validates the guild and converts it into a raw [`CreateGuildPayload`].
+    pub fn build(self) -> Result<CreateGuildPayload, ValidationError> {
+        let (min, max) = Self::NAME_LENGTH;
+        check_range("name", self.name.chars().count(), min, max)?;
+        if let Some(description) = &self.description {
+            let length = description.chars().count();
+            check_range("description", length, 0, Self::MAX_DESCRIPTION_LENGTH)?;
+        }
+
+        Ok(CreateGuildPayload {
+            name: self.name,
+            description: self.description,
+            icon: self.icon.map(ImageData::into_string),
+            banner: self.banner.map(ImageData::into_string),
+            public: self.public,
+            nonce: None,
+        })
+    }
+}
+
+/// Builds an edit to a guild. Only the fields that are set are changed.
+///
+/// # Example
+/// ```no_run
+/// use adapt::builder::ImageData;
+/// # use adapt::{models::GuildId, WithCtx};
+///
+/// # async fn run(guild: WithCtx<GuildId>) -> adapt::Result<()> {
+/// let icon = ImageData::from_path("icon.png").await?;
+/// let guild = guild.edit(|g| g.icon(icon).clear_banner()).await?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[must_use = "this builder does nothing unless it is passed to a request"]
+pub struct EditGuildBuilder {
+    name: Option<String>,
+    description: Maybe<String>,
+    icon: Maybe<ImageData>,
+    banner: Maybe<ImageData>,
+    public: Option<bool>,
+}
+
+impl EditGuildBuilder {
+    /// Creates a new, empty guild edit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the guild.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the description of the guild.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Maybe::Value(description.into());
+        self
+    }
+
+    /// Removes the description of the guild.
+    pub fn clear_description(mut self) -> Self {
+        self.description = Maybe::Null;
+        self
+    }
+
+    /// Sets the icon of the guild.
+    pub fn icon(mut self, icon: ImageData) -> Self {
+        self.icon = Maybe::Value(icon);
+        self
+    }
+
+    /// Sets the icon of the guild to the given image bytes.
+    pub fn icon_bytes(self, bytes: impl AsRef<[u8]>) -> Self {
+        self.icon(ImageData::from_bytes(bytes))
+    }
+
+    /// Removes the icon of the guild.
+    pub fn clear_icon(mut self) -> Self {
+        self.icon = Maybe::Null;
+        self
+    }
+
+    /// Sets the banner of the guild.
+    pub fn banner(mut self, banner: ImageData) -> Self {
+        self.banner = Maybe::Value(banner);
+        self
+    }
+
+    /// Sets the banner of the guild to the given image bytes.
+    pub fn banner_bytes(self, bytes: impl AsRef<[u8]>) -> Self {
+        self.banner(ImageData::from_bytes(bytes))
+    }
+
+    /// Removes the banner of the guild.
+    pub fn clear_banner(mut self) -> Self {
+        self.banner = Maybe::Null;
+        self
+    }
+
+    /// Sets whether the guild is public, meaning it can be discovered by anyone. See
+    /// [`GuildBuilder::public`].
+    pub const fn public(mut self, public: bool) -> Self {
+        self.public = Some(public);
+        self
+    }
+
+    /// Validates the edit and converts it into a raw [`EditGuildPayload`].
+    pub fn build(self) -> Result<EditGuildPayload, ValidationError> {
+        if let Some(name) = &self.name {
+            let (min, max) = GuildBuilder::NAME_LENGTH;
+            check_range("name", name.chars().count(), min, max)?;
+        }
+        if let Maybe::Value(description) = &self.description {
+            let length = description.chars().count();
+            check_range(
+                "description",
+                length,
+                0,
+                GuildBuilder::MAX_DESCRIPTION_LENGTH,
+            )?;
+        }
+
+        Ok(EditGuildPayload {
+            name: self.name,
+            description: self.description,
+            icon: map_image(self.icon),
+            banner: map_image(self.banner),
+            public: self.public,
+        })
+    }
+}
+
+fn map_image(image: Maybe<ImageData>) -> Maybe<String> {
+    match image {
+        Maybe::Absent => Maybe::Absent,
+        Maybe::Null => Maybe::Null,
+        Maybe::Value(image) => Maybe::Value(image.into_string()),
+    }
+}
@@ -0,0 +1,69 @@
+use essence::http::guild::GetGuildQuery;
+
+/// Builds a query for fetching guilds, selecting which related objects are included in the
+/// response.
+///
+/// By default, only the guild itself is fetched. Including more objects makes the response larger
+/// but saves separate requests for them.
+///
+/// # Example
+/// ```no_run
+/// use adapt::builder::GuildQueryBuilder;
+/// # use adapt::{models::GuildId, WithCtx};
+///
+/// # async fn run(guild: WithCtx<GuildId>) -> adapt::Result<()> {
+/// let guild = guild.fetch(GuildQueryBuilder::new().channels().roles()).await?;
+/// let roles = guild.roles.as_deref().unwrap_or_default();
+/// # Ok(()) }
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[must_use = "this builder does nothing unless it is passed to a request"]
+pub struct GuildQueryBuilder {
+    channels: bool,
+    members: bool,
+    roles: bool,
+}
+
+impl GuildQueryBuilder {
+    /// Creates a new query builder which includes no related objects.
+    pub const fn new() -> Self {
+        Self {
+            channels: false,
+            members: false,
+            roles: false,
+        }
+    }
+
+    /// Creates a new query builder which includes all related objects.
+    pub const fn all() -> Self {
+        Self::new().channels().members().roles()
+    }
+
+    /// Include the channels of the guild.
+    pub const fn channels(mut self) -> Self {
+        self.channels = true;
+        self
+    }
+
+    /// Include the members of the guild.
+    pub const fn members(mut self) -> Self {
+        self.members = true;
+        self
+    }
+
+    /// Include the roles of the guild.
+    pub const fn roles(mut self) -> Self {
+        self.roles = true;
+        self
+    }
+
+    /// Converts the query into a raw [`GetGuildQuery`].
+    #[must_use]
+    pub const fn build(self) -> GetGuildQuery {
+        GetGuildQuery {
+            channels: self.channels,
+            members: self.members,
+            roles: self.roles,
+        }
+    }
+}
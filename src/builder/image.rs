@@ -0,0 +1,54 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::path::Path;
+
+/// Image data to be uploaded as part of a payload, such as a guild icon or banner.
+///
+/// The Adapt API expects images as base64-encoded [data URIs]; this type takes care of the
+/// encoding and detects the MIME type of the image from its contents.
+///
+/// [data URIs]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Basics_of_HTTP/Data_URLs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageData(String);
+
+impl ImageData {
+    /// Encodes the given image bytes.
+    #[must_use]
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Self {
+        let bytes = bytes.as_ref();
+        Self(format!(
+            "data:{};base64,{}",
+            Self::detect_mime_type(bytes),
+            STANDARD.encode(bytes),
+        ))
+    }
+
+    /// Reads and encodes the image at the given path.
+    pub async fn from_path(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        tokio::fs::read(path).await.map(Self::from_bytes)
+    }
+
+    /// Detects the MIME type of an image from its magic bytes, defaulting to
+    /// `application/octet-stream` if the format is unknown.
+    #[must_use]
+    pub fn detect_mime_type(bytes: &[u8]) -> &'static str {
+        match bytes {
+            [0x89, b'P', b'N', b'G', ..] => "image/png",
+            [0xFF, 0xD8, 0xFF, ..] => "image/jpeg",
+            [b'G', b'I', b'F', b'8', ..] => "image/gif",
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp",
+            _ => "application/octet-stream",
+        }
+    }
+
+    /// Returns the encoded data URI.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Consumes the image, returning the encoded data URI.
+    #[must_use]
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
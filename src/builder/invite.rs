@@ -0,0 +1,63 @@
+use super::{check_range, ValidationError};
+use essence::http::invite::CreateInvitePayload;
+use std::time::Duration;
+
+/// Builds a new guild invite.
+///
+/// By default, invites never expire and can be used an unlimited number of times.
+///
+/// # Example
+/// ```no_run
+/// use adapt::builder::InviteBuilder;
+/// use std::time::Duration;
+/// # use adapt::{models::GuildId, WithCtx};
+///
+/// # async fn run(guild: WithCtx<GuildId>) -> adapt::Result<()> {
+/// let builder = InviteBuilder::new()
+///     .max_uses(10)
+///     .max_age(Duration::from_secs(24 * 60 * 60));
+/// let invite = guild.create_invite(builder).await?;
+/// println!("Created invite {}", invite.code);
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[must_use = "this builder does nothing unless it is passed to a request"]
+pub struct InviteBuilder {
+    max_uses: u32,
+    max_age: Duration,
+}
+
+impl InviteBuilder {
+    /// The maximum age of an invite.
+    pub const MAX_AGE: Duration = Duration::from_secs(u32::MAX as u64);
+
+    /// Creates a new invite builder with the default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of times the invite can be used, where `0` means unlimited.
+    pub const fn max_uses(mut self, max_uses: u32) -> Self {
+        self.max_uses = max_uses;
+        self
+    }
+
+    /// Sets how long the invite is valid for, with a precision of seconds. A duration of zero
+    /// means the invite never expires.
+    pub const fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Validates the invite and converts it into a raw [`CreateInvitePayload`].
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn build(self) -> Result<CreateInvitePayload, ValidationError> {
+        let max_age = usize::try_from(self.max_age.as_secs()).unwrap_or(usize::MAX);
+        check_range("max_age", max_age, 0, Self::MAX_AGE.as_secs() as usize)?;
+
+        Ok(CreateInvitePayload {
+            max_age: max_age as u32,
+            max_uses: self.max_uses,
+        })
+    }
+}
@@ -0,0 +1,125 @@
+use super::{check_range, ValidationError};
+use crate::models::{Id, RoleId};
+use essence::{http::member::EditMemberPayload, Maybe};
+
+/// Builds an edit to a guild member.
+///
+/// Roles are edited as deltas: roles can be added and removed individually without knowing the
+/// full list of roles of the member. The deltas are applied to the member's current roles when
+/// the edit is sent.
+///
+/// # Example
+/// ```no_run
+/// # use adapt::{models::{Member, RoleId}, WithCtx};
+/// # async fn run(member: WithCtx<Member>, muted: RoleId, verified: RoleId) -> adapt::Result<()> {
+/// let member = member
+///     .edit(|m| m.nick("New nickname").add_role(verified).remove_role(muted))
+///     .await?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[must_use = "this builder does nothing unless it is passed to a request"]
+pub struct EditMemberBuilder {
+    nick: Maybe<String>,
+    roles: Option<Vec<RoleId>>,
+    add_roles: Vec<RoleId>,
+    remove_roles: Vec<RoleId>,
+}
+
+impl EditMemberBuilder {
+    /// The minimum and maximum length of a nickname, in characters.
+    pub const NICK_LENGTH: (usize, usize) = (1, 32);
+
+    /// Creates a new, empty member edit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the nickname of the member.
+    pub fn nick(mut self, nick: impl Into<String>) -> Self {
+        self.nick = Maybe::Value(nick.into());
+        self
+    }
+
+    /// Removes the nickname of the member.
+    pub fn clear_nick(mut self) -> Self {
+        self.nick = Maybe::Null;
+        self
+    }
+
+    /// Adds the given role to the member.
+    pub fn add_role(mut self, role_id: RoleId) -> Self {
+        self.remove_roles.retain(|id| *id != role_id);
+        self.add_roles.push(role_id);
+        self
+    }
+
+    /// Removes the given role from the member.
+    pub fn remove_role(mut self, role_id: RoleId) -> Self {
+        self.add_roles.retain(|id| *id != role_id);
+        self.remove_roles.push(role_id);
+        self
+    }
+
+    /// Replaces all roles of the member with the given roles. Roles added or removed afterwards
+    /// are applied on top of these.
+    pub fn roles(mut self, role_ids: impl IntoIterator<Item = RoleId>) -> Self {
+        self.roles = Some(role_ids.into_iter().collect());
+        self.add_roles.clear();
+        self.remove_roles.clear();
+        self
+    }
+
+    /// Returns whether the edit changes the roles of the member.
+    #[must_use]
+    pub fn edits_roles(&self) -> bool {
+        self.roles.is_some() || !self.add_roles.is_empty() || !self.remove_roles.is_empty()
+    }
+
+    /// Validates the edit and converts it into a raw [`EditMemberPayload`], applying the role
+    /// deltas to the given current roles of the member.
+    pub fn build(self, current_roles: &[RoleId]) -> Result<EditMemberPayload, ValidationError> {
+        if let Maybe::Value(nick) = &self.nick {
+            let (min, max) = Self::NICK_LENGTH;
+            check_range("nick", nick.chars().count(), min, max)?;
+        }
+
+        let roles = self.edits_roles().then(|| {
+            let mut roles = self.roles.unwrap_or_else(|| current_roles.to_vec());
+            roles.retain(|id| !self.remove_roles.contains(id));
+            for role_id in self.add_roles {
+                if !roles.contains(&role_id) {
+                    roles.push(role_id);
+                }
+            }
+            roles.into_iter().map(|id| id.get()).collect()
+        });
+
+        Ok(EditMemberPayload {
+            nick: self.nick,
+            roles,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_role_deltas() {
+        let current = [RoleId::from(1), RoleId::from(2)];
+        let payload = EditMemberBuilder::new()
+            .add_role(RoleId::from(3))
+            .remove_role(RoleId::from(1))
+            .add_role(RoleId::from(2))
+            .build(&current)
+            .unwrap();
+
+        assert_eq!(payload.roles, Some(vec![2, 3]));
+        assert_eq!(
+            EditMemberBuilder::new().build(&current).unwrap().roles,
+            None
+        );
+    }
+}
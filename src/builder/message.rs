@@ -0,0 +1,330 @@
+use super::{check_range, ValidationError};
+use crate::models::{Embed, IntoCreateMessage, Message};
+use essence::{
+    http::message::{CreateMessagePayload, EditMessagePayload},
+    Maybe,
+};
+
+/// Builds a new message. This can be passed anywhere a message payload is accepted, for example
+/// to [`WithCtx<ChannelId>::send`](crate::WithCtx::send).
+///
+/// # Example
+/// ```no_run
+/// use adapt::builder::MessageBuilder;
+/// # use adapt::{models::ChannelId, WithCtx};
+///
+/// # async fn run(channel: WithCtx<ChannelId>) -> adapt::Result<()> {
+/// let message = MessageBuilder::new("Hello!").nonce("local-1");
+/// channel.send(message).await?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug, Default)]
+#[must_use = "this builder does nothing unless it is passed to a request"]
+pub struct MessageBuilder {
+    content: Option<String>,
+    embeds: Vec<Embed>,
+    nonce: Option<String>,
+    skip_validation: bool,
+}
+
+impl MessageBuilder {
+    /// The maximum length of the content of a message, in characters.
+    pub const MAX_CONTENT_LENGTH: usize = 4096;
+    /// The maximum number of embeds in a message.
+    pub const MAX_EMBEDS: usize = 10;
+    /// The maximum length of the title of an embed, in characters.
+    pub const MAX_EMBED_TITLE_LENGTH: usize = 256;
+    /// The maximum length of the description of an embed, in characters.
+    pub const MAX_EMBED_DESCRIPTION_LENGTH: usize = 4096;
+    /// The maximum number of fields in an embed.
+    pub const MAX_EMBED_FIELDS: usize = 25;
+    /// The maximum length of the name of an embed field, in characters.
+    pub const MAX_EMBED_FIELD_NAME_LENGTH: usize = 256;
+    /// The maximum length of the value of an embed field, in characters.
+    pub const MAX_EMBED_FIELD_VALUE_LENGTH: usize = 1024;
+    /// The maximum length of the name of an embed author, in characters.
+    pub const MAX_EMBED_AUTHOR_NAME_LENGTH: usize = 256;
+    /// The maximum length of the footer text of an embed, in characters.
+    pub const MAX_EMBED_FOOTER_LENGTH: usize = 2048;
+    /// The maximum number of files attached to a message.
+    pub const MAX_ATTACHMENTS: usize = 10;
+    /// The maximum size of a file attached to a message, in bytes.
+    pub const MAX_ATTACHMENT_SIZE: u64 = 25 * 1024 * 1024;
+
+    /// Creates a new message builder with the given content.
+    pub fn new(content: impl Into<String>) -> Self {
+        Self::default().content(content)
+    }
+
+    /// Sets the content of the message.
+    pub fn content(mut self, content: impl Into<String>) -> Self {
+        self.content = Some(content.into());
+        self
+    }
+
+    /// Adds the given embed to the message.
+    pub fn add_embed(mut self, embed: Embed) -> Self {
+        self.embeds.push(embed);
+        self
+    }
+
+    /// Sets the nonce of the message. The nonce is echoed back in the `message_create` event of
+    /// the message, which allows matching the event to a message sent by this client, for example
+    /// to replace an optimistically rendered message. See
+    /// [`WithCtx<ChannelId>::send_and_confirm`](crate::WithCtx::send_and_confirm).
+    pub fn nonce(mut self, nonce: impl Into<String>) -> Self {
+        self.nonce = Some(nonce.into());
+        self
+    }
+
+    /// Skips client-side validation of the message when it is sent, for example when sending to
+    /// a self-hosted instance with different limits. The API still rejects invalid messages.
+    pub const fn skip_validation(mut self) -> Self {
+        self.skip_validation = true;
+        self
+    }
+
+    /// Converts the message into a raw [`CreateMessagePayload`] without validating it. Messages
+    /// are validated when they are sent; see [`validate_message`].
+    #[must_use]
+    pub fn build(self) -> CreateMessagePayload {
+        CreateMessagePayload {
+            content: self.content,
+            embeds: self.embeds,
+            nonce: self.nonce,
+        }
+    }
+}
+
+impl IntoCreateMessage for MessageBuilder {
+    fn into_create_message(self) -> CreateMessagePayload {
+        self.build()
+    }
+
+    fn skips_validation(&self) -> bool {
+        self.skip_validation
+    }
+}
+
+/// Validates the given message payload against the limits of the Adapt API, along with the sizes
+/// of the files attached to it, in bytes. See the constants on [`MessageBuilder`] for the limits.
+///
+/// # Errors
+/// * If the message has no content, embeds or attachments.
+/// * If the content, an embed, or an attachment exceeds its limit.
+pub fn validate_message(
+    payload: &CreateMessagePayload,
+    attachment_sizes: &[u64],
+) -> Result<(), ValidationError> {
+    let content = payload.content.as_deref().unwrap_or_default();
+    if content.is_empty() && payload.embeds.is_empty() && attachment_sizes.is_empty() {
+        return Err(ValidationError::Conflict(
+            "a message must have content, embeds or attachments",
+        ));
+    }
+
+    validate_content(content)?;
+    validate_embeds(&payload.embeds)?;
+
+    let count = attachment_sizes.len();
+    check_range("attachments", count, 0, MessageBuilder::MAX_ATTACHMENTS)?;
+    for &size in attachment_sizes {
+        check_range(
+            "attachment size",
+            usize::try_from(size).unwrap_or(usize::MAX),
+            0,
+            usize::try_from(MessageBuilder::MAX_ATTACHMENT_SIZE).unwrap_or(usize::MAX),
+        )?;
+    }
+    Ok(())
+}
+
+fn validate_content(content: &str) -> Result<(), ValidationError> {
+    let length = content.chars().count();
+    check_range("content", length, 0, MessageBuilder::MAX_CONTENT_LENGTH)
+}
+
+/// Checks the number of embeds and the length of every text field of each embed.
+fn validate_embeds(embeds: &[Embed]) -> Result<(), ValidationError> {
+    let check_text = |field, text: &str, max| check_range(field, text.chars().count(), 0, max);
+
+    check_range("embeds", embeds.len(), 0, MessageBuilder::MAX_EMBEDS)?;
+    for embed in embeds {
+        check_text(
+            "embed title",
+            embed.title.as_deref().unwrap_or_default(),
+            MessageBuilder::MAX_EMBED_TITLE_LENGTH,
+        )?;
+        check_text(
+            "embed description",
+            embed.description.as_deref().unwrap_or_default(),
+            MessageBuilder::MAX_EMBED_DESCRIPTION_LENGTH,
+        )?;
+        if let Some(author) = &embed.author {
+            check_text(
+                "embed author name",
+                &author.name,
+                MessageBuilder::MAX_EMBED_AUTHOR_NAME_LENGTH,
+            )?;
+        }
+        if let Some(footer) = &embed.footer {
+            check_text(
+                "embed footer",
+                &footer.text,
+                MessageBuilder::MAX_EMBED_FOOTER_LENGTH,
+            )?;
+        }
+        check_range(
+            "embed fields",
+            embed.fields.len(),
+            0,
+            MessageBuilder::MAX_EMBED_FIELDS,
+        )?;
+        for field in &embed.fields {
+            check_text(
+                "embed field name",
+                &field.name,
+                MessageBuilder::MAX_EMBED_FIELD_NAME_LENGTH,
+            )?;
+            check_text(
+                "embed field value",
+                &field.value,
+                MessageBuilder::MAX_EMBED_FIELD_VALUE_LENGTH,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Builds an edit to a message.
+///
+/// Only the fields that are set are sent, so an edit never clobbers fields it does not touch.
+/// Embeds can be added individually without knowing the current embeds of the message; these
+/// are applied on top of the current embeds when the edit is sent.
+///
+/// Attachments cannot be edited, so they are always retained.
+///
+/// # Example
+/// ```no_run
+/// # use adapt::{models::Message, WithCtx};
+/// # async fn run(message: WithCtx<Message>) -> adapt::Result<()> {
+/// let message = message.edit(|e| e.content("Edited!").clear_embeds()).await?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug, Default)]
+#[must_use = "this builder does nothing unless it is passed to a request"]
+pub struct EditMessageBuilder {
+    content: Maybe<String>,
+    embeds: Option<Vec<Embed>>,
+    add_embeds: Vec<Embed>,
+}
+
+impl EditMessageBuilder {
+    /// Creates a new, empty message edit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the content of the message.
+    pub fn content(mut self, content: impl Into<String>) -> Self {
+        self.content = Maybe::Value(content.into());
+        self
+    }
+
+    /// Removes the content of the message.
+    pub fn clear_content(mut self) -> Self {
+        self.content = Maybe::Null;
+        self
+    }
+
+    /// Adds the given embed to the message, after its current embeds.
+    pub fn add_embed(mut self, embed: Embed) -> Self {
+        self.add_embeds.push(embed);
+        self
+    }
+
+    /// Replaces all embeds of the message with the given embeds. Embeds added afterwards are
+    /// applied on top of these.
+    pub fn embeds(mut self, embeds: impl IntoIterator<Item = Embed>) -> Self {
+        self.embeds = Some(embeds.into_iter().collect());
+        self.add_embeds.clear();
+        self
+    }
+
+    /// Removes all embeds from the message.
+    pub fn clear_embeds(self) -> Self {
+        self.embeds([])
+    }
+
+    /// Returns whether the edit changes the embeds of the message.
+    #[must_use]
+    pub fn edits_embeds(&self) -> bool {
+        self.embeds.is_some() || !self.add_embeds.is_empty()
+    }
+
+    /// Validates the edit and converts it into a raw [`EditMessagePayload`], applying added
+    /// embeds to the current embeds of the given message.
+    ///
+    /// # Errors
+    /// * If the edit would leave the message without any content, embeds or attachments.
+    /// * If the new content or embeds exceed the limits on [`MessageBuilder`].
+    pub fn build(self, current: &Message) -> Result<EditMessagePayload, ValidationError> {
+        let embeds = self.edits_embeds().then(|| {
+            let mut embeds = self.embeds.unwrap_or_else(|| current.embeds.clone());
+            embeds.extend(self.add_embeds);
+            embeds
+        });
+
+        let has_content = match &self.content {
+            Maybe::Value(content) => !content.is_empty(),
+            Maybe::Null => false,
+            Maybe::Absent => !current.content.is_empty(),
+        };
+        let has_embeds = embeds
+            .as_ref()
+            .map_or(!current.embeds.is_empty(), |e| !e.is_empty());
+        if !has_content && !has_embeds && current.attachments.is_empty() {
+            return Err(ValidationError::Conflict(
+                "a message must have content, embeds or attachments",
+            ));
+        }
+
+        if let Maybe::Value(content) = &self.content {
+            validate_content(content)?;
+        }
+        if let Some(embeds) = &embeds {
+            validate_embeds(embeds)?;
+        }
+
+        Ok(EditMessagePayload {
+            content: self.content,
+            embeds: embeds.map_or(Maybe::Absent, Maybe::Value),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_limits() {
+        let message = |content: &str| MessageBuilder::new(content).build();
+
+        assert!(validate_message(&message("Hello"), &[]).is_ok());
+        assert!(validate_message(&message(""), &[]).is_err());
+        assert!(validate_message(&message(""), &[1]).is_ok());
+        assert!(validate_message(&message(&"a".repeat(4097)), &[]).is_err());
+        assert!(validate_message(&message("Hello"), &[u64::MAX]).is_err());
+        assert!(validate_message(&message("Hello"), &[1; 11]).is_err());
+
+        let embed = |title: &str| Embed {
+            title: Some(title.to_string()),
+            ..Embed::default()
+        };
+        let payload = MessageBuilder::default().add_embed(embed(&"a".repeat(257)));
+        assert!(validate_message(&payload.build(), &[]).is_err());
+        let payload = MessageBuilder::default().add_embed(embed("Title"));
+        assert!(validate_message(&payload.build(), &[]).is_ok());
+    }
+}
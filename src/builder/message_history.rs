@@ -0,0 +1,206 @@
+use super::{check_range, ValidationError};
+use crate::models::{Id, MessageId, UserId};
+use essence::http::message::MessageHistoryQuery;
+
+/// Builds a query for fetching the message history of a channel.
+///
+/// By default, the most recent 100 messages are fetched, newest first.
+///
+/// # Example
+/// ```no_run
+/// use adapt::builder::MessageHistoryQueryBuilder;
+/// # use adapt::{models::ChannelId, WithCtx};
+///
+/// # async fn run(channel: WithCtx<ChannelId>) -> adapt::Result<()> {
+/// let query = MessageHistoryQueryBuilder::new().limit(50).oldest_first();
+/// let messages = channel.history(query).await?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[must_use = "this builder does nothing unless it is passed to a request"]
+pub struct MessageHistoryQueryBuilder {
+    before: Option<MessageId>,
+    after: Option<MessageId>,
+    around: Option<MessageId>,
+    limit: Option<u8>,
+    author: Option<UserId>,
+    oldest_first: bool,
+}
+
+impl MessageHistoryQueryBuilder {
+    /// The maximum number of messages that can be fetched in a single request.
+    pub const MAX_LIMIT: u8 = 100;
+
+    /// Creates a new query builder with the default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only fetch messages sent before the given message.
+    pub const fn before(mut self, message_id: MessageId) -> Self {
+        self.before = Some(message_id);
+        self
+    }
+
+    /// Only fetch messages sent after the given message.
+    pub const fn after(mut self, message_id: MessageId) -> Self {
+        self.after = Some(message_id);
+        self
+    }
+
+    /// Only fetch the given message and the messages sent around it: half of the limit before it,
+    /// including the message itself, and the rest after it. Cannot be combined with
+    /// [`Self::before`] or [`Self::after`].
+    ///
+    /// The API has no such query, so [`WithCtx<ChannelId>::history`] makes one request for
+    /// each side of the message, and [`Self::build`] rejects it.
+    ///
+    /// [`WithCtx<ChannelId>::history`]: crate::WithCtx::history
+    pub const fn around(mut self, message_id: MessageId) -> Self {
+        self.around = Some(message_id);
+        self
+    }
+
+    /// Sets the maximum number of messages to fetch. Must be between 1 and
+    /// [`Self::MAX_LIMIT`]. Defaults to [`Self::MAX_LIMIT`].
+    pub const fn limit(mut self, limit: u8) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Only fetch messages sent by the given user.
+    pub const fn author(mut self, user_id: UserId) -> Self {
+        self.author = Some(user_id);
+        self
+    }
+
+    /// Fetch the oldest messages first instead of the newest.
+    pub const fn oldest_first(mut self) -> Self {
+        self.oldest_first = true;
+        self
+    }
+
+    /// Returns whether the messages will be returned oldest first.
+    #[must_use]
+    pub const fn is_oldest_first(&self) -> bool {
+        self.oldest_first
+    }
+
+    /// Validates the query and converts it into a raw [`MessageHistoryQuery`].
+    ///
+    /// # Errors
+    /// * If the limit is out of range, or `before` is not later than `after`.
+    /// * If [`Self::around`] is set, since it cannot be expressed as a single query.
+    pub fn build(self) -> Result<MessageHistoryQuery, ValidationError> {
+        let limit = self.limit.unwrap_or(Self::MAX_LIMIT);
+        check_range("limit", limit as usize, 1, Self::MAX_LIMIT as usize)?;
+
+        if self.around.is_some() {
+            return Err(ValidationError::Conflict(
+                "`around` cannot be sent as a single query",
+            ));
+        }
+
+        if let (Some(before), Some(after)) = (self.before, self.after) {
+            if before <= after {
+                return Err(ValidationError::Conflict(
+                    "`before` must be a later message than `after`",
+                ));
+            }
+        }
+
+        Ok(MessageHistoryQuery {
+            before: self.before.map(Into::into),
+            after: self.after.map(Into::into),
+            limit,
+            user_id: self.author.map(Into::into),
+            oldest_first: self.oldest_first,
+        })
+    }
+
+    /// Splits a query for the messages around a message into a query for the message and the
+    /// messages before it, newest first, and a query for the messages after it, oldest first, if
+    /// any are requested. Returns `None` if [`Self::around`] is not set.
+    pub(crate) fn split_around(
+        self,
+    ) -> Result<Option<(MessageHistoryQuery, Option<MessageHistoryQuery>)>, ValidationError> {
+        let Some(around) = self.around else {
+            return Ok(None);
+        };
+        if self.before.is_some() || self.after.is_some() {
+            return Err(ValidationError::Conflict(
+                "`around` cannot be combined with `before` or `after`",
+            ));
+        }
+        let limit = self.limit.unwrap_or(Self::MAX_LIMIT);
+        check_range("limit", limit as usize, 1, Self::MAX_LIMIT as usize)?;
+
+        let base = Self {
+            around: None,
+            ..self
+        };
+        let older = Self {
+            before: Some(MessageId::from(around.get().saturating_add(1))),
+            limit: Some(limit - limit / 2),
+            oldest_first: false,
+            ..base.clone()
+        };
+        let newer = (limit / 2 > 0).then(|| Self {
+            after: Some(around),
+            limit: Some(limit / 2),
+            oldest_first: true,
+            ..base
+        });
+        Ok(Some((older.build()?, newer.map(Self::build).transpose()?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_limit() {
+        assert!(MessageHistoryQueryBuilder::new().limit(0).build().is_err());
+        assert!(MessageHistoryQueryBuilder::new()
+            .limit(101)
+            .build()
+            .is_err());
+        assert_eq!(
+            MessageHistoryQueryBuilder::new().build().unwrap().limit,
+            100
+        );
+    }
+
+    #[test]
+    fn validate_range() {
+        let query = MessageHistoryQueryBuilder::new()
+            .before(MessageId::from(1))
+            .after(MessageId::from(2));
+        assert!(query.build().is_err());
+    }
+
+    #[test]
+    fn split_around() {
+        let query = MessageHistoryQueryBuilder::new()
+            .around(MessageId::from(10))
+            .limit(5);
+        assert!(query.clone().build().is_err());
+
+        let (older, newer) = query.split_around().unwrap().unwrap();
+        assert_eq!(
+            (older.before, older.limit, older.oldest_first),
+            (Some(11), 3, false)
+        );
+        let newer = newer.unwrap();
+        assert_eq!(
+            (newer.after, newer.limit, newer.oldest_first),
+            (Some(10), 2, true)
+        );
+
+        let conflict = MessageHistoryQueryBuilder::new()
+            .around(MessageId::from(10))
+            .after(MessageId::from(1));
+        assert!(conflict.split_around().is_err());
+    }
+}
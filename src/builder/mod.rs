@@ -0,0 +1,78 @@
+//! Builders for constructing request payloads and queries.
+//!
+//! Builders validate their values client-side before any request is made, returning a
+//! [`ValidationError`] if a value would be rejected by the Adapt API.
+
+mod channel;
+mod guild;
+mod guild_query;
+mod image;
+mod invite;
+mod member;
+mod message;
+mod message_history;
+mod role;
+
+pub use channel::{ChannelBuilder, EditChannelBuilder};
+pub use guild::{EditGuildBuilder, GuildBuilder};
+pub use guild_query::GuildQueryBuilder;
+pub use image::ImageData;
+pub use invite::InviteBuilder;
+pub use member::EditMemberBuilder;
+pub use message::{validate_message, EditMessageBuilder, MessageBuilder};
+pub use message_history::MessageHistoryQueryBuilder;
+pub use role::RoleBuilder;
+
+use std::fmt::{self, Display};
+
+/// An error that occurs when a builder is given values that would be rejected by the Adapt API.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    /// A numeric value or length was outside of its allowed range.
+    OutOfRange {
+        /// The name of the field that is out of range.
+        field: &'static str,
+        /// The minimum allowed value, inclusive.
+        min: usize,
+        /// The maximum allowed value, inclusive.
+        max: usize,
+        /// The value that was given.
+        value: usize,
+    },
+    /// Two or more values were given that contradict each other.
+    Conflict(&'static str),
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange {
+                field,
+                min,
+                max,
+                value,
+            } => write!(f, "{field} must be between {min} and {max}, got {value}"),
+            Self::Conflict(reason) => f.write_str(reason),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Validates that `value` is within `min..=max`.
+pub(crate) const fn check_range(
+    field: &'static str,
+    value: usize,
+    min: usize,
+    max: usize,
+) -> Result<(), ValidationError> {
+    if value < min || value > max {
+        return Err(ValidationError::OutOfRange {
+            field,
+            min,
+            max,
+            value,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use super::{check_range, ImageData, ValidationError};
+use crate::models::{PermissionPair, Permissions};
+use essence::http::role::CreateRolePayload;
+
+/// Builds a new role.
+///
+/// # Example
+/// ```no_run
+/// use adapt::builder::RoleBuilder;
+/// use adapt::models::Permissions;
+/// # use adapt::{models::GuildId, WithCtx};
+///
+/// # async fn run(guild: WithCtx<GuildId>) -> adapt::Result<()> {
+/// let builder = RoleBuilder::new("Moderator")
+///     .color(0x3498db)
+///     .allow(Permissions::KICK_MEMBERS | Permissions::MANAGE_MESSAGES)
+///     .hoist(true);
+/// let role = guild.create_role(builder).await?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[must_use = "this builder does nothing unless it is passed to a request"]
+pub struct RoleBuilder {
+    name: String,
+    color: Option<u32>,
+    icon: Option<ImageData>,
+    permissions: PermissionPair,
+    hoist: bool,
+    mentionable: bool,
+}
+
+impl RoleBuilder {
+    /// The minimum and maximum length of a role name, in characters.
+    pub const NAME_LENGTH: (usize, usize) = (1, 32);
+    /// The maximum value of a role color.
+    pub const MAX_COLOR: u32 = 0xFF_FF_FF;
+
+    /// Creates a new role builder with the given name.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self::default().name(name)
+    }
+
+    /// Sets the name of the role.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Sets the color of the role as an RGB integer, e.g. `0xFF0000` for red.
+    pub const fn color(mut self, color: u32) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Sets the icon of the role.
+    pub fn icon(mut self, icon: ImageData) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    /// Allows the given permissions, in addition to any previously allowed permissions. The
+    /// permissions are removed from the denied permissions.
+    pub fn allow(mut self, permissions: Permissions) -> Self {
+        self.permissions.allow |= permissions;
+        self.permissions.deny &= !permissions;
+        self
+    }
+
+    /// Denies the given permissions, in addition to any previously denied permissions. The
+    /// permissions are removed from the allowed permissions.
+    pub fn deny(mut self, permissions: Permissions) -> Self {
+        self.permissions.deny |= permissions;
+        self.permissions.allow &= !permissions;
+        self
+    }
+
+    /// Sets both the allowed and denied permissions of the role, replacing any previous ones.
+    pub const fn permissions(mut self, permissions: PermissionPair) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
+    /// Sets whether the role is hoisted, meaning members with the role are displayed separately
+    /// in the member list. Defaults to `false`.
+    pub const fn hoist(mut self, hoist: bool) -> Self {
+        self.hoist = hoist;
+        self
+    }
+
+    /// Sets whether anyone can mention the role. Defaults to `false`.
+    pub const fn mentionable(mut self, mentionable: bool) -> Self {
+        self.mentionable = mentionable;
+        self
+    }
+
+    /// Validates the role and converts it into a raw [`CreateRolePayload`].
+    pub fn build(self) -> Result<CreateRolePayload, ValidationError> {
+        let (min, max) = Self::NAME_LENGTH;
+        check_range("name", self.name.chars().count(), min, max)?;
+        if let Some(color) = self.color {
+            check_range("color", color as usize, 0, Self::MAX_COLOR as usize)?;
+        }
+
+        Ok(CreateRolePayload {
+            name: self.name,
+            color: self.color,
+            icon: self.icon.map(ImageData::into_string),
+            permissions: self.permissions,
+            hoisted: self.hoist,
+            mentionable: self.mentionable,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allow_and_deny_are_exclusive() {
+        let payload = RoleBuilder::new("role")
+            .allow(Permissions::KICK_MEMBERS | Permissions::BAN_MEMBERS)
+            .deny(Permissions::BAN_MEMBERS)
+            .build()
+            .unwrap();
+
+        assert_eq!(payload.permissions.allow, Permissions::KICK_MEMBERS);
+        assert_eq!(payload.permissions.deny, Permissions::BAN_MEMBERS);
+    }
+}
@@ -0,0 +1,271 @@
+use crate::models::{
+    Channel, ChannelId, DmChannel, Guild, GuildId, Member, Role, RoleId, User, UserId,
+};
+use futures_util::future::BoxFuture;
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug},
+    hash::Hash,
+    sync::RwLock,
+};
+
+/// A store for the models held by the [`Cache`](super::Cache).
+///
+/// The cache resolves gateway events into calls to these methods, so implementations only need to
+/// store and retrieve models, for example in an external key-value store. The default backend is
+/// [`InMemoryBackend`]. Custom backends are set with
+/// [`ClientOptions::cache_backend`](crate::client::ClientOptions::cache_backend).
+///
+/// The methods return futures so that implementations can make requests without blocking the
+/// task receiving events, for example through an asynchronous client. Since events are applied to
+/// the cache before they are dispatched, implementations should still complete quickly.
+pub trait CacheBackend: Send + Sync {
+    /// Returns the guild with the given ID.
+    fn guild(&self, guild_id: GuildId) -> BoxFuture<'_, Option<Guild>>;
+    /// Returns all stored guilds.
+    fn guilds(&self) -> BoxFuture<'_, Vec<Guild>>;
+    /// Stores the given guild, replacing any guild with the same ID. Its channels, members, and
+    /// roles are always `None`, as they are stored separately.
+    fn insert_guild(&self, guild: Guild) -> BoxFuture<'_, ()>;
+    /// Removes the guild with the given ID, returning it if it was stored.
+    fn remove_guild(&self, guild_id: GuildId) -> BoxFuture<'_, Option<Guild>>;
+
+    /// Returns the guild channel with the given ID.
+    fn channel(&self, channel_id: ChannelId) -> BoxFuture<'_, Option<Channel>>;
+    /// Returns the stored channels of the given guild.
+    fn guild_channels(&self, guild_id: GuildId) -> BoxFuture<'_, Vec<Channel>>;
+    /// Stores the given guild channel, replacing any channel with the same ID.
+    fn insert_channel(&self, channel: Channel) -> BoxFuture<'_, ()>;
+    /// Removes the guild channel with the given ID, returning it if it was stored.
+    fn remove_channel(&self, channel_id: ChannelId) -> BoxFuture<'_, Option<Channel>>;
+
+    /// Returns the DM or group DM channel with the given ID.
+    fn dm_channel(&self, channel_id: ChannelId) -> BoxFuture<'_, Option<DmChannel>>;
+    /// Returns all stored DM and group DM channels.
+    fn dm_channels(&self) -> BoxFuture<'_, Vec<DmChannel>>;
+    /// Stores the given DM or group DM channel, replacing any channel with the same ID.
+    fn insert_dm_channel(&self, channel: DmChannel) -> BoxFuture<'_, ()>;
+    /// Removes the DM or group DM channel with the given ID, returning it if it was stored.
+    fn remove_dm_channel(&self, channel_id: ChannelId) -> BoxFuture<'_, Option<DmChannel>>;
+
+    /// Returns the user with the given ID.
+    fn user(&self, user_id: UserId) -> BoxFuture<'_, Option<User>>;
+    /// Stores the given user, replacing any user with the same ID.
+    fn insert_user(&self, user: User) -> BoxFuture<'_, ()>;
+    /// Removes the user with the given ID, returning it if it was stored.
+    fn remove_user(&self, user_id: UserId) -> BoxFuture<'_, Option<User>>;
+
+    /// Returns the member of the given guild with the given user ID.
+    fn member(&self, guild_id: GuildId, user_id: UserId) -> BoxFuture<'_, Option<Member>>;
+    /// Returns the stored members of the given guild.
+    fn members(&self, guild_id: GuildId) -> BoxFuture<'_, Vec<Member>>;
+    /// Stores the given member, replacing any member of the same guild with the same user ID.
+    fn insert_member(&self, member: Member) -> BoxFuture<'_, ()>;
+    /// Removes the member of the given guild with the given user ID, returning it if it was
+    /// stored.
+    fn remove_member(&self, guild_id: GuildId, user_id: UserId) -> BoxFuture<'_, Option<Member>>;
+
+    /// Returns the role with the given ID.
+    fn role(&self, role_id: RoleId) -> BoxFuture<'_, Option<Role>>;
+    /// Returns the stored roles of the given guild.
+    fn roles(&self, guild_id: GuildId) -> BoxFuture<'_, Vec<Role>>;
+    /// Stores the given role, replacing any role with the same ID.
+    fn insert_role(&self, role: Role) -> BoxFuture<'_, ()>;
+    /// Removes the role with the given ID, returning it if it was stored.
+    fn remove_role(&self, role_id: RoleId) -> BoxFuture<'_, Option<Role>>;
+
+    /// Removes all stored models. This is called when the client becomes ready, before the state
+    /// sent by the gateway is stored, so that models removed while the client was disconnected
+    /// are not kept.
+    fn clear(&self) -> BoxFuture<'_, ()>;
+}
+
+/// Wraps a value which is available immediately into a future.
+fn ready<T: Send + 'static>(value: T) -> BoxFuture<'static, T> {
+    Box::pin(std::future::ready(value))
+}
+
+/// A map of cached models, keyed by ID.
+struct Store<K, V>(RwLock<HashMap<K, V>>);
+
+impl<K: Eq + Hash, V: Clone> Store<K, V> {
+    fn get(&self, key: &K) -> Option<V> {
+        self.0.read().expect("poison").get(key).cloned()
+    }
+
+    fn insert(&self, key: K, value: V) {
+        self.0.write().expect("poison").insert(key, value);
+    }
+
+    fn remove(&self, key: &K) -> Option<V> {
+        self.0.write().expect("poison").remove(key)
+    }
+
+    fn filter(&self, f: impl Fn(&K, &V) -> bool) -> Vec<V> {
+        let map = self.0.read().expect("poison");
+        map.iter()
+            .filter(|(key, value)| f(key, value))
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.0.read().expect("poison").len()
+    }
+
+    fn clear(&self) {
+        self.0.write().expect("poison").clear();
+    }
+}
+
+impl<K, V> Default for Store<K, V> {
+    fn default() -> Self {
+        Self(RwLock::default())
+    }
+}
+
+/// The default [`CacheBackend`], which stores models in hash maps in memory.
+#[derive(Default)]
+pub struct InMemoryBackend {
+    guilds: Store<GuildId, Guild>,
+    channels: Store<ChannelId, Channel>,
+    dm_channels: Store<ChannelId, DmChannel>,
+    users: Store<UserId, User>,
+    members: Store<(GuildId, UserId), Member>,
+    roles: Store<RoleId, Role>,
+}
+
+impl InMemoryBackend {
+    /// Creates a new, empty in-memory backend.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CacheBackend for InMemoryBackend {
+    fn guild(&self, guild_id: GuildId) -> BoxFuture<'_, Option<Guild>> {
+        ready(self.guilds.get(&guild_id))
+    }
+
+    fn guilds(&self) -> BoxFuture<'_, Vec<Guild>> {
+        ready(self.guilds.filter(|_, _| true))
+    }
+
+    fn insert_guild(&self, guild: Guild) -> BoxFuture<'_, ()> {
+        self.guilds.insert(guild.id, guild);
+        ready(())
+    }
+
+    fn remove_guild(&self, guild_id: GuildId) -> BoxFuture<'_, Option<Guild>> {
+        ready(self.guilds.remove(&guild_id))
+    }
+
+    fn channel(&self, channel_id: ChannelId) -> BoxFuture<'_, Option<Channel>> {
+        ready(self.channels.get(&channel_id))
+    }
+
+    fn guild_channels(&self, guild_id: GuildId) -> BoxFuture<'_, Vec<Channel>> {
+        ready(
+            self.channels
+                .filter(|_, channel| channel.guild_id == Some(guild_id)),
+        )
+    }
+
+    fn insert_channel(&self, channel: Channel) -> BoxFuture<'_, ()> {
+        self.channels.insert(channel.id, channel);
+        ready(())
+    }
+
+    fn remove_channel(&self, channel_id: ChannelId) -> BoxFuture<'_, Option<Channel>> {
+        ready(self.channels.remove(&channel_id))
+    }
+
+    fn dm_channel(&self, channel_id: ChannelId) -> BoxFuture<'_, Option<DmChannel>> {
+        ready(self.dm_channels.get(&channel_id))
+    }
+
+    fn dm_channels(&self) -> BoxFuture<'_, Vec<DmChannel>> {
+        ready(self.dm_channels.filter(|_, _| true))
+    }
+
+    fn insert_dm_channel(&self, channel: DmChannel) -> BoxFuture<'_, ()> {
+        self.dm_channels.insert(channel.id, channel);
+        ready(())
+    }
+
+    fn remove_dm_channel(&self, channel_id: ChannelId) -> BoxFuture<'_, Option<DmChannel>> {
+        ready(self.dm_channels.remove(&channel_id))
+    }
+
+    fn user(&self, user_id: UserId) -> BoxFuture<'_, Option<User>> {
+        ready(self.users.get(&user_id))
+    }
+
+    fn insert_user(&self, user: User) -> BoxFuture<'_, ()> {
+        self.users.insert(user.id, user);
+        ready(())
+    }
+
+    fn remove_user(&self, user_id: UserId) -> BoxFuture<'_, Option<User>> {
+        ready(self.users.remove(&user_id))
+    }
+
+    fn member(&self, guild_id: GuildId, user_id: UserId) -> BoxFuture<'_, Option<Member>> {
+        ready(self.members.get(&(guild_id, user_id)))
+    }
+
+    fn members(&self, guild_id: GuildId) -> BoxFuture<'_, Vec<Member>> {
+        ready(self.members.filter(|(id, _), _| *id == guild_id))
+    }
+
+    fn insert_member(&self, member: Member) -> BoxFuture<'_, ()> {
+        self.members
+            .insert((member.guild_id(), member.id()), member);
+        ready(())
+    }
+
+    fn remove_member(&self, guild_id: GuildId, user_id: UserId) -> BoxFuture<'_, Option<Member>> {
+        ready(self.members.remove(&(guild_id, user_id)))
+    }
+
+    fn role(&self, role_id: RoleId) -> BoxFuture<'_, Option<Role>> {
+        ready(self.roles.get(&role_id))
+    }
+
+    fn roles(&self, guild_id: GuildId) -> BoxFuture<'_, Vec<Role>> {
+        ready(self.roles.filter(|_, role| role.guild_id == guild_id))
+    }
+
+    fn insert_role(&self, role: Role) -> BoxFuture<'_, ()> {
+        self.roles.insert(role.id, role);
+        ready(())
+    }
+
+    fn remove_role(&self, role_id: RoleId) -> BoxFuture<'_, Option<Role>> {
+        ready(self.roles.remove(&role_id))
+    }
+
+    fn clear(&self) -> BoxFuture<'_, ()> {
+        self.guilds.clear();
+        self.channels.clear();
+        self.dm_channels.clear();
+        self.users.clear();
+        self.members.clear();
+        self.roles.clear();
+        ready(())
+    }
+}
+
+impl Debug for InMemoryBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InMemoryBackend")
+            .field("guilds", &self.guilds.len())
+            .field("channels", &self.channels.len())
+            .field("dm_channels", &self.dm_channels.len())
+            .field("users", &self.users.len())
+            .field("members", &self.members.len())
+            .field("roles", &self.roles.len())
+            .finish()
+    }
+}
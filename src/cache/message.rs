@@ -0,0 +1,135 @@
+use crate::models::{ChannelId, Message, MessageId};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+
+type Key = (ChannelId, MessageId);
+
+#[derive(Debug, Default)]
+struct Entries {
+    /// Cached messages along with the tick at which they were last used.
+    messages: HashMap<Key, (u64, Message)>,
+    /// Keys by the tick at which they were last used, used to evict the least recently used
+    /// message first.
+    order: BTreeMap<u64, Key>,
+    tick: u64,
+}
+
+impl Entries {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+/// Holds up to a fixed number of messages, evicting the least recently used message once full.
+#[derive(Debug)]
+pub(crate) struct MessageCache {
+    entries: Mutex<Entries>,
+    capacity: usize,
+}
+
+impl MessageCache {
+    /// Creates a new cache holding at most `capacity` messages. A capacity of `0` disables the
+    /// cache.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::default(),
+            capacity,
+        }
+    }
+
+    /// Returns the message with the given key, marking it as recently used.
+    pub fn get(&self, channel_id: ChannelId, message_id: MessageId) -> Option<Message> {
+        let key = (channel_id, message_id);
+        let mut entries = self.entries.lock().expect("poison");
+        let tick = entries.next_tick();
+        let (used, message) = entries.messages.get_mut(&key)?;
+        let previous = std::mem::replace(used, tick);
+        let message = message.clone();
+        entries.order.remove(&previous);
+        entries.order.insert(tick, key);
+        Some(message)
+    }
+
+    /// Caches the given message, returning the message it replaces, if any.
+    pub fn insert(&self, message: Message) -> Option<Message> {
+        if self.capacity == 0 {
+            return None;
+        }
+
+        let key = (message.channel_id, message.id);
+        let mut entries = self.entries.lock().expect("poison");
+        let tick = entries.next_tick();
+        let previous = entries.messages.insert(key, (tick, message));
+        if let Some((used, _)) = &previous {
+            entries.order.remove(used);
+        }
+        entries.order.insert(tick, key);
+
+        while entries.messages.len() > self.capacity {
+            let Some((_, oldest)) = entries.order.pop_first() else {
+                break;
+            };
+            entries.messages.remove(&oldest);
+        }
+        previous.map(|(_, message)| message)
+    }
+
+    /// Removes the message with the given key, returning it if it was cached.
+    pub fn remove(&self, channel_id: ChannelId, message_id: MessageId) -> Option<Message> {
+        let mut entries = self.entries.lock().expect("poison");
+        let (used, message) = entries.messages.remove(&(channel_id, message_id))?;
+        entries.order.remove(&used);
+        Some(message)
+    }
+
+    /// Returns the number of cached messages.
+    pub fn len(&self) -> usize {
+        self.entries.lock().expect("poison").messages.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: u64) -> Message {
+        Message::from_raw(essence::models::Message {
+            id,
+            channel_id: 1,
+            author_id: None,
+            author: None,
+            content: Some(id.to_string()),
+            embeds: Vec::new(),
+            attachments: Vec::new(),
+            flags: 0,
+            mentions: Vec::new(),
+            edited_at: None,
+        })
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let cache = MessageCache::new(2);
+        let channel_id = ChannelId::from(1);
+        cache.insert(message(1));
+        cache.insert(message(2));
+
+        // Using the first message makes the second one the least recently used
+        assert!(cache.get(channel_id, MessageId::from(1)).is_some());
+        cache.insert(message(3));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(channel_id, MessageId::from(2)).is_none());
+
+        let replaced = cache.insert(message(1)).unwrap();
+        assert_eq!(replaced.content, "1");
+        assert!(cache.remove(channel_id, MessageId::from(3)).is_some());
+        assert_eq!(cache.len(), 1);
+
+        let disabled = MessageCache::new(0);
+        disabled.insert(message(1));
+        assert_eq!(disabled.len(), 0);
+    }
+}
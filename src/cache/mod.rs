@@ -0,0 +1,733 @@
+//! A cache of models received over the gateway.
+//!
+//! The cache is populated when the client becomes ready, replacing anything cached before, and is
+//! kept up to date by the events dispatched afterwards, so that lookups of guilds, channels,
+//! users, members, roles, and presences do not require a request to the REST API. It is accessed
+//! through [`Context::cache`].
+//!
+//! Every event is applied to the cache before it is dispatched to any event handler, so handlers
+//! always observe the state after the event. The previous state is passed to the handler instead
+//! where it is known, for example in [`Event::MessageUpdate`](crate::ws::Event::MessageUpdate).
+//!
+//! Only models the client has received over the gateway are cached. Lookups return `None` for
+//! anything else, in which case the model must be fetched over HTTP.
+//!
+//! Which models are cached is configured with [`CacheSettings`]; messages are only cached if a
+//! capacity is set with [`CacheSettings::messages`]. Messages, presences, and unread channels are
+//! always kept in memory, while other models are kept in memory by default.
+//! Implement [`CacheBackend`] to store them elsewhere, or enable the `redis` feature to share them
+//! between processes through Redis.
+//!
+//! [`Context::cache`]: crate::Context::cache
+
+mod backend;
+mod message;
+#[cfg(feature = "redis")]
+mod redis;
+mod settings;
+mod unread;
+
+#[cfg(feature = "redis")]
+pub use self::redis::{ModelKind, RedisBackend};
+pub use backend::{CacheBackend, InMemoryBackend};
+pub use settings::CacheSettings;
+pub use unread::ChannelUnread;
+
+use crate::models::{
+    Channel, ChannelId, DmChannel, Guild, GuildId, Id, Member, Message, MessageId, Role, RoleId,
+    User, UserId,
+};
+use essence::models::Presence;
+use message::MessageCache;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Debug},
+    sync::{Arc, RwLock},
+};
+use unread::Unreads;
+
+/// Stores guilds, channels, users, members, and roles received over the gateway in a
+/// [`CacheBackend`].
+///
+/// Lookups return clones of the cached models. Guilds are cached without their channels, members,
+/// and roles; use [`Self::guild_channels`], [`Self::members`], and [`Self::roles`] for those.
+pub struct Cache {
+    settings: CacheSettings,
+    current_user: RwLock<Option<User>>,
+    backend: Arc<dyn CacheBackend>,
+    messages: MessageCache,
+    presences: RwLock<HashMap<UserId, Presence>>,
+    unreads: Unreads,
+    /// The ID of the last message received in every known DM channel, if any. This is kept in
+    /// memory rather than written to the backend for every message, and applied when DM channels
+    /// are read.
+    dm_last_messages: RwLock<HashMap<ChannelId, Option<MessageId>>>,
+    /// Roles deleted since the client became ready. They are removed from members when members
+    /// are read, rather than rewriting every member of the guild.
+    deleted_roles: RwLock<HashSet<RoleId>>,
+}
+
+/// The cached state of a model which was replaced or removed by a gateway event, used to resolve
+/// the previous state of the model in the dispatched event.
+#[cfg(feature = "ws")]
+#[derive(Debug, Default)]
+pub(crate) enum Replaced {
+    /// Nothing was replaced, or the replaced model was not cached.
+    #[default]
+    None,
+    /// A message was edited or deleted.
+    Message(Message),
+}
+
+impl Cache {
+    /// Creates a new, empty cache which stores models in memory.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_backend(Arc::new(InMemoryBackend::new()))
+    }
+
+    /// Creates a new cache which stores models in the given backend.
+    #[must_use]
+    pub fn with_backend(backend: Arc<dyn CacheBackend>) -> Self {
+        Self {
+            settings: CacheSettings::new(),
+            current_user: RwLock::default(),
+            backend,
+            messages: MessageCache::new(0),
+            presences: RwLock::default(),
+            unreads: Unreads::default(),
+            dm_last_messages: RwLock::default(),
+            deleted_roles: RwLock::default(),
+        }
+    }
+
+    /// Sets which kinds of models are cached. By default, everything except messages is cached.
+    #[must_use]
+    pub fn with_settings(mut self, settings: CacheSettings) -> Self {
+        self.messages = MessageCache::new(settings.message_capacity);
+        self.settings = settings;
+        self
+    }
+
+    /// Returns the settings selecting which kinds of models are cached.
+    #[must_use]
+    pub const fn settings(&self) -> CacheSettings {
+        self.settings
+    }
+
+    /// Returns the backend in which models are stored.
+    #[must_use]
+    pub fn backend(&self) -> &Arc<dyn CacheBackend> {
+        &self.backend
+    }
+
+    /// Returns the user the client is logged in as, once the client is ready.
+    #[must_use]
+    pub fn current_user(&self) -> Option<User> {
+        self.current_user.read().expect("poison").clone()
+    }
+
+    /// Returns the guild with the given ID.
+    #[must_use]
+    pub async fn guild(&self, guild_id: GuildId) -> Option<Guild> {
+        self.backend.guild(guild_id).await
+    }
+
+    /// Returns all cached guilds.
+    #[must_use]
+    pub async fn guilds(&self) -> Vec<Guild> {
+        self.backend.guilds().await
+    }
+
+    /// Returns the channel with the given ID. This includes DM channels, see
+    /// [`Self::dm_channel`] to retrieve them with their recipients.
+    #[must_use]
+    pub async fn channel(&self, channel_id: ChannelId) -> Option<Channel> {
+        match self.backend.channel(channel_id).await {
+            Some(channel) => Some(channel),
+            None => self.dm_channel(channel_id).await.map(Into::into),
+        }
+    }
+
+    /// Returns the channels of the given guild, in no particular order.
+    #[must_use]
+    pub async fn guild_channels(&self, guild_id: GuildId) -> Vec<Channel> {
+        self.backend.guild_channels(guild_id).await
+    }
+
+    /// Returns the DM or group DM channel with the given ID.
+    #[must_use]
+    pub async fn dm_channel(&self, channel_id: ChannelId) -> Option<DmChannel> {
+        let channel = self.backend.dm_channel(channel_id).await?;
+        Some(self.with_last_message(channel))
+    }
+
+    /// Returns all DM and group DM channels of the client user, in no particular order.
+    #[must_use]
+    pub async fn dm_channels(&self) -> Vec<DmChannel> {
+        let channels = self.backend.dm_channels().await;
+        channels
+            .into_iter()
+            .map(|channel| self.with_last_message(channel))
+            .collect()
+    }
+
+    /// Returns the DM channel between the client user and the given user, if one is cached.
+    ///
+    /// Before the client user is known, any cached DM channel including the given user is
+    /// returned.
+    #[must_use]
+    pub async fn dm_channel_with(&self, user_id: UserId) -> Option<DmChannel> {
+        let current_user_id = self.current_user().map(|user| user.id);
+        self.dm_channels()
+            .await
+            .into_iter()
+            .find(|channel| match current_user_id {
+                Some(current_user_id) => channel.recipient(current_user_id) == Some(user_id),
+                None => !channel.is_group() && channel.recipient_ids.contains(&user_id),
+            })
+    }
+
+    /// Returns the message in the given channel with the given ID, if it is still cached. See
+    /// [`CacheSettings::messages`].
+    #[must_use]
+    pub fn message(&self, channel_id: ChannelId, message_id: MessageId) -> Option<Message> {
+        self.messages.get(channel_id, message_id)
+    }
+
+    /// Returns the last known presence of the user with the given ID.
+    #[must_use]
+    pub fn presence(&self, user_id: UserId) -> Option<Presence> {
+        self.presences
+            .read()
+            .expect("poison")
+            .get(&user_id)
+            .cloned()
+    }
+
+    /// Returns the unread state of the given channel, or `None` if it has no unread messages.
+    #[must_use]
+    pub fn unread(&self, channel_id: ChannelId) -> Option<ChannelUnread> {
+        self.unreads.get(channel_id)
+    }
+
+    /// Returns the unread state of every channel with unread messages, in no particular order.
+    ///
+    /// Unread channels are sent by the gateway when the client becomes ready, and are updated as
+    /// messages are received and acknowledged afterwards.
+    #[must_use]
+    pub fn unreads(&self) -> Vec<ChannelUnread> {
+        self.unreads.list()
+    }
+
+    /// Returns the user with the given ID.
+    #[must_use]
+    pub async fn user(&self, user_id: UserId) -> Option<User> {
+        self.backend.user(user_id).await
+    }
+
+    /// Returns the member of the given guild with the given user ID.
+    #[must_use]
+    pub async fn member(&self, guild_id: GuildId, user_id: UserId) -> Option<Member> {
+        let member = self.backend.member(guild_id, user_id).await?;
+        Some(self.without_deleted_roles(member))
+    }
+
+    /// Returns the cached members of the given guild, in no particular order.
+    #[must_use]
+    pub async fn members(&self, guild_id: GuildId) -> Vec<Member> {
+        let members = self.backend.members(guild_id).await;
+        members
+            .into_iter()
+            .map(|member| self.without_deleted_roles(member))
+            .collect()
+    }
+
+    /// Returns the role with the given ID.
+    #[must_use]
+    pub async fn role(&self, role_id: RoleId) -> Option<Role> {
+        self.backend.role(role_id).await
+    }
+
+    /// Returns the roles of the given guild, in no particular order.
+    #[must_use]
+    pub async fn roles(&self, guild_id: GuildId) -> Vec<Role> {
+        self.backend.roles(guild_id).await
+    }
+
+    /// Applies the last message received in the given DM channel, if it is newer than the stored
+    /// one.
+    fn with_last_message(&self, mut channel: DmChannel) -> DmChannel {
+        let last_messages = self.dm_last_messages.read().expect("poison");
+        if let Some(last_message_id) = last_messages.get(&channel.id).copied().flatten() {
+            channel.last_message_id = channel.last_message_id.max(Some(last_message_id));
+        }
+        channel
+    }
+
+    fn without_deleted_roles(&self, mut member: Member) -> Member {
+        let deleted = self.deleted_roles.read().expect("poison");
+        if !deleted.is_empty() {
+            member.roles.retain(|role_id| !deleted.contains(role_id));
+        }
+        member
+    }
+
+    async fn set_current_user(&self, user: User) {
+        if self.settings.users {
+            self.backend.insert_user(user.clone()).await;
+        }
+        *self.current_user.write().expect("poison") = Some(user);
+    }
+
+    /// Caches the given guild along with its channels, members, and roles, if included.
+    pub(crate) async fn insert_guild(&self, mut guild: Guild) {
+        let channels = guild.channels.take().unwrap_or_default();
+        if self.settings.channels {
+            for channel in channels {
+                self.backend.insert_channel(channel).await;
+            }
+        }
+        let members = guild.members.take().unwrap_or_default();
+        if self.settings.members {
+            for member in members {
+                self.backend.insert_member(member).await;
+            }
+        }
+        let roles = guild.roles.take().unwrap_or_default();
+        if self.settings.roles {
+            for role in roles {
+                self.backend.insert_role(role).await;
+            }
+        }
+        if self.settings.guilds {
+            self.backend.insert_guild(guild).await;
+        }
+    }
+
+    /// Removes the given guild along with its channels, members, and roles.
+    async fn remove_guild(&self, guild_id: GuildId) {
+        if self.settings.guilds {
+            self.backend.remove_guild(guild_id).await;
+        }
+        if self.settings.channels {
+            for channel in self.backend.guild_channels(guild_id).await {
+                self.backend.remove_channel(channel.id).await;
+                self.unreads.remove(channel.id);
+            }
+        }
+        if self.settings.members {
+            for member in self.backend.members(guild_id).await {
+                self.backend.remove_member(guild_id, member.id()).await;
+            }
+        }
+        if self.settings.roles {
+            for role in self.backend.roles(guild_id).await {
+                self.backend.remove_role(role.id).await;
+            }
+        }
+    }
+
+    async fn insert_channel(&self, channel: essence::models::Channel) {
+        if let essence::models::Channel::Dm(channel) = channel {
+            self.insert_dm_channel(DmChannel::from_raw(channel)).await;
+        } else {
+            self.insert_guild_channel(Channel::from_raw(channel)).await;
+        }
+    }
+
+    /// Caches the given guild channel. DM channels are only cached through
+    /// [`Self::insert_dm_channel`], since they are stored along with their recipients.
+    pub(crate) async fn insert_guild_channel(&self, channel: Channel) {
+        if self.settings.channels && channel.guild_id.is_some() {
+            self.backend.insert_channel(channel).await;
+        }
+    }
+
+    /// Caches the given DM or group DM channel.
+    pub(crate) async fn insert_dm_channel(&self, channel: DmChannel) {
+        if !self.settings.channels {
+            return;
+        }
+        self.dm_last_messages
+            .write()
+            .expect("poison")
+            .entry(channel.id)
+            .or_default();
+        self.backend.insert_dm_channel(channel).await;
+    }
+
+    fn insert_presence(&self, presence: Presence) {
+        if !self.settings.presences {
+            return;
+        }
+        self.presences
+            .write()
+            .expect("poison")
+            .insert(UserId::from(presence.user_id), presence);
+    }
+
+    /// Removes the given role. Members it was assigned to no longer include it once they are read.
+    async fn remove_role(&self, role_id: RoleId) {
+        if self.settings.roles {
+            self.backend.remove_role(role_id).await;
+        }
+        if self.settings.members {
+            self.deleted_roles.write().expect("poison").insert(role_id);
+        }
+    }
+
+    async fn insert_member(&self, member: &essence::models::Member) {
+        if let essence::models::MaybePartialUser::Full(user) = &member.user {
+            self.insert_user(User::from_raw(user.clone())).await;
+        }
+        if self.settings.members {
+            let member = Member::from_raw(member.clone());
+            self.backend.insert_member(member).await;
+        }
+    }
+
+    pub(crate) async fn insert_user(&self, user: User) {
+        if let Some(current_user) = self
+            .current_user
+            .write()
+            .expect("poison")
+            .as_mut()
+            .filter(|current| current.id == user.id)
+        {
+            *current_user = user.clone();
+        }
+        if self.settings.users {
+            self.backend.insert_user(user).await;
+        }
+    }
+
+    /// Applies the given gateway event to the cache, returning the cached state it replaced.
+    #[cfg(feature = "ws")]
+    pub(crate) async fn apply(&self, event: &crate::ws::InboundMessage) -> Replaced {
+        use crate::ws::InboundMessage;
+
+        match event {
+            InboundMessage::Ready {
+                user,
+                guilds,
+                dm_channels,
+                presences,
+                relationships,
+                unacked,
+                ..
+            } => {
+                // Models may have been removed while the client was disconnected, so only keep
+                // the state sent by the gateway
+                self.backend.clear().await;
+                self.presences.write().expect("poison").clear();
+                self.dm_last_messages.write().expect("poison").clear();
+                self.deleted_roles.write().expect("poison").clear();
+                self.set_current_user(User::from_raw(user.user.clone()))
+                    .await;
+                for guild in guilds {
+                    self.insert_guild(Guild::from_raw(guild.clone())).await;
+                }
+                for channel in dm_channels {
+                    self.insert_channel(essence::models::Channel::Dm(channel.clone()))
+                        .await;
+                }
+                for relationship in relationships {
+                    self.insert_user(User::from_raw(relationship.user.clone()))
+                        .await;
+                }
+                for presence in presences {
+                    self.insert_presence(presence.clone());
+                }
+                if self.settings.unreads {
+                    self.unreads
+                        .reset(unacked.iter().map(|channel| ChannelUnread {
+                            channel_id: channel.channel_id.into(),
+                            last_acked_message_id: channel.last_message_id.map(Into::into),
+                            last_message_id: None,
+                            unread_count: 0,
+                            mentions: channel.mentions.iter().copied().map(Into::into).collect(),
+                        }));
+                }
+            }
+            InboundMessage::UserUpdate { after, .. } => {
+                self.insert_user(User::from_raw(after.clone())).await;
+            }
+            InboundMessage::UserDelete { user_id } => {
+                let user_id = UserId::from(*user_id);
+                if self.settings.users {
+                    self.backend.remove_user(user_id).await;
+                }
+                self.presences.write().expect("poison").remove(&user_id);
+            }
+            InboundMessage::PresenceUpdate { presence } => self.insert_presence(presence.clone()),
+            InboundMessage::ChannelCreate { channel, .. }
+            | InboundMessage::ChannelUpdate { after: channel, .. } => {
+                self.insert_channel(channel.clone()).await;
+            }
+            InboundMessage::ChannelDelete { channel_id } => {
+                let channel_id = ChannelId::from(*channel_id);
+                if self.settings.channels {
+                    self.backend.remove_channel(channel_id).await;
+                    self.backend.remove_dm_channel(channel_id).await;
+                    self.dm_last_messages
+                        .write()
+                        .expect("poison")
+                        .remove(&channel_id);
+                }
+                self.unreads.remove(channel_id);
+            }
+            InboundMessage::ChannelAck {
+                channel_id,
+                last_message_id,
+            } => {
+                self.unreads.ack(
+                    ChannelId::from(*channel_id),
+                    MessageId::from(*last_message_id),
+                );
+            }
+            InboundMessage::GuildCreate { guild, .. } => {
+                self.insert_guild(Guild::from_raw(guild.clone())).await
+            }
+            InboundMessage::GuildUpdate { after, .. } if self.settings.guilds => {
+                let guild = Guild::from_raw_partial(after.clone());
+                self.backend.insert_guild(guild).await;
+            }
+            InboundMessage::GuildRemove { guild_id } => {
+                self.remove_guild(GuildId::from(*guild_id)).await;
+            }
+            InboundMessage::MemberJoin { member, .. }
+            | InboundMessage::MemberUpdate { after: member, .. } => {
+                self.insert_member(member).await
+            }
+            InboundMessage::MemberRemove { guild_id, user_id } if self.settings.members => {
+                let (guild_id, user_id) = (GuildId::from(*guild_id), UserId::from(*user_id));
+                self.backend.remove_member(guild_id, user_id).await;
+            }
+            InboundMessage::RoleCreate { role }
+            | InboundMessage::RoleUpdate { after: role, .. }
+                if self.settings.roles =>
+            {
+                self.backend.insert_role(Role::from_raw(role.clone())).await;
+            }
+            InboundMessage::RoleDelete { role_id, .. } => {
+                self.remove_role(RoleId::from(*role_id)).await;
+            }
+            InboundMessage::MessageCreate { message, nonce } => {
+                if let Some(author) = &message.author {
+                    self.insert_user(User::from_raw(author.clone())).await;
+                }
+                if self.settings.unreads {
+                    if let Some(user_id) = self.current_user().map(|user| user.id.get()) {
+                        if message.author_id != Some(user_id) {
+                            self.unreads.record_message(
+                                ChannelId::from(message.channel_id),
+                                MessageId::from(message.id),
+                                message.mentions.contains(&user_id),
+                            );
+                        }
+                    }
+                }
+                let mut message = Message::from_raw(message.clone());
+                // Only known DM channels have an entry
+                if let Some(last_message_id) = self
+                    .dm_last_messages
+                    .write()
+                    .expect("poison")
+                    .get_mut(&message.channel_id)
+                {
+                    *last_message_id = Some(message.id);
+                }
+                message.nonce.clone_from(nonce);
+                self.messages.insert(message);
+            }
+            InboundMessage::MessageUpdate { after, .. } => {
+                if let Some(old) = self.messages.insert(Message::from_raw(after.clone())) {
+                    return Replaced::Message(old);
+                }
+            }
+            InboundMessage::MessageDelete {
+                message_id,
+                channel_id,
+            } => {
+                let channel_id = ChannelId::from(*channel_id);
+                if let Some(old) = self
+                    .messages
+                    .remove(channel_id, MessageId::from(*message_id))
+                {
+                    return Replaced::Message(old);
+                }
+            }
+            InboundMessage::RelationshipCreate { relationship } => {
+                self.insert_user(User::from_raw(relationship.user.clone()))
+                    .await;
+            }
+            _ => (),
+        }
+        Replaced::None
+    }
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for Cache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cache")
+            .field("current_user", &self.current_user())
+            .field("messages", &self.messages.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{PermissionPair, Permissions};
+
+    fn role(id: u64, guild_id: u64) -> Role {
+        Role {
+            id: RoleId::from(id),
+            guild_id: GuildId::from(guild_id),
+            name: String::new(),
+            color: None,
+            permissions: PermissionPair {
+                allow: Permissions::empty(),
+                deny: Permissions::empty(),
+            },
+            position: 0,
+        }
+    }
+
+    fn message(content: Option<&str>) -> essence::models::Message {
+        essence::models::Message {
+            id: 2,
+            channel_id: 1,
+            author_id: None,
+            author: None,
+            content: content.map(ToString::to_string),
+            embeds: Vec::new(),
+            attachments: Vec::new(),
+            flags: 0,
+            mentions: Vec::new(),
+            edited_at: None,
+        }
+    }
+
+    fn dm_channel(id: u64) -> DmChannel {
+        DmChannel {
+            id: ChannelId::from(id),
+            kind: essence::models::ChannelType::Dm,
+            name: None,
+            topic: None,
+            icon: None,
+            owner_id: None,
+            recipient_ids: Vec::new(),
+            last_message_id: None,
+        }
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "ws")]
+    async fn remove_guild() {
+        let backend = Arc::new(InMemoryBackend::new());
+        let cache = Cache::with_backend(backend.clone());
+        for (id, guild_id) in [(1, 1), (2, 1), (3, 2)] {
+            backend.insert_role(role(id, guild_id)).await;
+        }
+
+        cache
+            .apply(&crate::ws::InboundMessage::RoleDelete {
+                role_id: 2,
+                guild_id: 1,
+            })
+            .await;
+        assert_eq!(cache.roles(GuildId::from(1)).await.len(), 1);
+
+        cache
+            .apply(&crate::ws::InboundMessage::GuildRemove { guild_id: 1 })
+            .await;
+        assert!(cache.roles(GuildId::from(1)).await.is_empty());
+        assert!(cache.role(RoleId::from(3)).await.is_some());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "ws")]
+    async fn apply_message_events() {
+        use crate::ws::InboundMessage;
+
+        let cache = Cache::new().with_settings(CacheSettings::new().messages(10));
+        cache.insert_dm_channel(dm_channel(1)).await;
+
+        cache
+            .apply(&InboundMessage::MessageCreate {
+                message: message(Some("hello")),
+                nonce: None,
+            })
+            .await;
+        let channel = cache.dm_channel(ChannelId::from(1)).await.unwrap();
+        assert_eq!(channel.last_message_id, Some(MessageId::from(2)));
+
+        let replaced = cache
+            .apply(&InboundMessage::MessageUpdate {
+                before: message(Some("hello")),
+                after: message(Some("edited")),
+            })
+            .await;
+        assert!(matches!(replaced, Replaced::Message(old) if old.content == "hello"));
+
+        let replaced = cache
+            .apply(&InboundMessage::MessageDelete {
+                message_id: 2,
+                channel_id: 1,
+            })
+            .await;
+        assert!(matches!(replaced, Replaced::Message(old) if old.content == "edited"));
+        assert!(cache
+            .message(ChannelId::from(1), MessageId::from(2))
+            .is_none());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "ws")]
+    async fn skip_disabled_kinds() {
+        use crate::ws::InboundMessage;
+
+        let backend = Arc::new(InMemoryBackend::new());
+        let settings = CacheSettings::new().channels(false).unreads(false);
+        let cache = Cache::with_backend(backend.clone()).with_settings(settings);
+        cache
+            .set_current_user(User {
+                id: UserId::from(3),
+                username: String::new(),
+                display_name: None,
+                avatar: None,
+                banner: None,
+                bio: None,
+            })
+            .await;
+        backend.insert_dm_channel(dm_channel(1)).await;
+
+        cache
+            .apply(&InboundMessage::MessageCreate {
+                message: essence::models::Message {
+                    author_id: Some(4),
+                    mentions: vec![3],
+                    ..message(None)
+                },
+                nonce: None,
+            })
+            .await;
+        assert!(cache.unreads().is_empty());
+        let channel = cache.dm_channel(ChannelId::from(1)).await.unwrap();
+        assert_eq!(channel.last_message_id, None);
+
+        cache
+            .apply(&InboundMessage::ChannelDelete { channel_id: 1 })
+            .await;
+        assert!(cache.dm_channel(ChannelId::from(1)).await.is_some());
+    }
+}
@@ -0,0 +1,491 @@
+use super::CacheBackend;
+use crate::models::{
+    Channel, ChannelId, DmChannel, Guild, GuildId, Member, Role, RoleId, User, UserId,
+};
+use ::redis::{aio::ConnectionManager, Cmd, FromRedisValue, RedisResult};
+use futures_util::future::BoxFuture;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Display},
+    time::Duration,
+};
+
+/// A kind of model stored by the [`RedisBackend`], used to configure expiry per model type.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ModelKind {
+    /// A [`Guild`], without its channels, members, and roles.
+    Guild,
+    /// A guild [`Channel`].
+    Channel,
+    /// A [`DmChannel`].
+    DmChannel,
+    /// A [`User`].
+    User,
+    /// A guild [`Member`].
+    Member,
+    /// A guild [`Role`].
+    Role,
+}
+
+impl ModelKind {
+    /// The name of this kind of model in Redis keys.
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Guild => "guild",
+            Self::Channel => "channel",
+            Self::DmChannel => "dm_channel",
+            Self::User => "user",
+            Self::Member => "member",
+            Self::Role => "role",
+        }
+    }
+}
+
+/// A [`CacheBackend`] which stores models in Redis, so that multiple processes can share cached
+/// state.
+///
+/// Models are stored as JSON under keys of the form `{prefix}:{kind}:{id}`, for example
+/// `adapt:user:1234`. Members are keyed by both the guild and user ID. Sets of IDs are kept under
+/// `{prefix}:guilds`, `{prefix}:dm_channels`, and `{prefix}:guild:{id}:{channels|members|roles}`
+/// to look up the models of a guild.
+///
+/// Requests are made through an asynchronous [`ConnectionManager`], which reconnects
+/// automatically, so they never block the task receiving events from the gateway. Errors are
+/// logged and treated as cache misses. All keys with the prefix are deleted when the client
+/// becomes ready, so processes sharing a prefix must belong to the same client.
+///
+/// # Example
+/// ```no_run
+/// use adapt::cache::{ModelKind, RedisBackend};
+/// use adapt::client::ClientOptions;
+/// use redis::aio::ConnectionManager;
+/// use std::{sync::Arc, time::Duration};
+///
+/// # async fn run() -> redis::RedisResult<()> {
+/// let client = redis::Client::open("redis://127.0.0.1/")?;
+/// let backend = RedisBackend::new(ConnectionManager::new(client).await?)
+///     .prefix("my-bot")
+///     .ttl(ModelKind::User, Duration::from_secs(3600));
+/// let options = ClientOptions::new("token").cache_backend(Arc::new(backend));
+/// # Ok(()) }
+/// ```
+#[must_use]
+pub struct RedisBackend {
+    conn: ConnectionManager,
+    keys: Keys,
+    ttls: HashMap<ModelKind, Duration>,
+}
+
+/// Builds the keys under which models and indexes are stored.
+#[derive(Debug)]
+struct Keys {
+    prefix: String,
+}
+
+impl Keys {
+    fn key(&self, kind: ModelKind, id: impl Display) -> String {
+        format!("{}:{}:{id}", self.prefix, kind.as_str())
+    }
+
+    fn member_key(&self, guild_id: GuildId, user_id: UserId) -> String {
+        self.key(ModelKind::Member, format_args!("{guild_id}:{user_id}"))
+    }
+
+    fn guild_index(&self, guild_id: GuildId, kind: ModelKind) -> String {
+        format!("{}:guild:{guild_id}:{}s", self.prefix, kind.as_str())
+    }
+
+    fn global_index(&self, kind: ModelKind) -> String {
+        format!("{}:{}s", self.prefix, kind.as_str())
+    }
+}
+
+/// Logs the error of a failed request, which is then treated as a cache miss.
+fn log_err<T>(result: RedisResult<T>) -> Option<T> {
+    result
+        .map_err(|err| warn!("Redis cache request failed: {err}"))
+        .ok()
+}
+
+impl RedisBackend {
+    /// The number of keys deleted at a time when the cache is cleared.
+    const CLEAR_BATCH_SIZE: usize = 512;
+
+    /// Creates a new Redis backend which makes requests through the given connection manager.
+    /// Keys are prefixed with `adapt` and do not expire.
+    pub fn new(conn: ConnectionManager) -> Self {
+        Self {
+            conn,
+            keys: Keys {
+                prefix: "adapt".to_string(),
+            },
+            ttls: HashMap::new(),
+        }
+    }
+
+    /// Sets the prefix of all keys, so that multiple clients can share a Redis database.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.keys.prefix = prefix.into();
+        self
+    }
+
+    /// Expires models of the given kind after the given duration, counted from the last time the
+    /// model was stored. Models do not expire by default.
+    ///
+    /// Durations are rounded down to whole seconds, with a minimum of one second.
+    pub fn ttl(mut self, kind: ModelKind, ttl: Duration) -> Self {
+        self.ttls.insert(kind, ttl);
+        self
+    }
+
+    async fn query<T: FromRedisValue>(&self, cmd: &Cmd) -> Option<T> {
+        log_err(cmd.query_async(&mut self.conn.clone()).await)
+    }
+
+    fn decode<T: DeserializeOwned>(key: &str, value: &str) -> Option<T> {
+        serde_json::from_str(value)
+            .map_err(|err| warn!("Could not deserialize cached {key}: {err}"))
+            .ok()
+    }
+
+    async fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let value: Option<String> = self.query(::redis::cmd("GET").arg(key)).await?;
+        Self::decode(key, &value?)
+    }
+
+    /// Stores the given model under the given key, adding its ID to the given index, if any.
+    async fn set<T: Serialize>(
+        &self,
+        kind: ModelKind,
+        key: &str,
+        value: &T,
+        index: Option<(String, String)>,
+    ) {
+        let value = serde_json::to_string(value).expect("models should serialize");
+        let mut pipe = ::redis::pipe();
+        pipe.atomic();
+        let set = pipe.cmd("SET").arg(key).arg(value);
+        if let Some(ttl) = self.ttls.get(&kind) {
+            set.arg("EX").arg(ttl.as_secs().max(1));
+        }
+        set.ignore();
+        if let Some((index, id)) = index {
+            pipe.cmd("SADD").arg(index).arg(id).ignore();
+        }
+        log_err::<()>(pipe.query_async(&mut self.conn.clone()).await);
+    }
+
+    /// Removes the model stored under the given key, returning it if it was stored.
+    async fn take<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let (value,): (Option<String>,) = log_err(
+            ::redis::pipe()
+                .atomic()
+                .cmd("GET")
+                .arg(key)
+                .cmd("DEL")
+                .arg(key)
+                .ignore()
+                .query_async(&mut self.conn.clone())
+                .await,
+        )?;
+        Self::decode(key, &value?)
+    }
+
+    async fn unindex(&self, index: &str, id: impl Display) {
+        self.query::<()>(::redis::cmd("SREM").arg(index).arg(id.to_string()))
+            .await;
+    }
+
+    /// Returns all models whose IDs are in the given index. IDs of models which expired are
+    /// removed from the index.
+    async fn list<T: DeserializeOwned>(
+        &self,
+        index: &str,
+        key: impl Fn(&str) -> String + Send,
+    ) -> Vec<T> {
+        let Some(ids) = self
+            .query::<Vec<String>>(::redis::cmd("SMEMBERS").arg(index))
+            .await
+        else {
+            return Vec::new();
+        };
+        if ids.is_empty() {
+            return Vec::new();
+        }
+
+        let keys = ids.iter().map(|id| key(id)).collect::<Vec<_>>();
+        let Some(values) = self
+            .query::<Vec<Option<String>>>(::redis::cmd("MGET").arg(&keys))
+            .await
+        else {
+            return Vec::new();
+        };
+
+        let mut models = Vec::with_capacity(values.len());
+        for ((id, key), value) in ids.iter().zip(&keys).zip(values) {
+            match value {
+                Some(value) => models.extend(Self::decode(key, &value)),
+                None => self.unindex(index, id).await,
+            }
+        }
+        models
+    }
+
+    /// Deletes every key with the prefix of this backend.
+    async fn clear_keys(&self) -> RedisResult<()> {
+        let mut conn = self.conn.clone();
+        let pattern = format!("{}:*", self.keys.prefix);
+        let mut cursor = 0_u64;
+        loop {
+            let (next, keys): (u64, Vec<String>) = ::redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(Self::CLEAR_BATCH_SIZE)
+                .query_async(&mut conn)
+                .await?;
+            if !keys.is_empty() {
+                ::redis::cmd("DEL")
+                    .arg(&keys)
+                    .query_async::<_, ()>(&mut conn)
+                    .await?;
+            }
+            if next == 0 {
+                return Ok(());
+            }
+            cursor = next;
+        }
+    }
+}
+
+impl CacheBackend for RedisBackend {
+    fn guild(&self, guild_id: GuildId) -> BoxFuture<'_, Option<Guild>> {
+        Box::pin(async move { self.get(&self.keys.key(ModelKind::Guild, guild_id)).await })
+    }
+
+    fn guilds(&self) -> BoxFuture<'_, Vec<Guild>> {
+        Box::pin(async move {
+            self.list(&self.keys.global_index(ModelKind::Guild), |id| {
+                self.keys.key(ModelKind::Guild, id)
+            })
+            .await
+        })
+    }
+
+    fn insert_guild(&self, guild: Guild) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            let key = self.keys.key(ModelKind::Guild, guild.id);
+            let index = self.keys.global_index(ModelKind::Guild);
+            let index = Some((index, guild.id.to_string()));
+            self.set(ModelKind::Guild, &key, &guild, index).await;
+        })
+    }
+
+    fn remove_guild(&self, guild_id: GuildId) -> BoxFuture<'_, Option<Guild>> {
+        Box::pin(async move {
+            self.unindex(&self.keys.global_index(ModelKind::Guild), guild_id)
+                .await;
+            self.take(&self.keys.key(ModelKind::Guild, guild_id)).await
+        })
+    }
+
+    fn channel(&self, channel_id: ChannelId) -> BoxFuture<'_, Option<Channel>> {
+        Box::pin(async move {
+            self.get(&self.keys.key(ModelKind::Channel, channel_id))
+                .await
+        })
+    }
+
+    fn guild_channels(&self, guild_id: GuildId) -> BoxFuture<'_, Vec<Channel>> {
+        Box::pin(async move {
+            self.list(&self.keys.guild_index(guild_id, ModelKind::Channel), |id| {
+                self.keys.key(ModelKind::Channel, id)
+            })
+            .await
+        })
+    }
+
+    fn insert_channel(&self, channel: Channel) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            let Some(guild_id) = channel.guild_id else {
+                return;
+            };
+            let key = self.keys.key(ModelKind::Channel, channel.id);
+            let index = self.keys.guild_index(guild_id, ModelKind::Channel);
+            let index = Some((index, channel.id.to_string()));
+            self.set(ModelKind::Channel, &key, &channel, index).await;
+        })
+    }
+
+    fn remove_channel(&self, channel_id: ChannelId) -> BoxFuture<'_, Option<Channel>> {
+        Box::pin(async move {
+            let key = self.keys.key(ModelKind::Channel, channel_id);
+            let channel: Channel = self.take(&key).await?;
+            if let Some(guild_id) = channel.guild_id {
+                let index = self.keys.guild_index(guild_id, ModelKind::Channel);
+                self.unindex(&index, channel_id).await;
+            }
+            Some(channel)
+        })
+    }
+
+    fn dm_channel(&self, channel_id: ChannelId) -> BoxFuture<'_, Option<DmChannel>> {
+        Box::pin(async move {
+            self.get(&self.keys.key(ModelKind::DmChannel, channel_id))
+                .await
+        })
+    }
+
+    fn dm_channels(&self) -> BoxFuture<'_, Vec<DmChannel>> {
+        Box::pin(async move {
+            self.list(&self.keys.global_index(ModelKind::DmChannel), |id| {
+                self.keys.key(ModelKind::DmChannel, id)
+            })
+            .await
+        })
+    }
+
+    fn insert_dm_channel(&self, channel: DmChannel) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            let key = self.keys.key(ModelKind::DmChannel, channel.id);
+            let index = self.keys.global_index(ModelKind::DmChannel);
+            let index = Some((index, channel.id.to_string()));
+            self.set(ModelKind::DmChannel, &key, &channel, index).await;
+        })
+    }
+
+    fn remove_dm_channel(&self, channel_id: ChannelId) -> BoxFuture<'_, Option<DmChannel>> {
+        Box::pin(async move {
+            let index = self.keys.global_index(ModelKind::DmChannel);
+            self.unindex(&index, channel_id).await;
+            self.take(&self.keys.key(ModelKind::DmChannel, channel_id))
+                .await
+        })
+    }
+
+    fn user(&self, user_id: UserId) -> BoxFuture<'_, Option<User>> {
+        Box::pin(async move { self.get(&self.keys.key(ModelKind::User, user_id)).await })
+    }
+
+    fn insert_user(&self, user: User) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            let key = self.keys.key(ModelKind::User, user.id);
+            self.set(ModelKind::User, &key, &user, None).await;
+        })
+    }
+
+    fn remove_user(&self, user_id: UserId) -> BoxFuture<'_, Option<User>> {
+        Box::pin(async move { self.take(&self.keys.key(ModelKind::User, user_id)).await })
+    }
+
+    fn member(&self, guild_id: GuildId, user_id: UserId) -> BoxFuture<'_, Option<Member>> {
+        Box::pin(async move { self.get(&self.keys.member_key(guild_id, user_id)).await })
+    }
+
+    fn members(&self, guild_id: GuildId) -> BoxFuture<'_, Vec<Member>> {
+        Box::pin(async move {
+            self.list(&self.keys.guild_index(guild_id, ModelKind::Member), |id| {
+                self.keys
+                    .key(ModelKind::Member, format_args!("{guild_id}:{id}"))
+            })
+            .await
+        })
+    }
+
+    fn insert_member(&self, member: Member) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            let key = self.keys.member_key(member.guild_id(), member.id());
+            let index = self.keys.guild_index(member.guild_id(), ModelKind::Member);
+            let index = Some((index, member.id().to_string()));
+            self.set(ModelKind::Member, &key, &member, index).await;
+        })
+    }
+
+    fn remove_member(&self, guild_id: GuildId, user_id: UserId) -> BoxFuture<'_, Option<Member>> {
+        Box::pin(async move {
+            let index = self.keys.guild_index(guild_id, ModelKind::Member);
+            self.unindex(&index, user_id).await;
+            self.take(&self.keys.member_key(guild_id, user_id)).await
+        })
+    }
+
+    fn role(&self, role_id: RoleId) -> BoxFuture<'_, Option<Role>> {
+        Box::pin(async move { self.get(&self.keys.key(ModelKind::Role, role_id)).await })
+    }
+
+    fn roles(&self, guild_id: GuildId) -> BoxFuture<'_, Vec<Role>> {
+        Box::pin(async move {
+            self.list(&self.keys.guild_index(guild_id, ModelKind::Role), |id| {
+                self.keys.key(ModelKind::Role, id)
+            })
+            .await
+        })
+    }
+
+    fn insert_role(&self, role: Role) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            let key = self.keys.key(ModelKind::Role, role.id);
+            let index = self.keys.guild_index(role.guild_id, ModelKind::Role);
+            let index = Some((index, role.id.to_string()));
+            self.set(ModelKind::Role, &key, &role, index).await;
+        })
+    }
+
+    fn remove_role(&self, role_id: RoleId) -> BoxFuture<'_, Option<Role>> {
+        Box::pin(async move {
+            let role: Role = self.take(&self.keys.key(ModelKind::Role, role_id)).await?;
+            let index = self.keys.guild_index(role.guild_id, ModelKind::Role);
+            self.unindex(&index, role_id).await;
+            Some(role)
+        })
+    }
+
+    fn clear(&self) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            log_err(self.clear_keys().await);
+        })
+    }
+}
+
+impl Debug for RedisBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedisBackend")
+            .field("prefix", &self.keys.prefix)
+            .field("ttls", &self.ttls)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PartialMember;
+
+    #[test]
+    fn keys_and_encoding() {
+        let keys = Keys {
+            prefix: "bot".to_string(),
+        };
+
+        let (guild_id, user_id) = (GuildId::from(1), UserId::from(2));
+        assert_eq!(keys.member_key(guild_id, user_id), "bot:member:1:2");
+        assert_eq!(
+            keys.guild_index(guild_id, ModelKind::Channel),
+            "bot:guild:1:channels"
+        );
+        assert_eq!(keys.global_index(ModelKind::DmChannel), "bot:dm_channels");
+
+        let member: Member = RedisBackend::decode(
+            "member",
+            r#"{"id":2,"guild_id":1,"nick":"nick","roles":[3]}"#,
+        )
+        .unwrap();
+        assert_eq!(member.partial(), PartialMember::new(guild_id, user_id));
+        let encoded = serde_json::to_string(&member).unwrap();
+        let decoded: Member = RedisBackend::decode("member", &encoded).unwrap();
+        assert_eq!(decoded.nick.as_deref(), Some("nick"));
+        assert_eq!(decoded.roles, [RoleId::from(3)]);
+    }
+}
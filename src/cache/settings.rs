@@ -0,0 +1,119 @@
+/// Selects which kinds of models are cached. See [`Cache::with_settings`].
+///
+/// Everything except messages is cached by default. Gateway events are not applied to disabled
+/// kinds at all, which saves memory and, with a remote [`CacheBackend`], round trips to the
+/// backend. Lookups of disabled kinds return `None` unless the backend is populated elsewhere.
+///
+/// # Example
+/// ```no_run
+/// use adapt::{cache::CacheSettings, ClientOptions};
+///
+/// // A bot which only needs guilds, channels and roles
+/// let settings = CacheSettings::new().members(false).presences(false).unreads(false);
+/// let options = ClientOptions::new("token").cache_settings(settings);
+/// ```
+///
+/// [`Cache::with_settings`]: super::Cache::with_settings
+/// [`CacheBackend`]: super::CacheBackend
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[must_use]
+pub struct CacheSettings {
+    pub(super) guilds: bool,
+    pub(super) channels: bool,
+    pub(super) users: bool,
+    pub(super) members: bool,
+    pub(super) roles: bool,
+    pub(super) presences: bool,
+    pub(super) unreads: bool,
+    pub(super) message_capacity: usize,
+}
+
+impl CacheSettings {
+    /// Creates new settings which cache everything except messages.
+    pub const fn new() -> Self {
+        Self {
+            guilds: true,
+            channels: true,
+            users: true,
+            members: true,
+            roles: true,
+            presences: true,
+            unreads: true,
+            message_capacity: 0,
+        }
+    }
+
+    /// Creates new settings which cache nothing. The user the client is logged in as is still
+    /// tracked.
+    pub const fn none() -> Self {
+        Self {
+            guilds: false,
+            channels: false,
+            users: false,
+            members: false,
+            roles: false,
+            presences: false,
+            unreads: false,
+            message_capacity: 0,
+        }
+    }
+
+    /// Sets whether guilds are cached. Their channels, members, and roles are controlled
+    /// separately.
+    pub const fn guilds(mut self, enabled: bool) -> Self {
+        self.guilds = enabled;
+        self
+    }
+
+    /// Sets whether guild, DM, and group DM channels are cached.
+    pub const fn channels(mut self, enabled: bool) -> Self {
+        self.channels = enabled;
+        self
+    }
+
+    /// Sets whether users are cached.
+    pub const fn users(mut self, enabled: bool) -> Self {
+        self.users = enabled;
+        self
+    }
+
+    /// Sets whether guild members are cached. This is usually the largest kind of model in large
+    /// guilds.
+    pub const fn members(mut self, enabled: bool) -> Self {
+        self.members = enabled;
+        self
+    }
+
+    /// Sets whether roles are cached.
+    pub const fn roles(mut self, enabled: bool) -> Self {
+        self.roles = enabled;
+        self
+    }
+
+    /// Sets whether the presences of users are cached.
+    pub const fn presences(mut self, enabled: bool) -> Self {
+        self.presences = enabled;
+        self
+    }
+
+    /// Sets whether unread channels are tracked. See [`Cache::unreads`](super::Cache::unreads).
+    pub const fn unreads(mut self, enabled: bool) -> Self {
+        self.unreads = enabled;
+        self
+    }
+
+    /// Caches up to `capacity` messages received over the gateway, evicting the least recently
+    /// used message once full. Defaults to `0`, which disables the message cache.
+    ///
+    /// Cached messages are used to resolve the previous state of edited and deleted messages.
+    pub const fn messages(mut self, capacity: usize) -> Self {
+        self.message_capacity = capacity;
+        self
+    }
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,166 @@
+use crate::models::{ChannelId, MessageId};
+use serde::Serialize;
+use std::{collections::HashMap, sync::RwLock};
+
+/// The unread state of a channel, tracked from the gateway. See [`Cache::unreads`].
+///
+/// [`Cache::unreads`]: super::Cache::unreads
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ChannelUnread {
+    /// The ID of the channel.
+    pub channel_id: ChannelId,
+    /// The ID of the last message the client user acknowledged in the channel, if any.
+    pub last_acked_message_id: Option<MessageId>,
+    /// The ID of the last message received in the channel since the client became ready, if any.
+    pub last_message_id: Option<MessageId>,
+    /// The number of unread messages received in the channel since the client became ready.
+    ///
+    /// Unread messages sent before the client became ready are not counted, so this may be `0`
+    /// for a channel which is unread.
+    pub unread_count: usize,
+    /// The IDs of the unread messages which mention the client user.
+    pub mentions: Vec<MessageId>,
+}
+
+impl ChannelUnread {
+    const fn new(channel_id: ChannelId) -> Self {
+        Self {
+            channel_id,
+            last_acked_message_id: None,
+            last_message_id: None,
+            unread_count: 0,
+            mentions: Vec::new(),
+        }
+    }
+
+    /// Returns the number of unread messages which mention the client user.
+    #[must_use]
+    pub fn mention_count(&self) -> usize {
+        self.mentions.len()
+    }
+}
+
+/// Tracks the unread channels of the client user. Channels without unread messages are not
+/// stored.
+#[derive(Debug, Default)]
+pub(crate) struct Unreads(RwLock<HashMap<ChannelId, ChannelUnread>>);
+
+impl Unreads {
+    pub fn get(&self, channel_id: ChannelId) -> Option<ChannelUnread> {
+        self.0.read().expect("poison").get(&channel_id).cloned()
+    }
+
+    pub fn list(&self) -> Vec<ChannelUnread> {
+        self.0.read().expect("poison").values().cloned().collect()
+    }
+
+    /// Replaces all unread channels with the given ones, as sent by the gateway when the client
+    /// becomes ready.
+    pub fn reset(&self, unreads: impl IntoIterator<Item = ChannelUnread>) {
+        let mut entries = self.0.write().expect("poison");
+        entries.clear();
+        entries.extend(
+            unreads
+                .into_iter()
+                .map(|unread| (unread.channel_id, unread)),
+        );
+    }
+
+    /// Records a new message sent by another user in the given channel.
+    pub fn record_message(&self, channel_id: ChannelId, message_id: MessageId, mentioned: bool) {
+        let mut entries = self.0.write().expect("poison");
+        let unread = entries
+            .entry(channel_id)
+            .or_insert_with(|| ChannelUnread::new(channel_id));
+
+        unread.last_message_id = Some(message_id);
+        unread.unread_count += 1;
+        if mentioned {
+            unread.mentions.push(message_id);
+        }
+    }
+
+    /// Marks the given channel as read up to and including the given message.
+    pub fn ack(&self, channel_id: ChannelId, message_id: MessageId) {
+        let mut entries = self.0.write().expect("poison");
+        let Some(unread) = entries.get_mut(&channel_id) else {
+            return;
+        };
+
+        if unread
+            .last_acked_message_id
+            .is_some_and(|acked| acked >= message_id)
+        {
+            return;
+        }
+        // Channels unread since the client became ready only know their mentions, so the latest
+        // mention is the latest message known to be unread
+        let latest = unread
+            .last_message_id
+            .or_else(|| unread.mentions.iter().max().copied());
+        if latest.is_none_or(|latest| latest <= message_id) {
+            entries.remove(&channel_id);
+            return;
+        }
+        // Only the last message is known, so an earlier acknowledgement keeps the unread count
+        unread.last_acked_message_id = Some(message_id);
+        unread.mentions.retain(|id| *id > message_id);
+    }
+
+    pub fn remove(&self, channel_id: ChannelId) {
+        self.0.write().expect("poison").remove(&channel_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ack_clears_unread() {
+        let unreads = Unreads::default();
+        let channel_id = ChannelId::from(1);
+        unreads.record_message(channel_id, MessageId::from(2), true);
+        unreads.record_message(channel_id, MessageId::from(3), false);
+
+        let unread = unreads.get(channel_id).unwrap();
+        assert_eq!(unread.unread_count, 2);
+        assert_eq!(unread.mention_count(), 1);
+
+        unreads.ack(channel_id, MessageId::from(2));
+        let unread = unreads.get(channel_id).unwrap();
+        assert_eq!(unread.last_acked_message_id, Some(MessageId::from(2)));
+        assert_eq!(unread.mention_count(), 0);
+
+        unreads.ack(channel_id, MessageId::from(3));
+        assert!(unreads.get(channel_id).is_none());
+    }
+
+    #[test]
+    fn ack_ready_seeded_unread() {
+        let unreads = Unreads::default();
+        let channel_id = ChannelId::from(1);
+        unreads.reset([ChannelUnread {
+            channel_id,
+            last_acked_message_id: Some(MessageId::from(2)),
+            last_message_id: None,
+            unread_count: 0,
+            mentions: vec![MessageId::from(4), MessageId::from(6)],
+        }]);
+
+        // Older than the last acknowledged message
+        unreads.ack(channel_id, MessageId::from(1));
+        let unread = unreads.get(channel_id).unwrap();
+        assert_eq!(unread.last_acked_message_id, Some(MessageId::from(2)));
+        assert_eq!(unread.mention_count(), 2);
+
+        // Older than the latest mention
+        unreads.ack(channel_id, MessageId::from(5));
+        let unread = unreads.get(channel_id).unwrap();
+        assert_eq!(unread.last_acked_message_id, Some(MessageId::from(5)));
+        assert_eq!(unread.mentions, [MessageId::from(6)]);
+
+        unreads.ack(channel_id, MessageId::from(6));
+        assert!(unreads.get(channel_id).is_none());
+    }
+}
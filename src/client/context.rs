@@ -1,9 +1,29 @@
-use crate::http::Http;
+use super::outbox::Outbox;
+use super::scheduler::{ScheduledTask, Scheduler};
+use crate::builder::{GuildBuilder, GuildQueryBuilder};
+use crate::cache::{Cache, ChannelUnread};
+use crate::http::{endpoints, Http};
+use crate::models::{
+    Channel, ChannelId, DmChannel, Guild, GuildId, Relationship, Relationships, User, UserId,
+};
 #[cfg(feature = "ws")]
-use crate::ws::Messenger;
+use crate::ws::{self, Event, Messenger, ShardInfo};
+use crate::Result;
+#[cfg(feature = "ws")]
+use essence::models::PresenceStatus;
+use futures_util::{stream, Stream, StreamExt, TryStreamExt};
+use serde::{Serialize, Serializer};
 use std::fmt::Debug;
+use std::future::Future;
 use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "ws")]
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    watch,
+};
+use tokio::time::Instant;
 
 /// Allows access to shared values regarding the client state, including the HTTP client, gateway
 /// connection, and cache.
@@ -16,15 +36,145 @@ pub struct Context {
     /// The messenger for the connection to Harmony.
     #[cfg(feature = "ws")]
     pub(crate) ws: Option<Messenger>,
+    /// Whether a `ready` event has been received from the gateway, or the client has stopped.
+    #[cfg(feature = "ws")]
+    pub(crate) ready: Arc<watch::Sender<ReadyState>>,
+    /// The shard claimed by the client, if a shard coordinator is used.
+    #[cfg(feature = "ws")]
+    pub(crate) shard: Option<ShardInfo>,
+    /// Broadcasts every dispatched event, used to wait for specific events.
+    #[cfg(feature = "ws")]
+    pub(crate) events: broadcast::Sender<Event>,
+    /// The sequence number of the event this context was dispatched with, if any.
+    #[cfg(feature = "ws")]
+    pub(crate) sequence: Option<u64>,
+    /// Runs tasks scheduled through this context until the client shuts down.
+    pub(crate) scheduler: Scheduler,
+    /// The queue of messages sent through [`WithCtx<ChannelId>::send_queued`].
+    pub(crate) outbox: Outbox,
+    /// The cache of models received over the gateway.
+    pub(crate) cache: Arc<Cache>,
+}
+
+/// Whether the client has connected to the gateway, as observed by
+/// [`Context::wait_until_ready`].
+#[cfg(feature = "ws")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ReadyState {
+    /// The client has not received a `ready` event yet.
+    Pending,
+    /// The client has received a `ready` event.
+    Ready,
+    /// The client has stopped, either because it was closed or after a fatal error.
+    Stopped,
 }
 
 impl Context {
+    /// The number of events buffered for each [`wait_for`](Self::wait_for) call. If a waiter lags
+    /// behind by more than this many events, the oldest events are skipped.
+    #[cfg(feature = "ws")]
+    const EVENT_BUFFER: usize = 256;
+
+    /// Creates a new context around the given HTTP client, without a gateway connection.
+    pub(crate) fn new(http: Arc<Http>) -> Self {
+        Self {
+            http,
+            #[cfg(feature = "ws")]
+            ws: None,
+            #[cfg(feature = "ws")]
+            ready: Arc::new(watch::Sender::new(ReadyState::Pending)),
+            #[cfg(feature = "ws")]
+            shard: None,
+            #[cfg(feature = "ws")]
+            events: broadcast::Sender::new(Self::EVENT_BUFFER),
+            #[cfg(feature = "ws")]
+            sequence: None,
+            scheduler: Scheduler::new(),
+            outbox: Outbox::new(None),
+            cache: Arc::default(),
+        }
+    }
+
     /// Returns a reference to the HTTP client, used to make requests to the REST API.
     #[must_use]
     pub const fn http(&self) -> &Arc<Http> {
         &self.http
     }
 
+    /// Returns a reference to the cache of guilds, channels, users, members, and roles received
+    /// over the gateway.
+    ///
+    /// The cache is populated once the client is ready and is kept up to date by subsequent
+    /// events. It is empty for clients without a gateway connection.
+    #[must_use]
+    pub fn cache(&self) -> &Cache {
+        &self.cache
+    }
+
+    /// Returns the DM and group DM channels of the client user, as received over the gateway or
+    /// opened through [`WithCtx<UserId>::dm_channel`].
+    pub async fn dm_channels(&self) -> Vec<WithCtx<DmChannel>> {
+        self.cache
+            .dm_channels()
+            .await
+            .into_iter()
+            .map(|channel| self.clone().with(channel))
+            .collect()
+    }
+
+    /// Returns the unread state of every channel with unread messages, as tracked from the
+    /// gateway. See [`Cache::unreads`].
+    #[must_use]
+    pub fn unreads(&self) -> Vec<ChannelUnread> {
+        self.cache.unreads()
+    }
+
+    /// Returns the channel with the given ID from the cache, or fetches it if it is not cached.
+    ///
+    /// Fetched guild channels are added to the cache, unless channels are disabled in its
+    /// [settings](crate::cache::CacheSettings).
+    pub async fn channel(&self, channel_id: ChannelId) -> Result<WithCtx<Channel>> {
+        if let Some(channel) = self.cache.channel(channel_id).await {
+            return Ok(self.clone().with(channel));
+        }
+        let channel = self.clone().with(channel_id).fetch().await?;
+        self.cache
+            .insert_guild_channel(channel.inner().clone())
+            .await;
+        Ok(channel)
+    }
+
+    /// Returns the guild with the given ID from the cache, or fetches it if it is not cached.
+    ///
+    /// The guild is returned without its channels, members, and roles either way; use
+    /// [`WithCtx<GuildId>::fetch`] to fetch them along with the guild. Fetched guilds are added to
+    /// the cache, unless guilds are disabled in its [settings](crate::cache::CacheSettings).
+    pub async fn guild(&self, guild_id: GuildId) -> Result<WithCtx<Guild>> {
+        if let Some(guild) = self.cache.guild(guild_id).await {
+            return Ok(self.clone().with(guild));
+        }
+        let guild = self
+            .clone()
+            .with(guild_id)
+            .fetch(GuildQueryBuilder::new())
+            .await?;
+        self.cache.insert_guild(guild.inner().clone()).await;
+        Ok(guild)
+    }
+
+    /// Returns the user with the given ID from the cache, or fetches it if it is not cached.
+    ///
+    /// Fetched users are added to the cache, unless users are disabled in its
+    /// [settings](crate::cache::CacheSettings).
+    pub async fn user(&self, user_id: UserId) -> Result<WithCtx<User>> {
+        if let Some(user) = self.cache.user(user_id).await {
+            return Ok(self.clone().with(user));
+        }
+        let user = self.clone().with(user_id).fetch().await?;
+        self.cache.insert_user(user.inner().clone()).await;
+        Ok(user)
+    }
+
     /// Returns a reference to the websocket messenger. This is `None` if there is no active
     /// connection to Harmony yet.
     #[cfg(feature = "ws")]
@@ -33,10 +183,278 @@ impl Context {
         self.ws.as_ref()
     }
 
+    /// Returns the ID of the shard the client is running, between `0` and
+    /// [`shard_count`](Self::shard_count)` - 1`. This is `0` if the client is not sharded.
+    #[cfg(feature = "ws")]
+    #[must_use]
+    pub fn shard_id(&self) -> u32 {
+        self.shard.map_or(0, |shard| shard.id)
+    }
+
+    /// Returns the total number of shards of the client. This is `1` if the client is not
+    /// sharded.
+    #[cfg(feature = "ws")]
+    #[must_use]
+    pub fn shard_count(&self) -> u32 {
+        self.shard.map_or(1, |shard| shard.count)
+    }
+
+    /// Returns the sequence number of the gateway event this context was dispatched with, or
+    /// `None` if this context was not passed to an event handler.
+    ///
+    /// Sequence numbers are assigned by the gateway and increase monotonically with every event
+    /// received in a session, continuing across resumed connections. If the gateway does not send
+    /// them, they are counted from `0` for the `ready` event instead. A new session restarts the
+    /// sequence.
+    #[cfg(feature = "ws")]
+    #[must_use]
+    pub const fn sequence(&self) -> Option<u64> {
+        self.sequence
+    }
+
+    /// Waits until the client has received its first `ready` event from the gateway. Resolves
+    /// immediately if the client is already ready.
+    ///
+    /// This is useful in combination with
+    /// [`Client::start_in_background`](crate::Client::start_in_background) to perform work once
+    /// the client has connected.
+    ///
+    /// # Errors
+    /// * [`ws::Error::NoConnection`] if the client stopped, for example after a fatal connection
+    ///   error, before or while waiting. The error itself is returned by the task running the
+    ///   client.
+    #[cfg(feature = "ws")]
+    pub async fn wait_until_ready(&self) -> Result<()> {
+        let mut rx = self.ready.subscribe();
+        // The sender is owned by this context, so it cannot be dropped while waiting
+        let state = rx
+            .wait_for(|state| *state != ReadyState::Pending)
+            .await
+            .map_or(ReadyState::Stopped, |state| *state);
+
+        match state {
+            ReadyState::Ready => Ok(()),
+            _ => Err(ws::Error::NoConnection.into()),
+        }
+    }
+
+    /// Waits for the first dispatched event matching the given predicate, returning `None` if no
+    /// such event is received within the given timeout.
+    ///
+    /// Only events dispatched after this method is called are considered.
+    ///
+    /// # Errors
+    /// * [`ws::Error::BlockingDispatch`] if called from an event handler run by the
+    ///   [inline](ws::handler::DispatchExecutor::inline) executor, since no event is dispatched
+    ///   until the handler finishes. Spawn a task to wait for events from such handlers.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use adapt::ws::Event;
+    /// use std::time::Duration;
+    ///
+    /// # async fn run(ctx: adapt::Context) -> adapt::Result<()> {
+    /// let event = ctx
+    ///     .wait_for(
+    ///         |event| matches!(event, Event::MessageCreate(message) if message.content == "yes"),
+    ///         Duration::from_secs(30),
+    ///     )
+    ///     .await?;
+    /// # Ok(()) }
+    /// ```
+    #[cfg(feature = "ws")]
+    pub async fn wait_for(
+        &self,
+        predicate: impl FnMut(&Event) -> bool + Send,
+        timeout: Duration,
+    ) -> Result<Option<Event>> {
+        let mut events = self.subscribe_events()?;
+        Ok(Self::recv_matching(&mut events, predicate, timeout).await)
+    }
+
+    /// Subscribes to dispatched events, failing if the events could never be received because
+    /// the current task is dispatching them inline.
+    #[cfg(feature = "ws")]
+    pub(crate) fn subscribe_events(&self) -> Result<broadcast::Receiver<Event>> {
+        if ws::handler::is_dispatching_inline() {
+            return Err(ws::Error::BlockingDispatch.into());
+        }
+        Ok(self.events.subscribe())
+    }
+
+    /// Receives the next event matching the given predicate, or `None` if no such event is
+    /// received within the given timeout.
+    #[cfg(feature = "ws")]
+    pub(crate) async fn recv_matching(
+        events: &mut broadcast::Receiver<Event>,
+        mut predicate: impl FnMut(&Event) -> bool + Send,
+        timeout: Duration,
+    ) -> Option<Event> {
+        let wait = async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) if predicate(&event) => return Some(event),
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        };
+        tokio::time::timeout(timeout, wait).await.ok().flatten()
+    }
+
+    /// Updates the presence of the client through the current gateway connection. The presence is
+    /// restored automatically if the connection is re-established.
+    ///
+    /// # Errors
+    /// * [`ws::Error::NoConnection`] if there is no active connection to Harmony.
+    #[cfg(feature = "ws")]
+    pub async fn set_presence(
+        &self,
+        status: PresenceStatus,
+        custom_status: Option<String>,
+    ) -> Result<()> {
+        let ws = self.ws.as_ref().ok_or(ws::Error::NoConnection)?;
+        ws.update_presence(status, custom_status).await?;
+        Ok(())
+    }
+
+    /// Runs the given future after the given delay, unless the client shuts down first.
+    ///
+    /// Scheduled tasks are tied to the lifetime of the client: they are cancelled once the gateway
+    /// connection started by [`Client::start`](crate::Client::start) ends.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use adapt::{models::ChannelId, Context};
+    /// # use std::time::Duration;
+    /// # fn run(ctx: Context, channel_id: ChannelId) {
+    /// let channel = ctx.clone().with(channel_id);
+    /// let reminder = ctx.schedule(Duration::from_secs(60 * 60), async move {
+    ///     let _ = channel.send("Reminder: stand up and stretch!").await;
+    /// });
+    /// # reminder.cancel();
+    /// # }
+    /// ```
+    pub fn schedule(
+        &self,
+        delay: Duration,
+        fut: impl Future<Output = ()> + Send + 'static,
+    ) -> ScheduledTask {
+        self.schedule_at(Instant::now() + delay, fut)
+    }
+
+    /// Runs the given future at the given instant, unless the client shuts down first. See
+    /// [`Self::schedule`].
+    pub fn schedule_at(
+        &self,
+        deadline: Instant,
+        fut: impl Future<Output = ()> + Send + 'static,
+    ) -> ScheduledTask {
+        self.scheduler.schedule_at(deadline, fut)
+    }
+
+    /// Runs the future returned by the given function repeatedly, once every `period`, starting
+    /// one period from now and until the client shuts down. If a run takes longer than the
+    /// period, the next run is delayed rather than bunched up.
+    pub fn schedule_every<F, Fut>(&self, period: Duration, f: F) -> ScheduledTask
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.scheduler.schedule_every(period, f)
+    }
+
+    /// Returns the number of messages sent through [`WithCtx<ChannelId>::send_queued`] which
+    /// have not been sent yet.
+    #[must_use]
+    pub fn queued_messages(&self) -> usize {
+        self.outbox.len()
+    }
+
     /// Wraps a value with the current context using [`WithCtx`].
     pub const fn with<T>(self, inner: T) -> WithCtx<T> {
         WithCtx { inner, ctx: self }
     }
+
+    /// Creates a new guild owned by the client, configured by the given function.
+    pub async fn create_guild(
+        &self,
+        f: impl FnOnce(GuildBuilder) -> GuildBuilder + Send,
+    ) -> Result<WithCtx<Guild>> {
+        let guild = self
+            .http
+            .request(endpoints::CreateGuild)
+            .body(f(GuildBuilder::default()).build()?)
+            .await?;
+
+        Ok(self.clone().with(Guild::from_raw(guild)))
+    }
+
+    /// Checks whether the given username is available to register.
+    ///
+    /// The API reports a taken username as an error; this maps it to `false`. All other errors,
+    /// such as an invalid username, are returned as-is.
+    pub async fn is_username_available(&self, username: &str) -> Result<bool> {
+        match self
+            .http
+            .request(endpoints::CheckUsernameAvailability(username))
+            .await
+        {
+            Ok(()) => Ok(true),
+            Err(err) if err.is_already_exists() => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Fetches all guilds the client user is a member of, including the related objects selected
+    /// by the given query.
+    pub async fn guilds(&self, query: GuildQueryBuilder) -> Result<Vec<WithCtx<Guild>>> {
+        let guilds = self
+            .http
+            .request(endpoints::GetAllGuilds)
+            .query(query.build())
+            .await?;
+
+        Ok(guilds
+            .into_iter()
+            .map(|guild| self.clone().with(Guild::from_raw(guild)))
+            .collect())
+    }
+
+    /// Returns a stream over the guilds the client user is a member of.
+    ///
+    /// The guild list is only requested once the stream is first polled, and without any
+    /// channels, members, or roles attached to keep the response as small as possible. The API
+    /// does not currently paginate this endpoint, so all guilds are still fetched in a single
+    /// request.
+    pub fn guilds_iter(&self) -> impl Stream<Item = Result<WithCtx<Guild>>> + Send + 'static {
+        let ctx = self.clone();
+
+        stream::once(async move {
+            let guilds = ctx
+                .http
+                .request(endpoints::GetAllGuilds)
+                .query(GuildQueryBuilder::new().build())
+                .await?;
+
+            let guilds = guilds
+                .into_iter()
+                .map(move |guild| Ok(ctx.clone().with(Guild::from_raw(guild))));
+            Ok::<_, crate::Error>(stream::iter(guilds))
+        })
+        .try_flatten()
+        .boxed()
+    }
+
+    /// Fetches the relationships of the client user, split into friends, incoming and outgoing
+    /// friend requests, and blocked users.
+    pub async fn relationships(&self) -> Result<Relationships> {
+        let relationships = self.http.request(endpoints::GetRelationships).await?;
+
+        Ok(Relationships::new(relationships.into_iter().map(
+            |relationship| self.clone().with(Relationship::from_raw(relationship)),
+        )))
+    }
 }
 
 impl Debug for Context {
@@ -74,6 +492,36 @@ impl<T> WithCtx<T> {
     pub fn inner_mut(&mut self) -> &mut T {
         &mut self.inner
     }
+
+    /// Consumes the [`WithCtx`] instance, returning the inner value and its context.
+    pub fn split(self) -> (T, Context) {
+        (self.inner, self.ctx)
+    }
+
+    /// Transforms the inner value with the given function, keeping the context attached.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use adapt::{models::Message, WithCtx};
+    ///
+    /// fn content(message: WithCtx<Message>) -> WithCtx<String> {
+    ///     message.map(|message| message.content)
+    /// }
+    /// ```
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> WithCtx<U> {
+        WithCtx {
+            inner: f(self.inner),
+            ctx: self.ctx,
+        }
+    }
+
+    /// Borrows the inner value, attaching a clone of the context to the reference.
+    pub fn as_ref(&self) -> WithCtx<&T> {
+        WithCtx {
+            inner: &self.inner,
+            ctx: self.ctx.clone(),
+        }
+    }
 }
 
 impl<T> Deref for WithCtx<T> {
@@ -90,8 +538,146 @@ impl<T> DerefMut for WithCtx<T> {
     }
 }
 
+/// Serializes only the inner value; the context is not serialized.
+impl<T: Serialize> Serialize for WithCtx<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.inner.serialize(serializer)
+    }
+}
+
 impl<T: Debug> Debug for WithCtx<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_tuple("WithCtx").field(&self.inner).finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "ws")]
+    use super::ReadyState;
+    use crate::http::Method;
+    use crate::models::{User, UserId};
+    use crate::testing::MockApi;
+    #[cfg(feature = "ws")]
+    use crate::{cache::Replaced, ws, Error};
+    #[cfg(feature = "ws")]
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn user_falls_back_to_http() {
+        let api = MockApi::start().await.unwrap();
+        api.respond(
+            Method::GET,
+            "/users/2",
+            404,
+            serde_json::json!({
+                "type": "NotFound",
+                "entity": "user",
+                "message": "User not found",
+            }),
+        );
+        let ctx = api.context();
+        ctx.cache()
+            .backend()
+            .insert_user(User {
+                id: UserId::from(1),
+                username: "cached".to_string(),
+                display_name: None,
+                avatar: None,
+                banner: None,
+                bio: None,
+            })
+            .await;
+
+        let user = ctx.user(UserId::from(1)).await.unwrap();
+        assert_eq!(user.username, "cached");
+        assert!(api.requests().is_empty());
+
+        let err = ctx.user(UserId::from(2)).await.unwrap_err();
+        assert!(err.is_not_found());
+        assert_eq!(api.requests()[0].path, "/users/2");
+    }
+
+    #[tokio::test]
+    async fn fetched_user_is_cached() {
+        let api = MockApi::start().await.unwrap();
+        api.respond(
+            Method::GET,
+            "/users/2",
+            200,
+            serde_json::json!({
+                "id": 2,
+                "username": "fetched",
+                "display_name": null,
+                "avatar": null,
+                "banner": null,
+                "bio": null,
+                "flags": 0,
+            }),
+        );
+        let ctx = api.context();
+
+        for _ in 0..2 {
+            let user = ctx.user(UserId::from(2)).await.unwrap();
+            assert_eq!(user.username, "fetched");
+        }
+        assert_eq!(api.requests().len(), 1);
+    }
+
+    #[cfg(feature = "ws")]
+    #[tokio::test]
+    async fn wait_until_ready_wakes_when_stopped() {
+        let api = MockApi::start().await.unwrap();
+        let ctx = api.context();
+        let waiter = tokio::spawn({
+            let ctx = ctx.clone();
+            async move { ctx.wait_until_ready().await }
+        });
+        tokio::task::yield_now().await;
+
+        ctx.ready.send_replace(ReadyState::Stopped);
+        let err = waiter.await.unwrap().unwrap_err();
+        assert!(matches!(err, Error::Harmony(ws::Error::NoConnection)));
+    }
+
+    #[cfg(feature = "ws")]
+    #[tokio::test]
+    async fn wait_until_ready_resolves_after_ready() {
+        let api = MockApi::start().await.unwrap();
+        let ctx = api.context();
+        let waiter = tokio::spawn({
+            let ctx = ctx.clone();
+            async move { ctx.wait_until_ready().await }
+        });
+        tokio::task::yield_now().await;
+
+        let ready: ws::InboundMessage = serde_json::from_value(serde_json::json!({
+            "event": "ready",
+            "session_id": "session",
+            "user": {
+                "id": 1,
+                "username": "user",
+                "display_name": null,
+                "avatar": null,
+                "banner": null,
+                "bio": null,
+                "flags": 0,
+                "email": "user@example.com",
+                "dm_privacy": 0,
+                "group_dm_privacy": 0,
+                "friend_request_privacy": 0,
+                "onboarding_flags": 0,
+                "settings": 0,
+            },
+            "guilds": [],
+            "dm_channels": [],
+            "presences": [],
+            "relationships": [],
+            "unacked": [],
+        }))
+        .unwrap();
+        let consumers: ws::ConsumerVec = Arc::new(tokio::sync::Mutex::new(Arc::from([])));
+        ws::event::dispatch(&ctx, &consumers, ready, Replaced::None, None).await;
+        waiter.await.unwrap().unwrap();
+    }
+}
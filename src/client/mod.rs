@@ -1,14 +1,27 @@
 //! Interact with Adapt using the client module.
 
 mod context;
+pub mod outbox;
+mod scheduler;
 
 #[cfg(feature = "ws")]
 use crate::ws;
-use crate::{http::Http, Result, Server};
+use crate::{
+    cache::{Cache, CacheBackend, CacheSettings},
+    http::{Http, TokenRetrievalMethod},
+    tls::TlsOptions,
+    Result, Server,
+};
 use essence::models::{Device, PresenceStatus};
+use outbox::{Outbox, OutboxStore};
 use std::sync::Arc;
+#[cfg(feature = "ws")]
+use tokio::task::JoinHandle;
 
+#[cfg(feature = "ws")]
+pub(crate) use context::ReadyState;
 pub use context::{Context, WithCtx};
+pub use scheduler::ScheduledTask;
 
 /// Configures options for a [`Client`].
 #[derive(Clone)]
@@ -21,6 +34,15 @@ pub struct ClientOptions<'a> {
     /// The options for connecting to the gateway.
     #[cfg(feature = "ws")]
     pub ws_options: ws::ConnectOptions,
+    /// The TLS options used by the HTTP client, if any.
+    pub tls: Option<TlsOptions>,
+    /// The store in which messages queued through
+    /// [`WithCtx<ChannelId>::send_queued`](WithCtx::send_queued) are persisted, if any.
+    pub outbox_store: Option<Arc<dyn OutboxStore>>,
+    /// The backend in which the cache stores models, or `None` to store them in memory.
+    pub cache_backend: Option<Arc<dyn CacheBackend>>,
+    /// Which kinds of models the cache stores.
+    pub cache_settings: CacheSettings,
 }
 
 impl<'a> ClientOptions<'a> {
@@ -31,6 +53,10 @@ impl<'a> ClientOptions<'a> {
             server,
             #[cfg(feature = "ws")]
             ws_options: ws::ConnectOptions::new(token),
+            tls: None,
+            outbox_store: None,
+            cache_backend: None,
+            cache_settings: CacheSettings::new(),
         }
     }
 
@@ -57,6 +83,65 @@ impl<'a> ClientOptions<'a> {
         self
     }
 
+    /// Sets where event handlers run when the gateway dispatches an event, for example to handle
+    /// events concurrently on a bounded pool of tasks. See [`ws::handler::DispatchExecutor`].
+    #[cfg(feature = "ws")]
+    #[inline]
+    pub fn dispatch_executor(mut self, executor: ws::handler::DispatchExecutor) -> Self {
+        self.ws_options = self.ws_options.dispatch_executor(executor);
+        self
+    }
+
+    /// Sets the TLS options used by both the HTTP client and the gateway connection, for example
+    /// to trust the certificate authority of a self-hosted instance.
+    #[inline]
+    pub fn tls(mut self, tls: TlsOptions) -> Self {
+        #[cfg(feature = "ws")]
+        {
+            self.ws_options = self.ws_options.tls(tls.clone());
+        }
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Sets the store in which messages queued through
+    /// [`WithCtx<ChannelId>::send_queued`](WithCtx::send_queued) are persisted. Messages left in
+    /// the store are sent once the client starts.
+    ///
+    /// Without a store, queued messages are only kept in memory and are lost if the process exits
+    /// before they are sent.
+    #[inline]
+    pub fn outbox_store(mut self, store: impl OutboxStore + 'static) -> Self {
+        self.outbox_store = Some(Arc::new(store));
+        self
+    }
+
+    /// Sets the backend in which the cache stores models received over the gateway. Defaults to
+    /// an [`InMemoryBackend`](crate::cache::InMemoryBackend).
+    #[inline]
+    pub fn cache_backend(mut self, backend: Arc<dyn CacheBackend>) -> Self {
+        self.cache_backend = Some(backend);
+        self
+    }
+
+    /// Sets which kinds of models the cache stores, for example to disable caching members and
+    /// presences in memory-constrained bots. Defaults to caching everything except messages.
+    #[inline]
+    pub fn cache_settings(mut self, settings: CacheSettings) -> Self {
+        self.cache_settings = settings;
+        self
+    }
+
+    /// Replaces the token used by both the HTTP client and the gateway connection.
+    fn with_token(mut self, token: String) -> Self {
+        #[cfg(feature = "ws")]
+        {
+            self.ws_options.token = secrecy::SecretString::new(token.clone());
+        }
+        self.token = token;
+        self
+    }
+
     /// Builds a new [`Client`] with these options.
     pub fn into_client(self) -> Client {
         Client::from_options(self)
@@ -85,6 +170,10 @@ pub struct Client {
     /// The websocket client maintaing connections with the gateway.
     #[cfg(feature = "ws")]
     pub ws: ws::Client,
+    /// The queue of messages sent through [`WithCtx<ChannelId>::send_queued`](WithCtx::send_queued).
+    outbox: Outbox,
+    /// The cache of models received over the gateway, shared by all contexts of the client.
+    cache: Arc<Cache>,
 }
 
 impl Client {
@@ -93,9 +182,66 @@ impl Client {
         Self::from_options(ClientOptions::new(token))
     }
 
+    /// Logs into the given user account with credentials (email and password) on the production
+    /// server, and creates a new client authenticated with the obtained token. The token is
+    /// obtained based on the given token retrieval method; when in doubt, use
+    /// `Default::default()`.
+    ///
+    /// See [`Self::login_with`] to configure the client, for example to log in on a self-hosted
+    /// instance.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use adapt::{http::TokenRetrievalMethod, Client};
+    ///
+    /// # async fn run() -> adapt::Result<()> {
+    /// let client = Client::login("user@example.com", "password", TokenRetrievalMethod::Reuse)
+    ///     .await?;
+    /// client.start().await?;
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// # See Also
+    /// * [`Http::login_on`] which only creates an HTTP client.
+    pub async fn login(
+        email: impl AsRef<str> + Send,
+        password: impl AsRef<str> + Send,
+        retrieval_method: TokenRetrievalMethod,
+    ) -> Result<Self> {
+        Self::login_with(ClientOptions::new(""), email, password, retrieval_method).await
+    }
+
+    /// Logs into the given user account with credentials (email and password) on the server of
+    /// the given options, and creates a new client with those options, authenticated with the
+    /// obtained token. Any token already set in the options is replaced.
+    ///
+    /// The login request is made with the TLS options of the given options, if any.
+    pub async fn login_with(
+        options: ClientOptions<'_>,
+        email: impl AsRef<str> + Send,
+        password: impl AsRef<str> + Send,
+        retrieval_method: TokenRetrievalMethod,
+    ) -> Result<Self> {
+        let token = Self::build_http(&options)
+            .login_token(email, password, retrieval_method)
+            .await?;
+
+        Ok(Self::from_options(options.with_token(token)))
+    }
+
+    fn build_http(options: &ClientOptions) -> Http {
+        let http = Http::from_token_and_uri(&options.token, options.server)
+            .with_convey_url(options.server.convey)
+            .with_app_url(options.server.app);
+        match options.tls.clone() {
+            Some(tls) => http.with_tls(tls),
+            None => http,
+        }
+    }
+
     /// Creates a new client with the given options.
     pub fn from_options(options: ClientOptions) -> Self {
-        let http = Http::from_token_and_uri(&options.token, options.server);
+        let http = Self::build_http(&options);
 
         #[cfg(feature = "ws")]
         let ws = ws::Client::new(options.ws_options);
@@ -103,6 +249,13 @@ impl Client {
         Self {
             http: Arc::new(http),
             ws,
+            outbox: Outbox::new(options.outbox_store),
+            cache: Arc::new(
+                options
+                    .cache_backend
+                    .map_or_else(Cache::new, Cache::with_backend)
+                    .with_settings(options.cache_settings),
+            ),
         }
     }
 
@@ -113,16 +266,132 @@ impl Client {
         self
     }
 
+    /// Registers a closure which is called for every event of kind `K`, receiving the payload of
+    /// the event.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use adapt::ws::events;
+    ///
+    /// # fn run(client: adapt::Client) {
+    /// client.on::<events::MessageCreate>(|message| async move {
+    ///     println!("Received message: {:?}", message.content);
+    /// });
+    /// # }
+    /// ```
+    #[cfg(feature = "ws")]
+    pub fn on<K: ws::events::EventKind>(
+        &self,
+        f: impl ws::handler::EventCallback<K::Payload> + 'static,
+    ) -> &Self {
+        self.add_handler(ws::handler::on::<K>(f))
+    }
+
+    /// Adds a new event consumer to the client, with the given options determining its order of
+    /// execution relative to other consumers.
+    #[cfg(feature = "ws")]
+    pub fn add_handler_with(
+        &self,
+        handler: impl ws::EventConsumer + 'static,
+        options: ws::handler::ConsumerOptions,
+    ) -> &Self {
+        self.ws.add_consumer_with(handler, options);
+        self
+    }
+
+    /// Adds a new event publisher to the client, which receives every raw dispatch event.
+    #[cfg(feature = "ws")]
+    pub fn add_publisher(&self, publisher: impl ws::EventPublisher + 'static) -> &Self {
+        self.ws.add_publisher(publisher);
+        self
+    }
+
+    fn context(&self) -> Context {
+        let mut ctx = Context::new(self.http.clone());
+        ctx.outbox = self.outbox.clone();
+        ctx.cache = self.cache.clone();
+        ctx
+    }
+
     /// Starts the client, connecting to the gateway and initializing the cache.
+    ///
+    /// Once the gateway connection ends, tasks scheduled through the [`Context`] are cancelled.
+    ///
+    /// # Errors
+    /// * If the messages left in the [outbox store](ClientOptions::outbox_store) cannot be
+    ///   loaded.
+    /// * If the gateway connection fails.
     pub async fn start(&self) -> Result<Context> {
-        let ctx = Context {
-            http: self.http.clone(),
-            #[cfg(feature = "ws")]
-            ws: None,
-        };
+        let ctx = self.context();
+        ctx.outbox.restore().await?;
+        ctx.outbox.ensure_running(&ctx);
 
         #[cfg(feature = "ws")]
-        self.ws.start(ctx.clone()).await?;
+        {
+            let result = self.ws.start(ctx.clone()).await;
+            ctx.scheduler.shutdown();
+            result?;
+        }
+
+        Ok(ctx)
+    }
+
+    /// Starts the client in the background, returning immediately with the [`Context`] of the
+    /// client and a handle to the task maintaining the gateway connection.
+    ///
+    /// Use [`Context::wait_until_ready`] to wait until the client has connected. The returned
+    /// context can be used to access the REST API; the gateway messenger is only available on the
+    /// contexts passed to event handlers.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn run(client: adapt::Client) -> adapt::Result<()> {
+    /// let (ctx, handle) = client.start_in_background();
+    /// ctx.wait_until_ready().await?;
+    /// // Do work after connecting...
+    /// handle.await.expect("client task panicked")?;
+    /// # Ok(()) }
+    /// ```
+    #[cfg(feature = "ws")]
+    pub fn start_in_background(&self) -> (Context, JoinHandle<Result<()>>) {
+        let ctx = self.context();
+        ctx.outbox.ensure_running(&ctx);
+        let ws = self.ws.clone();
+        let task_ctx = ctx.clone();
+        let handle = tokio::spawn(async move {
+            if let Err(err) = task_ctx.outbox.restore().await {
+                task_ctx.ready.send_replace(ReadyState::Stopped);
+                return Err(err);
+            }
+            let result = ws.start(task_ctx.clone()).await;
+            task_ctx.scheduler.shutdown();
+            result.map_err(Into::into)
+        });
+
+        (ctx, handle)
+    }
+
+    /// Tears down the current gateway connection and establishes a new one, for example after
+    /// detecting stale state. The session is resumed if a session store is configured.
+    ///
+    /// # Errors
+    /// * [`ws::Error::NoConnection`] if the client has not been started.
+    #[cfg(feature = "ws")]
+    pub async fn restart(&self) -> Result<()> {
+        self.ws.reconnect().await?;
+        Ok(())
+    }
+
+    /// Starts the client in consumer mode: instead of connecting to the gateway, serialized events
+    /// are consumed from the given source (for example, a message broker fed by another process's
+    /// [`EventPublisher`][ws::EventPublisher]) and dispatched to the registered handlers.
+    ///
+    /// The [`Context`] passed to handlers still provides access to the REST API, but has no
+    /// gateway connection. Returns once the source is exhausted.
+    #[cfg(feature = "ws")]
+    pub async fn start_from_source(&self, source: impl ws::EventSource) -> Result<Context> {
+        let ctx = self.context();
+        self.ws.consume(ctx.clone(), source).await?;
 
         Ok(ctx)
     }
@@ -0,0 +1,398 @@
+//! A reliable queue for outbound messages, used by
+//! [`WithCtx<ChannelId>::send_queued`](crate::WithCtx::send_queued).
+//!
+//! Queued messages are sent in order by a background task, which retries them across rate limits
+//! and failures to reach the API until they are accepted by the API. Each message carries a nonce, so
+//! that it is queued at most once and is not sent again once it is known to have been created.
+
+use super::Context;
+use crate::http::{endpoints, Http};
+use crate::utils::fs::write_atomic;
+use crate::Error;
+use essence::http::message::CreateMessagePayload;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fmt::{self, Debug},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::{watch, Notify};
+
+/// A message waiting in the outbox to be sent.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueuedMessage {
+    /// The nonce identifying the message.
+    pub nonce: String,
+    /// The ID of the channel to send the message to.
+    pub channel_id: u64,
+    /// The payload of the message. Its nonce is always set to [`Self::nonce`].
+    pub payload: CreateMessagePayload,
+}
+
+/// A store in which queued messages are persisted, so that they survive a restart of the process.
+///
+/// The queue is loaded once the client starts, and saved whenever a message is queued, sent, or
+/// permanently rejected. These methods are called on a blocking thread, so implementations may
+/// block, for example on file I/O. Saves are not queued up: if a save is still in progress, only
+/// the latest queue is saved once it completes.
+pub trait OutboxStore: Send + Sync {
+    /// Loads all messages that are still queued, in the order they were queued.
+    ///
+    /// # Errors
+    /// * If the stored queue cannot be read. The client fails to start in this case, and nothing
+    ///   is saved to the store, so that the stored messages are not lost.
+    fn load(&self) -> std::io::Result<Vec<QueuedMessage>>;
+
+    /// Saves the given queued messages, replacing the previously saved queue.
+    fn save(&self, messages: &[QueuedMessage]);
+}
+
+/// An [`OutboxStore`] that persists queued messages as JSON in a file. The file is replaced
+/// atomically, so that it is never left partially written.
+#[derive(Clone, Debug)]
+pub struct FileOutboxStore {
+    path: PathBuf,
+}
+
+impl FileOutboxStore {
+    /// Creates a new file outbox store which stores queued messages at the given path.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl OutboxStore for FileOutboxStore {
+    fn load(&self) -> std::io::Result<Vec<QueuedMessage>> {
+        let contents = match std::fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        serde_json::from_slice(&contents)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+
+    fn save(&self, messages: &[QueuedMessage]) {
+        let contents = serde_json::to_vec(messages).expect("queued messages should serialize");
+        if let Err(err) = write_atomic(&self.path, &contents) {
+            warn!("Could not save outbox to {}: {err}", self.path.display());
+        }
+    }
+}
+
+struct OutboxInner {
+    store: Option<Arc<dyn OutboxStore>>,
+    queue: Mutex<VecDeque<QueuedMessage>>,
+    /// The latest snapshot of the queue, saved to the store in the background once the stored
+    /// queue has been restored.
+    snapshots: watch::Sender<Vec<QueuedMessage>>,
+    restored: AtomicBool,
+    notify: Notify,
+    running: AtomicBool,
+}
+
+impl OutboxInner {
+    /// Publishes a snapshot of the given queue to be saved to the store, if there is one.
+    fn persist(&self, queue: &VecDeque<QueuedMessage>) {
+        if self.store.is_some() {
+            self.snapshots.send_replace(queue.iter().cloned().collect());
+        }
+    }
+
+    fn complete(&self, nonce: &str) -> bool {
+        let mut queue = self.queue.lock().expect("poison");
+        let Some(index) = queue.iter().position(|message| message.nonce == nonce) else {
+            return false;
+        };
+        queue.remove(index);
+        self.persist(&queue);
+        true
+    }
+}
+
+/// Resets the running flag of the outbox when the sending task stops, so that it is restarted on
+/// the next use.
+struct RunningGuard(Arc<OutboxInner>);
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.0.running.store(false, Ordering::SeqCst);
+    }
+}
+
+/// The queue of messages sent through
+/// [`WithCtx<ChannelId>::send_queued`](crate::WithCtx::send_queued), shared by all contexts of a
+/// client.
+#[derive(Clone)]
+pub(crate) struct Outbox(Arc<OutboxInner>);
+
+impl Outbox {
+    /// The delay before retrying after the first failure. The delay doubles after every
+    /// consecutive failure, up to [`Self::MAX_BACKOFF`].
+    const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+    /// The maximum delay between retries.
+    const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    /// Creates a new, empty outbox persisted in the given store. Messages left in the store are
+    /// only added once it is [restored](Self::restore).
+    pub fn new(store: Option<Arc<dyn OutboxStore>>) -> Self {
+        Self(Arc::new(OutboxInner {
+            store,
+            queue: Mutex::default(),
+            snapshots: watch::Sender::new(Vec::new()),
+            restored: AtomicBool::new(false),
+            notify: Notify::new(),
+            running: AtomicBool::new(false),
+        }))
+    }
+
+    /// Adds the messages left in the store ahead of any messages queued since, and starts saving
+    /// the queue to the store. Does nothing if there is no store or it was already restored.
+    ///
+    /// # Errors
+    /// * If the stored queue cannot be loaded. Nothing is saved to the store in this case.
+    pub async fn restore(&self) -> crate::Result<()> {
+        let Some(store) = self.0.store.clone() else {
+            return Ok(());
+        };
+        if self.0.restored.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let loader = store.clone();
+        let stored = tokio::task::spawn_blocking(move || loader.load())
+            .await
+            .map_err(std::io::Error::other)??;
+        {
+            let mut queue = self.0.queue.lock().expect("poison");
+            let restored = stored
+                .into_iter()
+                .filter(|stored| queue.iter().all(|queued| queued.nonce != stored.nonce))
+                .collect::<Vec<_>>();
+            for message in restored.into_iter().rev() {
+                queue.push_front(message);
+            }
+            self.0.persist(&queue);
+        }
+        self.0.restored.store(true, Ordering::SeqCst);
+        self.0.notify.notify_one();
+
+        let mut snapshots = self.0.snapshots.subscribe();
+        snapshots.mark_changed();
+        // Runs until the outbox is dropped, saving the last snapshot before exiting
+        tokio::spawn(async move {
+            while snapshots.changed().await.is_ok() {
+                let snapshot = snapshots.borrow_and_update().clone();
+                let store = store.clone();
+                if let Err(err) = tokio::task::spawn_blocking(move || store.save(&snapshot)).await {
+                    warn!("Outbox store failed to save the queue: {err}");
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Generates a nonce which is unique within this process.
+    pub fn generate_nonce() -> String {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        format!("{time:x}-{:x}", COUNTER.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Queues the given message. Returns `false` if a message with the same nonce is already
+    /// queued, in which case nothing is queued.
+    pub fn enqueue(&self, message: QueuedMessage) -> bool {
+        {
+            let mut queue = self.0.queue.lock().expect("poison");
+            if queue.iter().any(|queued| queued.nonce == message.nonce) {
+                return false;
+            }
+            queue.push_back(message);
+            self.0.persist(&queue);
+        }
+        self.0.notify.notify_one();
+        true
+    }
+
+    /// Marks the message with the given nonce as sent, for example after it was echoed by the
+    /// gateway, so that it is not sent again.
+    pub fn acknowledge(&self, nonce: &str) {
+        if self.0.complete(nonce) {
+            debug!("Queued message {nonce} was acknowledged by the gateway");
+        }
+    }
+
+    /// Returns the number of messages that are still queued.
+    pub fn len(&self) -> usize {
+        self.0.queue.lock().expect("poison").len()
+    }
+
+    /// Starts sending queued messages in the background if this is not already happening. The
+    /// task is stopped when the client shuts down.
+    pub fn ensure_running(&self, ctx: &Context) {
+        if self.0.running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let guard = RunningGuard(self.0.clone());
+        let http = ctx.http().clone();
+        let _ = ctx.scheduler.spawn(async move {
+            Self::run(&http, &guard.0).await;
+        });
+    }
+
+    async fn run(http: &Http, inner: &OutboxInner) {
+        let mut backoff = Self::INITIAL_BACKOFF;
+        loop {
+            let next = inner.queue.lock().expect("poison").front().cloned();
+            let Some(message) = next else {
+                inner.notify.notified().await;
+                continue;
+            };
+
+            let result = http
+                .request(endpoints::CreateMessage(message.channel_id))
+                .body(message.payload)
+                .await;
+            match result {
+                Ok(_) => {
+                    inner.complete(&message.nonce);
+                    backoff = Self::INITIAL_BACKOFF;
+                }
+                Err(err) if err.is_ratelimited() => {
+                    tokio::time::sleep(err.retry_after().unwrap_or(backoff)).await;
+                }
+                Err(err) if is_transient(&err) => {
+                    warn!(
+                        "Could not send queued message {}, retrying in {backoff:?}: {err:?}",
+                        message.nonce,
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(Self::MAX_BACKOFF);
+                }
+                Err(err) if matches!(err, Error::Reqwest(_)) => {
+                    // The message may have been created even though no response was received, so
+                    // it is not retried to avoid sending it twice. The gateway may still echo it
+                    // in the meantime, which confirms that it was created.
+                    tokio::time::sleep(backoff).await;
+                    if inner.complete(&message.nonce) {
+                        warn!(
+                            "Dropping queued message {} which may not have been sent: {err:?}",
+                            message.nonce,
+                        );
+                    }
+                }
+                Err(err) => {
+                    warn!(
+                        "Dropping queued message {} after error: {err:?}",
+                        message.nonce
+                    );
+                    inner.complete(&message.nonce);
+                }
+            }
+        }
+    }
+}
+
+impl Debug for Outbox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Outbox")
+            .field("queued", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Whether sending a message failed for a reason that may go away by itself, without the message
+/// having been created.
+fn is_transient(err: &Error) -> bool {
+    match err {
+        // The request never reached the server
+        Error::Reqwest(err) => err.is_connect(),
+        Error::Http(essence::Error::InternalError { .. }) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(nonce: &str) -> QueuedMessage {
+        QueuedMessage {
+            nonce: nonce.to_string(),
+            channel_id: 1,
+            payload: CreateMessagePayload::default(),
+        }
+    }
+
+    /// A file store which reports the nonces of every saved queue.
+    struct ReportingStore(
+        FileOutboxStore,
+        tokio::sync::mpsc::UnboundedSender<Vec<String>>,
+    );
+
+    impl OutboxStore for ReportingStore {
+        fn load(&self) -> std::io::Result<Vec<QueuedMessage>> {
+            self.0.load()
+        }
+
+        fn save(&self, messages: &[QueuedMessage]) {
+            self.0.save(messages);
+            let nonces = messages
+                .iter()
+                .map(|message| message.nonce.clone())
+                .collect();
+            let _ = self.1.send(nonces);
+        }
+    }
+
+    fn temp_path() -> PathBuf {
+        std::env::temp_dir().join(format!("adapt-outbox-{}", Outbox::generate_nonce()))
+    }
+
+    #[tokio::test]
+    async fn deduplicate() {
+        let path = temp_path();
+        let (tx, mut saved) = tokio::sync::mpsc::unbounded_channel();
+        let store = ReportingStore(FileOutboxStore::new(&path), tx);
+
+        let outbox = Outbox::new(Some(Arc::new(store)));
+        assert!(outbox.enqueue(message("a")));
+        outbox.restore().await.unwrap();
+        assert!(outbox.enqueue(message("b")));
+        assert!(!outbox.enqueue(message("a")));
+        outbox.acknowledge("a");
+        while saved.recv().await.unwrap() != ["b"] {}
+
+        // A new outbox picks up where the previous one left off
+        let outbox = Outbox::new(Some(Arc::new(FileOutboxStore::new(&path))));
+        assert!(outbox.enqueue(message("c")));
+        outbox.restore().await.unwrap();
+        let queue = outbox.0.queue.lock().unwrap().clone();
+        let nonces = queue.iter().map(|message| message.nonce.as_str());
+        assert!(nonces.eq(["b", "c"]));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn corrupt_store_is_kept() {
+        let path = temp_path();
+        std::fs::write(&path, "not json").unwrap();
+
+        let outbox = Outbox::new(Some(Arc::new(FileOutboxStore::new(&path))));
+        assert!(outbox.enqueue(message("a")));
+        assert!(outbox.restore().await.is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), b"not json");
+        std::fs::remove_file(path).unwrap();
+    }
+}
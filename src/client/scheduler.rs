@@ -0,0 +1,117 @@
+use std::{future::Future, sync::Arc, time::Duration};
+use tokio::{
+    sync::watch,
+    task::AbortHandle,
+    time::{self, Instant, MissedTickBehavior},
+};
+
+/// A handle to a task scheduled through [`Context::schedule`](super::Context::schedule) or
+/// [`Context::schedule_every`](super::Context::schedule_every).
+///
+/// Dropping the handle does not cancel the task.
+#[derive(Clone, Debug)]
+#[must_use = "dropping the handle does not cancel the task"]
+pub struct ScheduledTask(AbortHandle);
+
+impl ScheduledTask {
+    /// Cancels the task. If the task is currently running, it is stopped at its next `.await`.
+    pub fn cancel(&self) {
+        self.0.abort();
+    }
+
+    /// Whether the task has finished, either by running to completion or by being cancelled.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.0.is_finished()
+    }
+}
+
+/// Spawns scheduled tasks and cancels all of them once the client shuts down.
+#[derive(Clone, Debug)]
+pub(crate) struct Scheduler {
+    shutdown: Arc<watch::Sender<bool>>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            shutdown: Arc::new(watch::Sender::new(false)),
+        }
+    }
+
+    /// Spawns the given future, which is cancelled when the scheduler shuts down.
+    pub fn spawn(&self, fut: impl Future<Output = ()> + Send + 'static) -> ScheduledTask {
+        let mut shutdown = self.shutdown.subscribe();
+        let handle = tokio::spawn(async move {
+            tokio::select! {
+                biased;
+                _ = shutdown.wait_for(|shutdown| *shutdown) => {}
+                () = fut => {}
+            }
+        });
+        ScheduledTask(handle.abort_handle())
+    }
+
+    pub fn schedule_at(
+        &self,
+        deadline: Instant,
+        fut: impl Future<Output = ()> + Send + 'static,
+    ) -> ScheduledTask {
+        self.spawn(async move {
+            time::sleep_until(deadline).await;
+            fut.await;
+        })
+    }
+
+    pub fn schedule_every<F, Fut>(&self, period: Duration, mut f: F) -> ScheduledTask
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.spawn(async move {
+            let mut interval = time::interval_at(Instant::now() + period, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                f().await;
+            }
+        })
+    }
+
+    /// Cancels all scheduled tasks, including tasks scheduled after this call.
+    pub fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn shutdown_cancels_tasks() {
+        let scheduler = Scheduler::new();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let counter = runs.clone();
+        let task = scheduler.schedule_every(Duration::from_millis(10), move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async {}
+        });
+        time::sleep(Duration::from_millis(50)).await;
+        assert!(runs.load(Ordering::SeqCst) >= 1);
+
+        scheduler.shutdown();
+        time::sleep(Duration::from_millis(10)).await;
+        assert!(task.is_finished());
+        let after_shutdown = runs.load(Ordering::SeqCst);
+
+        let counter = runs.clone();
+        let _ = scheduler.schedule_at(Instant::now(), async move {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), after_shutdown);
+    }
+}
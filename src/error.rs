@@ -16,6 +16,106 @@ pub enum Error {
     #[cfg(feature = "ws")]
     /// An error occured within Adapt's gateway.
     Harmony(crate::ws::Error),
+    /// The given token could not be parsed.
+    InvalidToken(crate::utils::token::TokenError),
+    /// An I/O error occured, for example while reading or writing a file.
+    Io(std::io::Error),
+    /// A download finished, but the number of bytes received does not match the size of the
+    /// object reported by the server.
+    IncompleteDownload {
+        /// The size of the object reported by the server.
+        expected: u64,
+        /// The number of bytes received.
+        received: u64,
+    },
+    /// A builder was given values that would be rejected by the Adapt API.
+    Validation(crate::builder::ValidationError),
+}
+
+impl Error {
+    /// Returns the error returned by the Adapt REST API, if this is an [`Error::Http`].
+    #[must_use]
+    pub const fn as_api_error(&self) -> Option<&essence::Error> {
+        match self {
+            Self::Http(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Returns the error code of the error returned by the Adapt REST API, for example
+    /// `"NotFound"` or `"MissingPermissions"`. This is `None` if this is not an [`Error::Http`].
+    #[must_use]
+    pub fn error_code(&self) -> Option<String> {
+        let value = serde_json::to_value(self.as_api_error()?).ok()?;
+        value.get("type")?.as_str().map(ToString::to_string)
+    }
+
+    /// Whether the requested entity could not be found.
+    #[must_use]
+    pub const fn is_not_found(&self) -> bool {
+        matches!(self, Self::Http(essence::Error::NotFound { .. }))
+    }
+
+    /// Whether the client is missing permissions to perform the request.
+    #[must_use]
+    pub const fn is_missing_permissions(&self) -> bool {
+        matches!(self, Self::Http(essence::Error::MissingPermissions { .. }))
+    }
+
+    /// Returns the permissions the client is missing to perform the request, if this is a missing
+    /// permissions error.
+    #[must_use]
+    pub const fn missing_permissions(&self) -> Option<essence::models::Permissions> {
+        match self {
+            Self::Http(essence::Error::MissingPermissions { permissions, .. }) => {
+                Some(*permissions)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the request was rejected because of invalid credentials or an invalid token.
+    #[must_use]
+    pub const fn is_unauthorized(&self) -> bool {
+        matches!(
+            self,
+            Self::Http(
+                essence::Error::InvalidToken { .. }
+                    | essence::Error::InvalidCredentials { .. }
+                    | essence::Error::Unauthorized { .. }
+            )
+        )
+    }
+
+    /// Whether the request requires the client to own the guild it acts on.
+    #[must_use]
+    pub const fn is_not_owner(&self) -> bool {
+        matches!(self, Self::Http(essence::Error::NotOwner { .. }))
+    }
+
+    /// Whether the request conflicts with an existing entity, for example a username that is
+    /// already taken.
+    #[must_use]
+    pub const fn is_already_exists(&self) -> bool {
+        matches!(self, Self::Http(essence::Error::AlreadyExists { .. }))
+    }
+
+    /// Whether the request was ratelimited.
+    #[must_use]
+    pub const fn is_ratelimited(&self) -> bool {
+        matches!(self, Self::Http(essence::Error::Ratelimited { .. }))
+    }
+
+    /// Returns how long to wait before retrying the request, if this is a ratelimit error.
+    #[must_use]
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            Self::Http(essence::Error::Ratelimited { retry_after, .. }) => {
+                Some(std::time::Duration::from_secs_f32(retry_after.max(0.0)))
+            }
+            _ => None,
+        }
+    }
 }
 
 impl From<reqwest::Error> for Error {
@@ -38,8 +138,47 @@ impl From<simd_json::Error> for Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<crate::builder::ValidationError> for Error {
+    fn from(err: crate::builder::ValidationError) -> Self {
+        Self::Validation(err)
+    }
+}
+
+impl From<crate::utils::token::TokenError> for Error {
+    fn from(err: crate::utils::token::TokenError) -> Self {
+        Self::InvalidToken(err)
+    }
+}
+
+#[cfg(feature = "ws")]
 impl From<crate::ws::Error> for Error {
     fn from(err: crate::ws::Error) -> Self {
         Self::Harmony(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_error_helpers() {
+        let err = Error::Http(essence::Error::NotFound {
+            entity: "user".to_string(),
+            message: "User not found".to_string(),
+        });
+        assert!(err.is_not_found());
+        assert!(!err.is_ratelimited());
+        assert_eq!(err.error_code().as_deref(), Some("NotFound"));
+
+        let err = Error::Io(std::io::ErrorKind::NotFound.into());
+        assert!(err.as_api_error().is_none());
+        assert!(err.error_code().is_none());
+    }
+}
@@ -0,0 +1,134 @@
+//! Caching of `GET` responses using conditional requests.
+
+use super::RawResponse;
+use bytes::Bytes;
+use reqwest::{
+    header::{HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    RequestBuilder,
+};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// A cached response body along with its validators.
+#[derive(Clone, Debug)]
+struct CachedResponse {
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+    body: Bytes,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    responses: HashMap<String, CachedResponse>,
+    /// Keys in insertion order, used to evict the oldest entries first.
+    order: VecDeque<String>,
+}
+
+/// Stores the ETag and Last-Modified validators of `GET` responses, and serves cached bodies when
+/// the server responds with `304 Not Modified` to a conditional request.
+#[derive(Debug)]
+pub(crate) struct ResponseCache {
+    entries: Mutex<Entries>,
+    capacity: usize,
+}
+
+impl ResponseCache {
+    /// Creates a new cache holding at most `capacity` responses.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::default(),
+            capacity,
+        }
+    }
+
+    /// Adds conditional headers to the request if a response is cached under the given key.
+    pub fn prepare(&self, key: &str, mut request: RequestBuilder) -> RequestBuilder {
+        let entries = self.entries.lock().expect("poison");
+        if let Some(cached) = entries.responses.get(key) {
+            if let Some(etag) = &cached.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        request
+    }
+
+    /// Resolves the response of a conditional request, substituting the cached body on a
+    /// `304 Not Modified` response and caching successful responses that carry validators.
+    ///
+    /// A `304 Not Modified` response is returned unchanged if its entry has since been evicted, in
+    /// which case the request must be resent without conditional headers.
+    pub fn resolve(&self, key: String, mut response: RawResponse) -> RawResponse {
+        let mut entries = self.entries.lock().expect("poison");
+
+        if response.status == 304 {
+            if let Some(cached) = entries.responses.get(&key) {
+                response.status = 200;
+                response.body = cached.body.clone();
+            }
+            return response;
+        }
+
+        if (200..300).contains(&response.status) {
+            let etag = response.headers.get(ETAG).cloned();
+            let last_modified = response.headers.get(LAST_MODIFIED).cloned();
+
+            if etag.is_none() && last_modified.is_none() {
+                if entries.responses.remove(&key).is_some() {
+                    entries.order.retain(|k| k != &key);
+                }
+                return response;
+            }
+
+            let cached = CachedResponse {
+                etag,
+                last_modified,
+                body: response.body.clone(),
+            };
+            if entries.responses.insert(key.clone(), cached).is_none() {
+                entries.order.push_back(key);
+            }
+            while entries.order.len() > self.capacity {
+                if let Some(oldest) = entries.order.pop_front() {
+                    entries.responses.remove(&oldest);
+                }
+            }
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderMap;
+
+    fn response(status: u16, etag: Option<&'static str>, body: &'static [u8]) -> RawResponse {
+        let mut headers = HeaderMap::new();
+        if let Some(etag) = etag {
+            headers.insert(ETAG, HeaderValue::from_static(etag));
+        }
+        RawResponse {
+            status,
+            headers,
+            body: Bytes::from_static(body),
+        }
+    }
+
+    #[test]
+    fn serve_cached_on_not_modified() {
+        let cache = ResponseCache::new(1);
+        cache.resolve("a".to_string(), response(200, Some("\"1\""), b"{}"));
+
+        let resolved = cache.resolve("a".to_string(), response(304, None, b""));
+        assert_eq!(resolved.status, 200);
+        assert_eq!(resolved.body, b"{}"[..]);
+
+        // Inserting another entry evicts the oldest one
+        cache.resolve("b".to_string(), response(200, Some("\"2\""), b"[]"));
+        let resolved = cache.resolve("a".to_string(), response(304, None, b""));
+        assert_eq!(resolved.status, 304);
+    }
+}
@@ -0,0 +1,114 @@
+//! Coalescing of concurrent, identical GET requests.
+
+use super::RawResponse;
+use futures_util::future::{BoxFuture, FutureExt, Shared};
+use reqwest::RequestBuilder;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::sync::Mutex;
+
+/// A response, or `None` if the request could not be sent.
+type SharedResponse = Shared<BoxFuture<'static, Option<RawResponse>>>;
+
+/// Tracks in-flight GET requests so that concurrent, identical requests are only sent once.
+#[derive(Default)]
+pub(crate) struct Coalescer {
+    in_flight: Mutex<HashMap<String, SharedResponse>>,
+}
+
+impl Debug for Coalescer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let in_flight = self.in_flight.lock().map_or(0, |in_flight| in_flight.len());
+        f.debug_struct("Coalescer")
+            .field("in_flight", &in_flight)
+            .finish()
+    }
+}
+
+impl Coalescer {
+    /// Sends the given request, or waits for an identical request already in flight under the
+    /// given key.
+    ///
+    /// If the shared request fails to send, each waiter falls back to sending its own request so
+    /// that the original error is surfaced.
+    pub async fn send(&self, key: String, request: RequestBuilder) -> crate::Result<RawResponse> {
+        let Some(shared) = request.try_clone() else {
+            return RawResponse::send(request).await;
+        };
+
+        let response = {
+            let mut in_flight = self.in_flight.lock().expect("poison");
+            in_flight
+                .entry(key.clone())
+                .or_insert_with(|| {
+                    async move { RawResponse::send(shared).await.ok() }
+                        .boxed()
+                        .shared()
+                })
+                .clone()
+        };
+
+        let result = response.clone().await;
+        {
+            let mut in_flight = self.in_flight.lock().expect("poison");
+            if in_flight
+                .get(&key)
+                .is_some_and(|current| current.ptr_eq(&response))
+            {
+                in_flight.remove(&key);
+            }
+        }
+
+        match result {
+            Some(response) => Ok(response),
+            None => RawResponse::send(request).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn coalesce_concurrent_requests() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = [0; 1024];
+                    let _ = stream.read(&mut buf).await;
+                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                    let _ = stream
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}",
+                        )
+                        .await;
+                });
+            }
+        });
+
+        let client = reqwest::Client::new();
+        let coalescer = Coalescer::default();
+        let url = format!("http://{addr}/users/42");
+        let (a, b) = tokio::join!(
+            coalescer.send(url.clone(), client.get(&url)),
+            coalescer.send(url.clone(), client.get(&url)),
+        );
+
+        assert_eq!(a.unwrap().body, b"{}"[..]);
+        assert_eq!(b.unwrap().body, b"{}"[..]);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+}
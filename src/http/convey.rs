@@ -0,0 +1,457 @@
+//! Interact with convey, Adapt's CDN.
+
+use super::{handle_response, refresh::SharedToken};
+use crate::Error;
+use bytes::Bytes;
+use essence::models::Attachment;
+use futures_util::{stream, Stream, TryStreamExt};
+use reqwest::{
+    header::{HeaderValue, AUTHORIZATION, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE},
+    multipart, Body, Client, StatusCode,
+};
+use std::{
+    fmt::{self, Debug},
+    future::{Future, IntoFuture},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tokio::{fs::OpenOptions, io::AsyncWriteExt};
+
+/// A callback invoked with the number of bytes sent so far and the total number of bytes.
+pub type ProgressCallback = Arc<dyn Fn(u64, u64) + Send + Sync>;
+
+/// A file to be uploaded to convey.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileUpload {
+    /// The name of the file, including its extension.
+    pub filename: String,
+    /// The contents of the file.
+    pub data: Bytes,
+}
+
+impl FileUpload {
+    /// Creates a new file upload from the given filename and contents.
+    pub fn from_bytes(filename: impl Into<String>, data: impl Into<Bytes>) -> Self {
+        Self {
+            filename: filename.into(),
+            data: data.into(),
+        }
+    }
+
+    /// Reads the file at the given path into a new file upload. The filename is taken from the
+    /// last component of the path.
+    pub async fn from_path(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let data = tokio::fs::read(path).await?;
+        let filename = path.file_name().map_or_else(
+            || "file".to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+
+        Ok(Self::from_bytes(filename, data))
+    }
+
+    /// The size of the file in bytes.
+    #[must_use]
+    pub fn size(&self) -> u64 {
+        self.data.len() as u64
+    }
+}
+
+/// The source of a file to upload, either a path to a file on disk or an in-memory
+/// [`FileUpload`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FileSource {
+    /// A path to a file, which is read when the file is uploaded.
+    Path(PathBuf),
+    /// A file which is already in memory.
+    Upload(FileUpload),
+}
+
+impl FileSource {
+    /// Resolves this source into a [`FileUpload`], reading the file from disk if necessary.
+    pub async fn into_upload(self) -> std::io::Result<FileUpload> {
+        match self {
+            Self::Path(path) => FileUpload::from_path(path).await,
+            Self::Upload(upload) => Ok(upload),
+        }
+    }
+}
+
+impl From<FileUpload> for FileSource {
+    fn from(upload: FileUpload) -> Self {
+        Self::Upload(upload)
+    }
+}
+
+impl From<PathBuf> for FileSource {
+    fn from(path: PathBuf) -> Self {
+        Self::Path(path)
+    }
+}
+
+impl From<&Path> for FileSource {
+    fn from(path: &Path) -> Self {
+        Self::Path(path.to_path_buf())
+    }
+}
+
+impl From<&str> for FileSource {
+    fn from(path: &str) -> Self {
+        Self::Path(PathBuf::from(path))
+    }
+}
+
+/// A client for convey, Adapt's CDN. Obtained with [`Http::convey`][super::Http::convey].
+#[derive(Copy, Clone, Debug)]
+pub struct Convey<'a> {
+    client: &'a Client,
+    server: &'a str,
+    token: &'a SharedToken,
+}
+
+impl<'a> Convey<'a> {
+    pub(super) const fn new(client: &'a Client, server: &'a str, token: &'a SharedToken) -> Self {
+        Self {
+            client,
+            server,
+            token,
+        }
+    }
+
+    /// Returns the base URL of convey.
+    #[must_use]
+    pub const fn url(&self) -> &'a str {
+        self.server
+    }
+
+    /// Returns the full URL of the object at the given path, relative to the base URL of convey.
+    #[must_use]
+    pub fn object_url(&self, path: impl AsRef<str>) -> String {
+        let path = path.as_ref();
+        let separator = if path.starts_with('/') { "" } else { "/" };
+        format!("{}{separator}{path}", self.server)
+    }
+
+    async fn get(&self, path: impl AsRef<str>) -> crate::Result<reqwest::Response> {
+        let response = self
+            .client
+            .get(self.object_url(path))
+            .header(AUTHORIZATION, self.token.expose())
+            .send()
+            .await?;
+
+        if let Err(err) = response.error_for_status_ref() {
+            // Prefer the error returned by the API, falling back to the status code
+            let api_error = handle_response::<serde::de::IgnoredAny>(response)
+                .await
+                .err();
+            return Err(api_error.unwrap_or_else(|| err.into()));
+        }
+        Ok(response)
+    }
+
+    /// Fetches the object at the given path (relative to the base URL of convey) into memory.
+    /// Paths are given by models, for example [`User::avatar`](crate::models::User::avatar).
+    pub async fn fetch(&self, path: impl AsRef<str>) -> crate::Result<Bytes> {
+        Ok(self.get(path).await?.bytes().await?)
+    }
+
+    /// Fetches the object at the given path (relative to the base URL of convey) as a stream of
+    /// chunks, without buffering the whole object in memory.
+    pub async fn fetch_stream(
+        &self,
+        path: impl AsRef<str>,
+    ) -> crate::Result<impl Stream<Item = crate::Result<Bytes>> + Send + 'static> {
+        let response = self.get(path).await?;
+        Ok(response.bytes_stream().map_err(Into::into))
+    }
+
+    /// Fetches the attachment with the given ID and filename into memory.
+    pub async fn fetch_attachment(&self, id: u64, filename: &str) -> crate::Result<Bytes> {
+        self.fetch(format!("/attachments/{id}/{filename}")).await
+    }
+
+    /// Fetches the image of the custom emoji with the given ID into memory.
+    pub async fn fetch_emoji(&self, id: u64) -> crate::Result<Bytes> {
+        self.fetch(format!("/emojis/{id}")).await
+    }
+
+    /// Creates a new request to download the object at the given path (relative to the base URL
+    /// of convey) into the file at `dest`.
+    ///
+    /// The download is resumable: interrupted transfers are resumed from where they left off.
+    /// Any existing contents of `dest` are replaced, since they cannot be verified to belong to
+    /// the same version of the object. See [`Download`] for more information.
+    pub fn download_resumable(
+        &self,
+        path: impl AsRef<str>,
+        dest: impl Into<PathBuf>,
+    ) -> Download<'a> {
+        Download {
+            convey: *self,
+            url: self.object_url(path),
+            dest: dest.into(),
+            max_retries: Download::DEFAULT_MAX_RETRIES,
+            on_progress: None,
+        }
+    }
+
+    /// Creates a new request to upload the given file as an attachment.
+    pub fn upload(&self, file: FileUpload) -> Upload<'a> {
+        Upload {
+            convey: *self,
+            file,
+            on_progress: None,
+        }
+    }
+}
+
+/// An outgoing request to upload a file to convey.
+#[must_use = "must .await the upload to send it"]
+pub struct Upload<'a> {
+    convey: Convey<'a>,
+    file: FileUpload,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl Debug for Upload<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Upload")
+            .field("filename", &self.file.filename)
+            .field("size", &self.file.size())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> IntoFuture for Upload<'a> {
+    type Output = crate::Result<Attachment>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'a>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.send())
+    }
+}
+
+impl Upload<'_> {
+    /// The size of each chunk sent when reporting progress.
+    pub const CHUNK_SIZE: usize = 64 * 1024;
+
+    /// Sets a callback that is invoked with the number of bytes sent so far and the total number
+    /// of bytes after each chunk of the file is sent.
+    pub fn on_progress(mut self, f: impl Fn(u64, u64) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Arc::new(f));
+        self
+    }
+
+    /// Sends the upload request.
+    pub async fn send(self) -> crate::Result<Attachment> {
+        let FileUpload { filename, data } = self.file;
+        let total = data.len() as u64;
+
+        let body = match self.on_progress {
+            Some(on_progress) => {
+                let sent = Arc::new(AtomicU64::new(0));
+                let chunks = (0..data.len())
+                    .step_by(Self::CHUNK_SIZE)
+                    .map(move |start| data.slice(start..data.len().min(start + Self::CHUNK_SIZE)))
+                    .map(move |chunk| {
+                        let sent = sent.fetch_add(chunk.len() as u64, Ordering::Relaxed)
+                            + chunk.len() as u64;
+                        on_progress(sent, total);
+                        Ok::<_, std::io::Error>(chunk)
+                    });
+
+                Body::wrap_stream(stream::iter(chunks))
+            }
+            None => Body::from(data),
+        };
+
+        let part = multipart::Part::stream_with_length(body, total).file_name(filename);
+        let response = self
+            .convey
+            .client
+            .post(self.convey.server.to_string() + "/attachments")
+            .header(AUTHORIZATION, self.convey.token.expose())
+            .multipart(multipart::Form::new().part("file", part))
+            .send()
+            .await?;
+
+        handle_response(response).await
+    }
+}
+
+/// An outgoing request to download an object from convey into a file, resuming interrupted
+/// transfers using HTTP range requests.
+///
+/// Integrity is verified in two ways:
+/// * The `ETag` of the object, or its `Last-Modified` date if it has no `ETag`, is sent in an
+///   `If-Range` header when resuming, so that if the object changed in the meantime, the server
+///   sends the whole object again rather than a range of the new one. If the server sends
+///   neither, the transfer is restarted from the beginning instead of resumed.
+/// * Once the transfer completes, the size of the file is checked against the size reported by
+///   the server, returning [`Error::IncompleteDownload`] on a mismatch. If the server does not
+///   report the size, for example for compressed responses, the end of the response marks the
+///   end of the object.
+#[must_use = "must .await the download to send it"]
+pub struct Download<'a> {
+    convey: Convey<'a>,
+    url: String,
+    dest: PathBuf,
+    max_retries: u32,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl Debug for Download<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Download")
+            .field("url", &self.url)
+            .field("dest", &self.dest)
+            .field("max_retries", &self.max_retries)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> IntoFuture for Download<'a> {
+    type Output = crate::Result<u64>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'a>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.send())
+    }
+}
+
+impl Download<'_> {
+    /// The default number of times an interrupted transfer is resumed before giving up.
+    pub const DEFAULT_MAX_RETRIES: u32 = 5;
+
+    /// Sets the number of times an interrupted transfer is resumed before giving up. Defaults to
+    /// [`Self::DEFAULT_MAX_RETRIES`].
+    pub const fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets a callback that is invoked with the number of bytes received so far and the total
+    /// number of bytes after each chunk of the object is written. The total is `0` if the server
+    /// does not report the size of the object.
+    pub fn on_progress(mut self, f: impl Fn(u64, u64) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Arc::new(f));
+        self
+    }
+
+    /// Sends the download request, returning the total size of the downloaded object in bytes.
+    pub async fn send(self) -> crate::Result<u64> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.dest)
+            .await?;
+        let mut received = file.metadata().await?.len();
+        let mut validator = None;
+        let mut attempt = 0;
+
+        loop {
+            match self.attempt(&mut file, &mut received, &mut validator).await {
+                Ok(Some(total)) if received == total => return Ok(total),
+                // Without a known size, the end of the response is the end of the object
+                Ok(None) => return Ok(received),
+                Ok(Some(total)) if attempt >= self.max_retries => {
+                    return Err(Error::IncompleteDownload {
+                        expected: total,
+                        received,
+                    });
+                }
+                Err(err) if attempt >= self.max_retries || !is_transient(&err) => return Err(err),
+                Ok(_) | Err(_) => {
+                    attempt += 1;
+                    debug!("Resuming download of {} at byte {received}", self.url);
+                }
+            }
+        }
+    }
+
+    /// Requests the remainder of the object and appends it to the file, returning the total size
+    /// of the object if the server reported it.
+    ///
+    /// `validator` is the `ETag` or `Last-Modified` value of the object from the previous attempt.
+    /// Without one, the existing contents of the file cannot be verified, so the whole object is
+    /// requested again.
+    async fn attempt(
+        &self,
+        file: &mut tokio::fs::File,
+        received: &mut u64,
+        validator: &mut Option<HeaderValue>,
+    ) -> crate::Result<Option<u64>> {
+        let mut request = self
+            .convey
+            .client
+            .get(&self.url)
+            .header(AUTHORIZATION, self.convey.token.expose());
+
+        match validator.as_ref() {
+            Some(validator) if *received > 0 => {
+                request = request
+                    .header(RANGE, format!("bytes={received}-"))
+                    .header(IF_RANGE, validator);
+            }
+            _ => {
+                file.set_len(0).await?;
+                *received = 0;
+            }
+        }
+
+        let mut response = request.send().await?;
+        let total = match response.status() {
+            StatusCode::PARTIAL_CONTENT => content_range_total(&response)
+                .or_else(|| Some(*received + response.content_length()?)),
+            StatusCode::RANGE_NOT_SATISFIABLE => {
+                // The file already contains the whole object
+                return Ok(Some(content_range_total(&response).unwrap_or(*received)));
+            }
+            status if status.is_success() => {
+                // The server sent the whole object, either because it does not support ranges or
+                // because the object changed since the last attempt
+                file.set_len(0).await?;
+                *received = 0;
+                response.content_length()
+            }
+            _ => return handle_response(response).await,
+        };
+        let headers = response.headers();
+        *validator = headers
+            .get(ETAG)
+            .or_else(|| headers.get(LAST_MODIFIED))
+            .cloned();
+
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk).await?;
+            *received += chunk.len() as u64;
+
+            if let Some(on_progress) = &self.on_progress {
+                on_progress(*received, total.unwrap_or(0));
+            }
+        }
+        file.flush().await?;
+
+        Ok(total)
+    }
+}
+
+/// Parses the total size of the object out of a `Content-Range` header.
+fn content_range_total(response: &reqwest::Response) -> Option<u64> {
+    let header = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    header.rsplit_once('/')?.1.parse().ok()
+}
+
+/// Whether the download should be resumed after the given error.
+fn is_transient(err: &Error) -> bool {
+    match err {
+        Error::Reqwest(err) => err.is_timeout() || err.is_connect() || err.is_body(),
+        _ => false,
+    }
+}
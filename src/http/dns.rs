@@ -0,0 +1,138 @@
+//! DNS resolution controls for the HTTP client, for example to force a single IP version or to
+//! resolve the hostname of a self-hosted instance to a private address in split-horizon setups.
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::{collections::HashMap, net::SocketAddr};
+
+/// Which IP versions the HTTP client connects over.
+///
+/// When addresses of both versions are available, the client races connections to them
+/// ("happy eyeballs"), starting with the version the resolver returned first. The `Prefer*`
+/// variants control which version is tried first, and the `*Only` variants disable the fallback
+/// entirely.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum IpPreference {
+    /// Use addresses of both versions in the order they are resolved. This is the default.
+    #[default]
+    Any,
+    /// Only connect over IPv4.
+    Ipv4Only,
+    /// Only connect over IPv6.
+    Ipv6Only,
+    /// Try IPv4 first, falling back to IPv6.
+    PreferIpv4,
+    /// Try IPv6 first, falling back to IPv4.
+    PreferIpv6,
+}
+
+impl IpPreference {
+    /// Filters and orders the given addresses according to this preference.
+    fn arrange(self, mut addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+        match self {
+            Self::Any => {}
+            Self::Ipv4Only => addrs.retain(SocketAddr::is_ipv4),
+            Self::Ipv6Only => addrs.retain(SocketAddr::is_ipv6),
+            // Stable sorts keep the resolved order within each version
+            Self::PreferIpv4 => addrs.sort_by_key(SocketAddr::is_ipv6),
+            Self::PreferIpv6 => addrs.sort_by_key(SocketAddr::is_ipv4),
+        }
+        addrs
+    }
+}
+
+/// DNS options for the HTTP client. See [`Http::with_dns`](super::Http::with_dns).
+///
+/// # Example
+/// ```no_run
+/// use adapt::http::{dns::{DnsOptions, IpPreference}, Http};
+///
+/// let dns = DnsOptions::new()
+///     .ip_preference(IpPreference::Ipv4Only)
+///     .override_host("adapt.internal", ["10.0.0.5:443".parse().unwrap()]);
+/// let http = Http::from_token_and_uri("token", "https://adapt.internal").with_dns(dns);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[must_use]
+pub struct DnsOptions {
+    ip_preference: IpPreference,
+    overrides: HashMap<String, Vec<SocketAddr>>,
+}
+
+impl DnsOptions {
+    /// Creates new DNS options which resolve hostnames with the system resolver.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets which IP versions are connected over. Defaults to [`IpPreference::Any`].
+    pub const fn ip_preference(mut self, preference: IpPreference) -> Self {
+        self.ip_preference = preference;
+        self
+    }
+
+    /// Resolves the given hostname to the given addresses instead of querying DNS. A port of `0`
+    /// is replaced by the port of the request URL.
+    ///
+    /// The [`IpPreference`] also applies to overridden addresses.
+    pub fn override_host(
+        mut self,
+        host: impl Into<String>,
+        addrs: impl IntoIterator<Item = SocketAddr>,
+    ) -> Self {
+        self.overrides.insert(
+            host.into().to_ascii_lowercase(),
+            addrs.into_iter().collect(),
+        );
+        self
+    }
+}
+
+/// Resolves hostnames according to [`DnsOptions`].
+#[derive(Debug)]
+pub(crate) struct Resolver(pub DnsOptions);
+
+impl Resolve for Resolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_ascii_lowercase();
+        let overridden = self.0.overrides.get(&host).cloned();
+        let preference = self.0.ip_preference;
+
+        Box::pin(async move {
+            let addrs = match overridden {
+                Some(addrs) => addrs,
+                None => tokio::net::lookup_host((host.as_str(), 0)).await?.collect(),
+            };
+            let addrs = preference.arrange(addrs);
+            if addrs.is_empty() {
+                return Err(format!("no addresses for {host} match {preference:?}").into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn resolve_overrides() {
+        let v4 = "10.0.0.5:0".parse().unwrap();
+        let v6 = "[fd00::5]:0".parse().unwrap();
+        let addrs = vec![v6, v4];
+
+        assert_eq!(IpPreference::Any.arrange(addrs.clone()), [v6, v4]);
+        assert_eq!(IpPreference::PreferIpv4.arrange(addrs.clone()), [v4, v6]);
+        assert_eq!(IpPreference::Ipv6Only.arrange(addrs.clone()), [v6]);
+
+        let options = DnsOptions::new()
+            .ip_preference(IpPreference::Ipv4Only)
+            .override_host("Adapt.Internal", addrs);
+        let resolver = Resolver(options);
+        let resolved = resolver
+            .resolve("adapt.internal".parse().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resolved.collect::<Vec<_>>(), [v4]);
+    }
+}
@@ -50,6 +50,8 @@ endpoints! {
     GetGuildChannels(guild_id: u64) resp(Vec<models::Channel>) = GET "/guilds/{guild_id}/channels";
     CreateGuildChannel(guild_id: u64)
         body(channel::CreateGuildChannelPayload) resp(models::Channel) = POST "/guilds/{guild_id}/channels";
+    EditChannelPositions(guild_id: u64) body(Vec<u64>) = PATCH "/guilds/{guild_id}/channels";
+    TriggerTyping(channel_id: u64) = POST "/channels/{channel_id}/typing";
 
     // Messages
     GetMessageHistory(channel_id: u64)
@@ -63,11 +65,15 @@ endpoints! {
     DeleteMessage(channel_id: u64, message_id: u64) = DELETE "/channels/{channel_id}/messages/{message_id}";
     PinMessage(channel_id: u64, message_id: u64) = PUT "/channels/{channel_id}/messages/{message_id}/pin";
     UnpinMessage(channel_id: u64, message_id: u64) = DELETE "/channels/{channel_id}/messages/{message_id}/pin";
+    AddReaction<'a>(channel_id: u64, message_id: u64, emoji: &'a str)
+        = PUT "/channels/{channel_id}/messages/{message_id}/reactions/{emoji}";
+    RemoveOwnReaction<'a>(channel_id: u64, message_id: u64, emoji: &'a str)
+        = DELETE "/channels/{channel_id}/messages/{message_id}/reactions/{emoji}";
 
     // Guilds
     GetAllGuilds query(guild::GetGuildQuery) resp(Vec<models::Guild>) = GET "/guilds";
     CreateGuild body(guild::CreateGuildPayload) resp(models::Guild) = POST "/guilds";
-    GetGuild(guild_id: u64) resp(models::Guild) = GET "/guilds/{guild_id}";
+    GetGuild(guild_id: u64) query(guild::GetGuildQuery) resp(models::Guild) = GET "/guilds/{guild_id}";
     EditGuild(guild_id: u64) body(guild::EditGuildPayload) resp(models::Guild) = PATCH "/guilds/{guild_id}";
     DeleteGuild(guild_id: u64) body(guild::DeleteGuildPayload) = DELETE "/guilds/{guild_id}";
 
@@ -115,7 +121,8 @@ endpoints! {
     // Relationships
     GetRelationships resp(Vec<models::Relationship>) = GET "/relationships";
     BlockUser(target_id: u64) resp(models::Relationship) = PUT "/relationships/blocks/{target_id}";
-    SendFriendRequest resp(models::Relationship) = POST "/relationships/friends";
+    SendFriendRequest
+        body(user::SendFriendRequestPayload) resp(models::Relationship) = POST "/relationships/friends";
     AcceptFriendRequest(target_id: u64) resp(models::Relationship) = PUT "/relationships/friends/{target_id}";
     DeleteRelationship(target_id: u64) = DELETE "/relationships/{target_id}";
 
@@ -125,10 +132,74 @@ endpoints! {
     GetAuthenticatedUser resp(models::ClientUser) = GET "/users/me";
     EditUser resp(models::ClientUser) = PATCH "/users/me";
     DeleteUser = DELETE "/users/me";
+    GetDmChannels resp(Vec<models::DmChannel>) = GET "/users/me/channels";
+    CreateDmChannel
+        body(channel::CreateDmChannelPayload) resp(models::DmChannel) = POST "/users/me/channels";
     GetUser(user_id: u64) resp(models::User) = GET "/users/{user_id}";
 }
 
+/// Declares a custom REST endpoint, implementing [`Endpoint`] for it.
+///
+/// This is useful for declaring endpoints that are not modeled by this crate, such as endpoints
+/// specific to an extended instance of Adapt. Parameters are declared similarly to function
+/// parameters and become the fields of a tuple struct; they are interpolated into the path by name.
+///
+/// Query parameters, the request body, and the response are declared with `query(...)`,
+/// `body(...)` and `resp(...)` respectively, in that order. All of them are optional and default to
+/// `()`.
+///
+/// # Example
+/// ```no_run
+/// use adapt::essence::models::Message;
+/// use adapt::http::Http;
+///
+/// adapt::endpoint! {
+///     /// Gets the pinned messages in a channel.
+///     pub struct GetPinnedMessages(channel_id: u64)
+///         resp(Vec<Message>) = GET "/channels/{channel_id}/pins";
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> adapt::Result<()> {
+///     let token = std::env::var("ADAPT_TOKEN").expect("missing Adapt token");
+///     let http = Http::from_token(token);
+///
+///     let pins = http.request(GetPinnedMessages(123456789)).await?;
+///     println!("There are {} pinned messages", pins.len());
+///     Ok(())
+/// }
+/// ```
+#[macro_export]
+macro_rules! endpoint {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident $(<$($lt:lifetime),+>)? $(($($param:ident: $ty:ty),+ $(,)?))?
+        $(query($query:ty))? $(body($body:ty))? $(resp($resp:ty))? = $method:ident $path:literal;
+    ) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+        $vis struct $name $(<$($lt),+>)? $(( $(pub $ty),+ ))?;
+
+        impl $(<$($lt),+>)? $crate::http::endpoints::Endpoint for $name $(<$($lt),+>)? {
+            const METHOD: $crate::http::Method = $crate::http::Method::$method;
+            const PATH: &'static str = $path;
+
+            type Query = ($($query)?);
+            type Body = ($($body)?);
+            type Response = ($($resp)?);
+
+            #[inline]
+            fn path(&self) -> String {
+                $(let Self($($param),+) = *self;)?
+                format!($path, $($($param = $param),+)?)
+            }
+        }
+    };
+}
+
 /// Any REST endpoint.
+///
+/// To declare your own endpoints, see the [`endpoint!`][crate::endpoint] macro.
 pub trait Endpoint: Copy + Clone + PartialEq + Eq + Send + Sync {
     /// The HTTP method of the endpoint.
     const METHOD: reqwest::Method;
@@ -0,0 +1,163 @@
+//! Limiting of concurrent requests by priority.
+
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::oneshot;
+
+/// The priority of a request. Once the concurrency limit of a client is reached, waiting requests
+/// are sent in order of priority, and in the order they were made within the same priority. See
+/// [`Http::with_concurrency_limit`](super::Http::with_concurrency_limit).
+///
+/// Requests are [`Priority::Normal`] by default. Set the priority of a request with
+/// [`Request::priority`](super::Request::priority).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Bulk work which can wait for other requests, such as purging messages or backfilling
+    /// history.
+    Background,
+    /// The default priority.
+    #[default]
+    Normal,
+    /// Requests a user is waiting on, such as responses to commands.
+    Interactive,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    in_flight: usize,
+    /// Waiting requests, highest priority first and then in the order they were made.
+    waiting: BTreeMap<(Reverse<Priority>, u64), oneshot::Sender<()>>,
+    next_ticket: u64,
+}
+
+/// Limits the number of requests in flight at once, handing freed slots to the waiting request
+/// with the highest priority.
+#[derive(Debug)]
+pub(crate) struct Limiter {
+    limit: usize,
+    state: Mutex<State>,
+}
+
+impl Limiter {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Waits for a free slot, which is held until the returned permit is dropped.
+    pub async fn acquire(self: &Arc<Self>, priority: Priority) -> Permit {
+        let rx = {
+            let mut state = self.state.lock().expect("poison");
+            if state.in_flight < self.limit {
+                state.in_flight += 1;
+                return Permit(self.clone());
+            }
+
+            let (tx, rx) = oneshot::channel();
+            let ticket = state.next_ticket;
+            state.next_ticket += 1;
+            state.waiting.insert((Reverse(priority), ticket), tx);
+            rx
+        };
+
+        let mut waiter = Waiter {
+            limiter: self,
+            rx: Some(rx),
+        };
+        if let Some(rx) = &mut waiter.rx {
+            // The sender is only dropped without sending if the limiter is dropped, which cannot
+            // happen while this request holds a reference to it
+            let _ = rx.await;
+        }
+        waiter.rx = None;
+        Permit(self.clone())
+    }
+
+    /// Hands the slot of a finished request to the next waiting request, or frees it.
+    fn release(&self) {
+        let mut state = self.state.lock().expect("poison");
+        while let Some((_, tx)) = state.waiting.pop_first() {
+            // Fails if the waiting request was cancelled
+            if tx.send(()).is_ok() {
+                return;
+            }
+        }
+        state.in_flight -= 1;
+    }
+}
+
+/// A slot of a [`Limiter`], released when dropped.
+#[derive(Debug)]
+pub(crate) struct Permit(Arc<Limiter>);
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.0.release();
+    }
+}
+
+/// A request waiting for a slot. If the request is cancelled after it was handed a slot, the slot
+/// is passed on.
+struct Waiter<'a> {
+    limiter: &'a Limiter,
+    rx: Option<oneshot::Receiver<()>>,
+}
+
+impl Drop for Waiter<'_> {
+    fn drop(&mut self) {
+        if let Some(mut rx) = self.rx.take() {
+            rx.close();
+            if rx.try_recv().is_ok() {
+                self.limiter.release();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn higher_priority_first() {
+        let limiter = Arc::new(Limiter::new(1));
+        let permit = limiter.acquire(Priority::Normal).await;
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        for priority in [
+            Priority::Background,
+            Priority::Interactive,
+            Priority::Normal,
+        ] {
+            let limiter = limiter.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let _permit = limiter.acquire(priority).await;
+                tx.send(priority).unwrap();
+            });
+        }
+        // With time paused, the sleep only completes once every waiter is queued
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(permit);
+
+        let mut order = Vec::new();
+        for _ in 0..3 {
+            order.push(rx.recv().await.unwrap());
+        }
+        assert_eq!(
+            order,
+            [
+                Priority::Interactive,
+                Priority::Normal,
+                Priority::Background
+            ]
+        );
+        assert_eq!(limiter.state.lock().unwrap().in_flight, 0);
+    }
+}
@@ -1,37 +1,88 @@
+mod cache;
+mod coalesce;
+pub mod convey;
+pub mod dns;
 pub mod endpoints;
+mod limit;
+mod refresh;
+pub mod retry;
+pub mod webhook;
 
-use crate::{Error, Server};
-use bytes::Buf;
+use crate::{models::UserId, tls::TlsOptions, utils::token::TokenError, Error, Server};
+use bytes::{Buf, Bytes};
+use cache::ResponseCache;
+use coalesce::Coalescer;
+use convey::{Convey, FileUpload};
+use dns::DnsOptions;
 use endpoints::Endpoint;
 use essence::http;
+use limit::Limiter;
+use refresh::{SharedToken, TokenRefresh};
 use reqwest::{
-    header::{HeaderMap, HeaderName, AUTHORIZATION},
+    header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION},
     Client,
 };
-use secrecy::{ExposeSecret, SecretString};
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
 #[cfg(not(feature = "simd"))]
 use serde_json as json;
 #[cfg(feature = "simd")]
 use simd_json as json;
 use std::{
     future::{Future, IntoFuture},
+    marker::PhantomData,
     pin::Pin,
+    sync::Arc,
 };
 
 pub use http::auth::TokenRetrievalMethod;
+pub use limit::Priority;
+pub use reqwest::{header, Method};
+pub use retry::{DefaultRetryPolicy, FailedRequest, NeverRetry, RetryDecision, RetryPolicy};
+pub use webhook::WebhookClient;
 
 /// A utility constant which is the base URL for the production (main) server of Adapt's API.
 pub const BASE_URL: &str = Server::production().api;
 
-/// Wrapper type around a valid URL for the Adapt REST API.
-/// Defaults to the official instance (`https://api.adapt.chat`).
+/// A version of the Adapt REST API, which prefixes the path of every request (e.g. `/v1`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ApiVersion(u16);
+
+impl ApiVersion {
+    /// Version 1 of the API.
+    pub const V1: Self = Self(1);
+
+    /// Creates a new API version from its number.
+    #[inline]
+    #[must_use]
+    pub const fn new(version: u16) -> Self {
+        Self(version)
+    }
+
+    /// Returns the number of this version.
+    #[inline]
+    #[must_use]
+    pub const fn get(&self) -> u16 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+/// Wrapper type around a valid URL for the Adapt REST API, optionally pinned to an
+/// [`ApiVersion`]. Defaults to the official instance (`https://api.adapt.chat`) with unversioned
+/// paths.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct BaseUrl<'a>(&'a str);
+pub struct BaseUrl<'a>(&'a str, Option<ApiVersion>);
 
 impl Default for BaseUrl<'static> {
     #[inline]
     fn default() -> Self {
-        Self(BASE_URL)
+        Self(BASE_URL, None)
     }
 }
 
@@ -42,12 +93,27 @@ impl<'a> BaseUrl<'a> {
     pub const fn get(&self) -> &'a str {
         self.0
     }
+
+    /// Returns the API version paths are prefixed with, if any.
+    #[inline]
+    #[must_use]
+    pub const fn version(&self) -> Option<ApiVersion> {
+        self.1
+    }
+
+    /// Pins this base URL to the given API version, prefixing the path of every request with it.
+    #[inline]
+    #[must_use]
+    pub const fn with_version(mut self, version: ApiVersion) -> Self {
+        self.1 = Some(version);
+        self
+    }
 }
 
 impl<'a> From<&'a str> for BaseUrl<'a> {
     #[inline]
     fn from(uri: &'a str) -> Self {
-        Self(uri)
+        Self(uri, None)
     }
 }
 
@@ -61,7 +127,7 @@ impl<'a> From<BaseUrl<'a>> for &'a str {
 impl<'a> From<Server<'a>> for BaseUrl<'a> {
     #[inline]
     fn from(server: Server<'a>) -> Self {
-        Self(server.api)
+        Self(server.api, None)
     }
 }
 
@@ -69,12 +135,12 @@ impl<'a> From<Server<'a>> for BaseUrl<'a> {
 #[derive(Clone, Debug)]
 #[must_use = "must .await the request to send it"]
 pub struct Request<'a, E: Endpoint> {
-    client: &'a Client,
-    server: &'a str,
+    http: &'a Http,
     endpoint: E,
     query: Option<E::Query>,
     body: Option<E::Body>,
     headers: HeaderMap,
+    priority: Priority,
 }
 
 impl<'a, E: Endpoint + 'a> IntoFuture for Request<'a, E> {
@@ -88,20 +154,45 @@ impl<'a, E: Endpoint + 'a> IntoFuture for Request<'a, E> {
 
 impl<'a, E: Endpoint> Request<'a, E> {
     /// Creates a new intermediate request.
-    pub(super) fn new(client: &'a Client, server: &'a str, endpoint: E) -> Self {
+    pub(super) fn new(http: &'a Http, endpoint: E) -> Self {
         Self {
-            client,
-            server,
+            http,
             endpoint,
             query: None,
             body: None,
             headers: HeaderMap::new(),
+            priority: Priority::default(),
         }
     }
 
-    /// Adds a header to the request.
-    pub fn header(mut self, key: HeaderName, value: &str) -> Self {
-        self.headers.insert(key, value.parse().unwrap());
+    /// Sets a header on the request, replacing any value it was set to before, including the
+    /// default `Authorization` header. Values can be given as strings or as typed
+    /// [`HeaderValue`]s. Use [`Self::append_header`] to send several values.
+    ///
+    /// # Panics
+    /// * If the value is not a valid header value.
+    pub fn header<V>(mut self, key: HeaderName, value: V) -> Self
+    where
+        V: TryInto<HeaderValue>,
+        V::Error: std::fmt::Debug,
+    {
+        self.headers
+            .insert(key, value.try_into().expect("invalid header value"));
+        self
+    }
+
+    /// Adds a value to a header of the request, keeping any values it was set to before, so that
+    /// all of them are sent.
+    ///
+    /// # Panics
+    /// * If the value is not a valid header value.
+    pub fn append_header<V>(mut self, key: HeaderName, value: V) -> Self
+    where
+        V: TryInto<HeaderValue>,
+        V::Error: std::fmt::Debug,
+    {
+        self.headers
+            .append(key, value.try_into().expect("invalid header value"));
         self
     }
 
@@ -117,35 +208,382 @@ impl<'a, E: Endpoint> Request<'a, E> {
         self
     }
 
+    /// Sets the priority of the request, which decides the order in which waiting requests are
+    /// sent once the concurrency limit of the client is reached. Defaults to
+    /// [`Priority::Normal`]. See [`Http::with_concurrency_limit`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use adapt::http::{endpoints, Http, Priority};
+    ///
+    /// # async fn run(http: Http) -> adapt::Result<()> {
+    /// // Deleting old messages should not delay responses to commands
+    /// http.request(endpoints::DeleteMessage(123, 456))
+    ///     .priority(Priority::Background)
+    ///     .await?;
+    /// # Ok(()) }
+    /// ```
+    pub const fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
     /// Sends the request.
-    pub async fn send(self) -> crate::Result<E::Response> {
+    ///
+    /// Failed requests are retried according to the [`RetryPolicy`] of the [`Http`] client. For
+    /// `GET` requests, this also takes into account request coalescing and response caching if
+    /// they are enabled.
+    ///
+    /// # Panics
+    /// * If the body cannot be serialized.
+    pub async fn send(mut self) -> crate::Result<E::Response> {
+        let path = self.endpoint.path();
+        let body = self
+            .body
+            .as_ref()
+            .map(|body| json::to_string(body).unwrap());
+
+        let mut attempt = 1;
+        let mut refreshed = false;
+        loop {
+            let permit = self.acquire().await;
+            let result = self.send_once(&path, body.clone()).await;
+            drop(permit);
+
+            let error = match result {
+                Ok(response) => return Ok(response),
+                Err(error) => error,
+            };
+            if !refreshed && is_token_rejected(&error) {
+                refreshed = true;
+                if self.refresh_token().await? {
+                    debug!("Retrying {} {path} with a refreshed token", E::METHOD);
+                    continue;
+                }
+            }
+            let failed = FailedRequest {
+                attempt,
+                method: &E::METHOD,
+                path: &path,
+                error: &error,
+            };
+
+            match self.http.retry_policy.decide(&failed) {
+                RetryDecision::Retry(delay) => {
+                    debug!(
+                        "Retrying {} {path} in {delay:?} after error: {error:?}",
+                        E::METHOD
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                RetryDecision::Stop => return Err(error),
+            }
+        }
+    }
+
+    /// Replaces the token of this request with a fresh one after it was rejected as unauthorized,
+    /// obtaining it through the token refresh hook of the client unless another request already
+    /// did. Returns whether the request should be retried.
+    ///
+    /// # Errors
+    /// * If the refreshed token cannot be used as a header value. The token of the client is
+    ///   left unchanged in that case.
+    async fn refresh_token(&mut self) -> crate::Result<bool> {
+        let Some(refresh) = &self.http.token_refresh else {
+            return Ok(false);
+        };
+
+        let current = self.http.token.expose();
+        let header = if self
+            .headers
+            .get(AUTHORIZATION)
+            .is_some_and(|sent| sent != current.as_str())
+        {
+            HeaderValue::try_from(current).map_err(|_| TokenError::InvalidCharacters)?
+        } else {
+            let Some(token) = refresh.call().await else {
+                return Ok(false);
+            };
+            let token = token.trim().to_string();
+            let header =
+                HeaderValue::try_from(token.as_str()).map_err(|_| TokenError::InvalidCharacters)?;
+            self.http.token.set(token);
+            header
+        };
+
+        self.headers.insert(AUTHORIZATION, header);
+        Ok(true)
+    }
+
+    /// Waits for the concurrency limit of the client to allow this request, if there is one.
+    async fn acquire(&self) -> Option<limit::Permit> {
+        match &self.http.limiter {
+            Some(limiter) => Some(limiter.acquire(self.priority).await),
+            None => None,
+        }
+    }
+
+    async fn send_once(&self, path: &str, body: Option<String>) -> crate::Result<E::Response> {
         let mut request = self
+            .http
             .client
-            .request(E::METHOD, self.server.to_string() + &self.endpoint.path())
-            .headers(self.headers);
+            .request(E::METHOD, self.http.base_url() + path)
+            .headers(self.headers.clone());
 
-        if let Some(query) = self.query {
-            request = request.query(&query);
+        if let Some(query) = &self.query {
+            request = request.query(query);
         }
 
-        if let Some(body) = self.body {
-            let body = json::to_string(&body).unwrap();
+        let coalescer = self.http.coalescer.as_deref();
+        let cache = self.http.cache.as_deref();
+        if E::METHOD == Method::GET && (coalescer.is_some() || cache.is_some()) {
+            // Identical requests resolve to the same URL and headers
+            if let Some(built) = request.try_clone().and_then(|request| request.build().ok()) {
+                let key = format!("{}\n{:?}", built.url(), self.headers);
+                // Resent if the cached body is evicted before the response arrives
+                let unconditional = cache.and_then(|_| request.try_clone());
+                if let Some(cache) = cache {
+                    request = cache.prepare(&key, request);
+                }
+
+                let mut response = match coalescer {
+                    Some(coalescer) => coalescer.send(key.clone(), request).await?,
+                    None => RawResponse::send(request).await?,
+                };
+                if let Some(cache) = cache {
+                    response = cache.resolve(key.clone(), response);
+                    if let (304, Some(request)) = (response.status, unconditional) {
+                        response = cache.resolve(key, RawResponse::send(request).await?);
+                    }
+                }
+                return parse_response(response.status, response.body);
+            }
+        }
 
+        if let Some(body) = body {
             request = request
                 .body(body)
                 .header("Content-Type", "application/json");
         }
 
+        handle_response(request.send().await?).await
+    }
+
+    /// Sends the request as a multipart form along with the given files. The JSON body is sent in
+    /// a part named `json`, and each file in a part named `files`.
+    ///
+    /// Unlike [`Self::send`], requests with files are never retried.
+    ///
+    /// # Panics
+    /// * If the body cannot be serialized.
+    pub async fn send_with_files(
+        self,
+        files: impl IntoIterator<Item = FileUpload> + Send,
+    ) -> crate::Result<E::Response> {
+        let form = multipart_form(self.body.as_ref(), files)?;
+        let _permit = self.acquire().await;
+        let mut request = self
+            .http
+            .client
+            .request(E::METHOD, self.http.base_url() + &self.endpoint.path())
+            .headers(self.headers);
+        if let Some(query) = &self.query {
+            request = request.query(query);
+        }
+
+        handle_response(request.multipart(form).send().await?).await
+    }
+}
+
+/// Builds a multipart form with the given body as a `json` part, and each file as a `files` part.
+///
+/// # Panics
+/// * If the body cannot be serialized.
+fn multipart_form(
+    body: Option<&impl Serialize>,
+    files: impl IntoIterator<Item = FileUpload>,
+) -> crate::Result<reqwest::multipart::Form> {
+    let mut form = reqwest::multipart::Form::new();
+    if let Some(body) = body {
+        let part = reqwest::multipart::Part::text(json::to_string(body).unwrap())
+            .mime_str("application/json")?;
+        form = form.part("json", part);
+    }
+    for file in files {
+        let part = reqwest::multipart::Part::stream_with_length(file.data.clone(), file.size())
+            .file_name(file.filename);
+        form = form.part("files", part);
+    }
+    Ok(form)
+}
+
+/// Whether the request was rejected because the token is invalid, in which case a refreshed token
+/// may succeed.
+const fn is_token_rejected(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::Http(essence::Error::InvalidToken { .. } | essence::Error::Unauthorized { .. })
+    )
+}
+
+/// A fully received response, which can be shared between coalesced requests and cached.
+#[derive(Clone, Debug)]
+pub(crate) struct RawResponse {
+    status: u16,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl RawResponse {
+    /// Sends the request and receives the full response.
+    async fn send(request: reqwest::RequestBuilder) -> crate::Result<Self> {
         let response = request.send().await?;
-        let status = response.status().as_u16();
-        let reader = response.bytes().await?.reader();
+        Ok(Self {
+            status: response.status().as_u16(),
+            headers: response.headers().clone(),
+            body: response.bytes().await?,
+        })
+    }
+}
+
+/// Deserializes the body of a response, or returns an [`Error::Http`] if the response has an
+/// error status code.
+async fn handle_response<T: for<'a> Deserialize<'a>>(
+    response: reqwest::Response,
+) -> crate::Result<T> {
+    let status = response.status().as_u16();
+    parse_response(status, response.bytes().await?)
+}
 
-        if (400..=599).contains(&status) {
-            let error = json::from_reader(reader)?;
-            return Err(Error::Http(error));
+/// Deserializes a response body with the given status code, or returns an [`Error::Http`] if the
+/// status code is an error.
+///
+/// Empty bodies, such as those of `204 No Content` responses, are deserialized as `null`.
+fn parse_response<T: for<'a> Deserialize<'a>>(status: u16, bytes: Bytes) -> crate::Result<T> {
+    let bytes = if bytes.is_empty() {
+        Bytes::from_static(b"null")
+    } else {
+        bytes
+    };
+    let reader = bytes.reader();
+
+    if (400..=599).contains(&status) {
+        let error = json::from_reader(reader)?;
+        return Err(Error::Http(error));
+    }
+
+    json::from_reader(reader).map_err(Into::into)
+}
+
+/// An outgoing HTTP request to an arbitrary path, which is not modeled by an [`Endpoint`].
+///
+/// The response is deserialized into `T`, which defaults to a dynamic [`serde_json::Value`].
+/// Use [`Self::response`] to change the response type.
+#[derive(Debug)]
+#[must_use = "must .await the request to send it"]
+pub struct RawRequest<'a, T = serde_json::Value> {
+    client: &'a Client,
+    server: String,
+    method: Method,
+    path: String,
+    query: Option<String>,
+    body: Option<String>,
+    headers: HeaderMap,
+    _response: PhantomData<fn() -> T>,
+}
+
+impl<'a, T: for<'de> Deserialize<'de> + Send + 'a> IntoFuture for RawRequest<'a, T> {
+    type Output = crate::Result<T>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'a>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.send())
+    }
+}
+
+impl<'a, T> RawRequest<'a, T> {
+    /// Sets a header on the request, replacing any value it was set to before, including the
+    /// default `Authorization` header. Values can be given as strings or as typed
+    /// [`HeaderValue`]s. Use [`Self::append_header`] to send several values.
+    ///
+    /// # Panics
+    /// * If the value is not a valid header value.
+    pub fn header<V>(mut self, key: HeaderName, value: V) -> Self
+    where
+        V: TryInto<HeaderValue>,
+        V::Error: std::fmt::Debug,
+    {
+        self.headers
+            .insert(key, value.try_into().expect("invalid header value"));
+        self
+    }
+
+    /// Adds a value to a header of the request, keeping any values it was set to before, so that
+    /// all of them are sent.
+    ///
+    /// # Panics
+    /// * If the value is not a valid header value.
+    pub fn append_header<V>(mut self, key: HeaderName, value: V) -> Self
+    where
+        V: TryInto<HeaderValue>,
+        V::Error: std::fmt::Debug,
+    {
+        self.headers
+            .append(key, value.try_into().expect("invalid header value"));
+        self
+    }
+
+    /// Adds query parameters to the request.
+    ///
+    /// # Panics
+    /// * If the query parameters cannot be serialized.
+    pub fn query(mut self, query: &impl Serialize) -> Self {
+        self.query = Some(serde_urlencoded::to_string(query).expect("invalid query parameters"));
+        self
+    }
+
+    /// Sets the JSON body of the request.
+    ///
+    /// # Panics
+    /// * If the body cannot be serialized.
+    pub fn body(mut self, body: &impl Serialize) -> Self {
+        self.body = Some(json::to_string(body).unwrap());
+        self
+    }
+
+    /// Changes the type the response will be deserialized into.
+    pub fn response<U>(self) -> RawRequest<'a, U> {
+        RawRequest {
+            client: self.client,
+            server: self.server,
+            method: self.method,
+            path: self.path,
+            query: self.query,
+            body: self.body,
+            headers: self.headers,
+            _response: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: for<'de> Deserialize<'de>> RawRequest<'a, T> {
+    /// Sends the request.
+    pub async fn send(self) -> crate::Result<T> {
+        let mut url = self.server + &self.path;
+        if let Some(query) = self.query {
+            url.push(if url.contains('?') { '&' } else { '?' });
+            url.push_str(&query);
         }
 
-        json::from_reader(reader).map_err(Into::into)
+        let mut request = self.client.request(self.method, url).headers(self.headers);
+        if let Some(body) = self.body {
+            request = request
+                .body(body)
+                .header("Content-Type", "application/json");
+        }
+
+        handle_response(request.send().await?).await
     }
 }
 
@@ -174,7 +612,105 @@ impl<'a, E: Endpoint> Request<'a, E> {
 pub struct Http {
     client: Client,
     server: String,
-    token: SecretString,
+    version: Option<ApiVersion>,
+    convey: String,
+    app: String,
+    token: Arc<SharedToken>,
+    token_refresh: Option<TokenRefresh>,
+    coalescer: Option<Arc<Coalescer>>,
+    cache: Option<Arc<ResponseCache>>,
+    retry_policy: Arc<dyn RetryPolicy>,
+    limiter: Option<Arc<Limiter>>,
+    config: ClientConfig,
+}
+
+/// Configuration used to build the underlying [`reqwest::Client`].
+#[derive(Clone, Debug, Default)]
+struct ClientConfig {
+    compression: Compression,
+    tls: Option<TlsOptions>,
+    dns: Option<DnsOptions>,
+    default_headers: HeaderMap,
+}
+
+impl ClientConfig {
+    fn build(&self) -> Client {
+        let builder = reqwest::ClientBuilder::new()
+            .user_agent(concat!(
+                env!("CARGO_PKG_NAME"),
+                "/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .default_headers(self.default_headers.clone())
+            .gzip(self.compression.gzip);
+        #[cfg(feature = "brotli")]
+        let builder = builder.brotli(self.compression.brotli);
+
+        let builder = match &self.tls {
+            Some(tls) => tls
+                .root_certificates
+                .iter()
+                .fold(builder, |builder, certificate| {
+                    builder.add_root_certificate(certificate.to_reqwest())
+                })
+                .tls_built_in_root_certs(tls.built_in_roots),
+            None => builder,
+        };
+
+        let builder = match &self.dns {
+            Some(dns) => builder.dns_resolver(Arc::new(dns::Resolver(dns.clone()))),
+            None => builder,
+        };
+
+        builder.build().expect("failed to initialize HTTP client")
+    }
+}
+
+/// The compression algorithms the HTTP client accepts for responses, sent in the
+/// `Accept-Encoding` header. All algorithms are enabled by default.
+///
+/// Brotli compression requires the `brotli` feature; without it, [`Self::brotli`] has no effect.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[must_use]
+pub struct Compression {
+    gzip: bool,
+    brotli: bool,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl Compression {
+    /// Accepts all supported compression algorithms.
+    pub const fn all() -> Self {
+        Self {
+            gzip: true,
+            brotli: true,
+        }
+    }
+
+    /// Disables response compression entirely.
+    pub const fn none() -> Self {
+        Self {
+            gzip: false,
+            brotli: false,
+        }
+    }
+
+    /// Sets whether gzip compression is accepted.
+    pub const fn gzip(mut self, enabled: bool) -> Self {
+        self.gzip = enabled;
+        self
+    }
+
+    /// Sets whether brotli compression is accepted. This requires the `brotli` feature.
+    pub const fn brotli(mut self, enabled: bool) -> Self {
+        self.brotli = enabled;
+        self
+    }
 }
 
 impl Http {
@@ -195,22 +731,198 @@ impl Http {
     /// * If an error occurs while creating the client.
     /// * If the token is not a valid header value.
     pub fn from_token_and_uri<'a>(token: impl AsRef<str>, uri: impl Into<BaseUrl<'a>>) -> Self {
-        let client = reqwest::ClientBuilder::new()
-            .user_agent(concat!(
-                env!("CARGO_PKG_NAME"),
-                "/",
-                env!("CARGO_PKG_VERSION")
-            ))
-            .build()
-            .expect("failed to initialize HTTP client");
+        let config = ClientConfig::default();
+        let client = config.build();
 
+        let uri = uri.into();
         Self {
             client,
-            server: uri.into().get().to_string(),
-            token: SecretString::new(token.as_ref().to_string()),
+            server: uri.get().to_string(),
+            version: uri.version(),
+            convey: Server::production().convey.to_string(),
+            app: Server::production().app.to_string(),
+            token: Arc::new(SharedToken::new(token.as_ref().to_string())),
+            token_refresh: None,
+            coalescer: None,
+            cache: None,
+            retry_policy: Arc::new(DefaultRetryPolicy::default()),
+            limiter: None,
+            config,
         }
     }
 
+    /// Sets the TLS options of the client, for example to trust the certificate authority of a
+    /// self-hosted instance.
+    ///
+    /// # Panics
+    /// * If an error occurs while recreating the client.
+    pub fn with_tls(mut self, tls: TlsOptions) -> Self {
+        self.config.tls = Some(tls);
+        self.client = self.config.build();
+        self
+    }
+
+    /// Sets a header which is sent with every request made by this client, including requests to
+    /// convey, for example the service token headers required by an instance behind Cloudflare
+    /// Access. Setting the same header again replaces its value, since default headers can only
+    /// have a single value.
+    ///
+    /// Headers set on an individual request take precedence over default headers of the same name.
+    ///
+    /// # Panics
+    /// * If the value is not a valid header value.
+    /// * If an error occurs while recreating the client.
+    pub fn with_default_header<V>(mut self, key: HeaderName, value: V) -> Self
+    where
+        V: TryInto<HeaderValue>,
+        V::Error: std::fmt::Debug,
+    {
+        let value = value.try_into().expect("invalid header value");
+        self.config.default_headers.insert(key, value);
+        self.client = self.config.build();
+        self
+    }
+
+    /// Sets the DNS options of the client, for example to only connect over IPv4, or to resolve the
+    /// hostname of a self-hosted instance to an internal address. By default, hostnames are
+    /// resolved with the system resolver.
+    ///
+    /// These options only apply to HTTP requests, not to the gateway connection.
+    ///
+    /// # Panics
+    /// * If an error occurs while recreating the client.
+    pub fn with_dns(mut self, dns: DnsOptions) -> Self {
+        self.config.dns = Some(dns);
+        self.client = self.config.build();
+        self
+    }
+
+    /// Pins the client to the given API version, prefixing the path of every request with it
+    /// (e.g. `/v1`). By default, paths are unversioned.
+    pub const fn with_api_version(mut self, version: ApiVersion) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Returns the API version the client is pinned to, if any.
+    #[must_use]
+    pub const fn api_version(&self) -> Option<ApiVersion> {
+        self.version
+    }
+
+    /// Returns the base URL requests are made to, including the API version prefix if any.
+    fn base_url(&self) -> String {
+        match self.version {
+            Some(version) => format!("{}/{version}", self.server.trim_end_matches('/')),
+            None => self.server.clone(),
+        }
+    }
+
+    /// Sets a hook which is called when a request is rejected because the token is invalid
+    /// (`401 Unauthorized`), for example to re-read a rotated token from a secrets manager.
+    ///
+    /// If the hook returns a new token, it replaces the token of this client and all of its
+    /// clones, and the request is retried once with it. If the hook returns `None`, the request
+    /// fails with the original error. When several requests are rejected at once, the hook is
+    /// only called again for requests which were sent with the latest token.
+    ///
+    /// Only requests made through [`Self::request`] are retried. Other requests, as well as the
+    /// gateway connection, keep their credentials.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use adapt::http::Http;
+    ///
+    /// let http = Http::from_token("token").with_token_refresh(|| async {
+    ///     tokio::fs::read_to_string("/run/secrets/adapt-token").await.ok()
+    /// });
+    /// ```
+    pub fn with_token_refresh<F, Fut>(mut self, f: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<String>> + Send + 'static,
+    {
+        self.token_refresh = Some(TokenRefresh::new(f));
+        self
+    }
+
+    /// Sets the policy used to decide whether failed requests are retried. Defaults to
+    /// [`DefaultRetryPolicy`]; use [`NeverRetry`] to disable retries entirely.
+    pub fn with_retry_policy(mut self, policy: impl RetryPolicy + 'static) -> Self {
+        self.retry_policy = Arc::new(policy);
+        self
+    }
+
+    /// Limits the number of requests made through [`Self::request`] which are in flight at once,
+    /// across this client and all of its clones. Once the limit is reached, further requests wait
+    /// and are sent in order of their [`Priority`], so that background work such as bulk deletions
+    /// cannot delay requests a user is waiting on. Requests waiting to be retried do not count
+    /// towards the limit.
+    ///
+    /// Passing a limit of `0` removes the limit, which is the default.
+    pub fn with_concurrency_limit(mut self, limit: usize) -> Self {
+        self.limiter = (limit > 0).then(|| Arc::new(Limiter::new(limit)));
+        self
+    }
+
+    /// Sets which compression algorithms are accepted for responses. Large responses, such as
+    /// member or message lists, compress well, so all supported algorithms are enabled by default.
+    ///
+    /// # Panics
+    /// * If an error occurs while recreating the client.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.config.compression = compression;
+        self.client = self.config.build();
+        self
+    }
+
+    /// Enables or disables coalescing of concurrent `GET` requests. When enabled, requests to the
+    /// same endpoint that are made while an identical request is still in flight (for example,
+    /// `GetUser(42)` from several event handlers) share a single HTTP call and its response.
+    ///
+    /// This is disabled by default.
+    pub fn with_request_coalescing(mut self, enabled: bool) -> Self {
+        self.coalescer = enabled.then(Arc::default);
+        self
+    }
+
+    /// Enables caching of up to `capacity` `GET` responses. Responses carrying an `ETag` or
+    /// `Last-Modified` header are cached, and subsequent requests to the same endpoint are sent as
+    /// conditional requests. If the server responds with `304 Not Modified`, the cached body is
+    /// served instead, which reduces latency and rate limit pressure.
+    ///
+    /// Passing a capacity of `0` disables the cache, which is the default.
+    pub fn with_response_cache(mut self, capacity: usize) -> Self {
+        self.cache = (capacity > 0).then(|| Arc::new(ResponseCache::new(capacity)));
+        self
+    }
+
+    /// Sets the base URL of convey, Adapt's CDN, used by [`Self::convey`]. Defaults to the
+    /// production instance (`https://convey.adapt.chat`).
+    pub fn with_convey_url(mut self, url: impl AsRef<str>) -> Self {
+        self.convey = url.as_ref().to_string();
+        self
+    }
+
+    /// Sets the base URL of the web app, used to build links such as invite URLs. Defaults to the
+    /// production instance (`https://adapt.chat`).
+    pub fn with_app_url(mut self, url: impl AsRef<str>) -> Self {
+        self.app = url.as_ref().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Returns the base URL of the web app, used to build links such as invite URLs.
+    #[must_use]
+    pub fn app_url(&self) -> &str {
+        &self.app
+    }
+
+    /// Returns the ID of the user the token of this client belongs to, decoded from the token
+    /// without making a request. See [`crate::utils::token`].
+    pub fn user_id(&self) -> crate::Result<UserId> {
+        crate::utils::token::user_id(self.token.expose())
+    }
+
     /// Creates a new HTTP client with the given token and the default Adapt server URI.
     /// See [`BaseUrl`] for more information of what this is.
     ///
@@ -238,8 +950,22 @@ impl Http {
         password: impl AsRef<str> + Send,
         retrieval_method: TokenRetrievalMethod,
     ) -> crate::Result<Self> {
-        let mut slf = Self::from_token_and_uri("", server);
-        let user = slf
+        let slf = Self::from_token_and_uri("", server);
+        let token = slf.login_token(email, password, retrieval_method).await?;
+
+        slf.token.set(token);
+        Ok(slf)
+    }
+
+    /// Logs into the given user account with this client, returning the obtained token. The
+    /// token of this client is left unchanged.
+    pub(crate) async fn login_token(
+        &self,
+        email: impl AsRef<str> + Send,
+        password: impl AsRef<str> + Send,
+        retrieval_method: TokenRetrievalMethod,
+    ) -> crate::Result<String> {
+        let response = self
             .request(endpoints::Login)
             .body(http::auth::LoginRequest {
                 email: email.as_ref().to_string(),
@@ -248,8 +974,7 @@ impl Http {
             })
             .await?;
 
-        slf.token = SecretString::new(user.token);
-        Ok(slf)
+        Ok(response.token)
     }
 
     /// Logs into the given user account with credentials (email and password) on the production
@@ -287,19 +1012,64 @@ impl Http {
         Self::login_on(BaseUrl::default(), email, password, retrieval_method).await
     }
 
-    /// Returns the authentication token for this client. You should not expose this value to
-    /// anyone.
+    /// Returns the authentication token for this client, including any token obtained through
+    /// [`Self::with_token_refresh`]. You should not expose this value to anyone.
     #[inline]
     #[must_use]
-    pub const fn token(&self) -> &SecretString {
-        &self.token
+    pub fn token(&self) -> SecretString {
+        self.token.get()
+    }
+
+    /// Returns a client for convey, Adapt's CDN, which shares the HTTP client and credentials of
+    /// this client.
+    pub fn convey(&self) -> Convey<'_> {
+        Convey::new(&self.client, &self.convey, &self.token)
     }
 
     /// Creates a new outgoing HTTP request to the given endpoint. The request takes and returns raw
     /// models from [`essence`].
     pub fn request<E: Endpoint>(&self, endpoint: E) -> Request<E> {
-        let token = self.token.expose_secret();
-        Request::new(&self.client, &self.server, endpoint).header(AUTHORIZATION, token)
+        Request::new(self, endpoint).header(AUTHORIZATION, self.token.expose())
+    }
+
+    /// Creates a new outgoing HTTP request to an arbitrary path, relative to the base URL of the
+    /// API. This is an escape hatch for endpoints that are not (yet) modeled by this crate, such as
+    /// new or instance-specific endpoints.
+    ///
+    /// The response is deserialized into a [`serde_json::Value`] by default; see
+    /// [`RawRequest::response`] to deserialize into a concrete type instead.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use adapt::http::{Http, Method};
+    /// use adapt::essence::models::User;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> adapt::Result<()> {
+    ///     let token = std::env::var("ADAPT_TOKEN").expect("missing Adapt token");
+    ///     let http = Http::from_token(token);
+    ///
+    ///     let raw = http.request_raw(Method::GET, "/users/me").await?;
+    ///     println!("{raw:#?}");
+    ///
+    ///     let user = http.request_raw(Method::GET, "/users/me").response::<User>().await?;
+    ///     println!("Logged in as {}", user.username);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn request_raw(&self, method: Method, path: impl Into<String>) -> RawRequest<'_> {
+        let token = self.token.expose();
+        RawRequest {
+            client: &self.client,
+            server: self.base_url(),
+            method,
+            path: path.into(),
+            query: None,
+            body: None,
+            headers: HeaderMap::new(),
+            _response: PhantomData,
+        }
+        .header(AUTHORIZATION, token)
     }
 }
 
@@ -316,4 +1086,80 @@ mod tests {
         println!("{:#?}", http.request(endpoints::GetAuthenticatedUser).await);
         Ok(())
     }
+
+    #[test]
+    fn append_headers() {
+        let key = HeaderName::from_static("x-custom");
+        let http = Http::from_token("token")
+            .with_default_header(key.clone(), "default")
+            .with_default_header(key.clone(), "replaced");
+        assert_eq!(http.config.default_headers.get_all(&key).iter().count(), 1);
+        assert_eq!(http.config.default_headers[&key], "replaced");
+
+        let request = http
+            .request(endpoints::GetAuthenticatedUser)
+            .header(key.clone(), "a")
+            .append_header(key.clone(), HeaderValue::from_static("b"))
+            .header(AUTHORIZATION, "other");
+        assert_eq!(request.headers.get_all(&key).iter().count(), 2);
+        assert_eq!(request.headers.get_all(AUTHORIZATION).iter().count(), 1);
+        assert_eq!(request.headers[AUTHORIZATION], "other");
+    }
+
+    #[tokio::test]
+    async fn retry_with_refreshed_token() {
+        use secrecy::ExposeSecret;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Rejects every request which is not authorized with the token "new"
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = vec![0; 4096];
+                let len = stream.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..len]).to_ascii_lowercase();
+                let response = if request.contains("authorization: new\r\n") {
+                    "HTTP/1.1 204 No Content\r\n\r\n".to_string()
+                } else {
+                    let body = r#"{"type":"InvalidToken","message":"Invalid token"}"#;
+                    format!(
+                        "HTTP/1.1 401 Unauthorized\r\ncontent-length: {}\r\n\r\n{body}",
+                        body.len()
+                    )
+                };
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let http = Http::from_token_and_uri("old", url.as_str())
+            .with_retry_policy(NeverRetry)
+            .with_token_refresh(move || {
+                let token = (counter.fetch_add(1, Ordering::SeqCst) == 0).then(|| " new\n".into());
+                async move { token }
+            });
+
+        http.request(endpoints::DeleteChannel(1)).await.unwrap();
+        assert_eq!(http.token().expose_secret(), "new");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let http = Http::from_token_and_uri("old", url.as_str())
+            .with_retry_policy(NeverRetry)
+            .with_token_refresh(|| async { Some("new\r\nx-injected: 1".into()) });
+        let error = http.request(endpoints::DeleteChannel(1)).await.unwrap_err();
+        assert!(matches!(
+            error,
+            Error::InvalidToken(TokenError::InvalidCharacters)
+        ));
+        assert_eq!(http.token().expose_secret(), "old");
+    }
+
+    #[test]
+    fn parse_empty_response() {
+        parse_response::<()>(204, Bytes::new()).unwrap();
+        assert!(parse_response::<()>(409, Bytes::new()).is_err());
+    }
 }
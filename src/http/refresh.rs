@@ -0,0 +1,58 @@
+use futures_util::future::BoxFuture;
+use secrecy::{ExposeSecret, SecretString};
+use std::{
+    fmt::{self, Debug},
+    future::Future,
+    sync::{Arc, RwLock},
+};
+
+/// The token used to authenticate requests. It is shared by all clones of an
+/// [`Http`](super::Http) client, so that a refreshed token is picked up by all of them.
+#[derive(Debug)]
+pub(crate) struct SharedToken(RwLock<SecretString>);
+
+impl SharedToken {
+    pub fn new(token: String) -> Self {
+        Self(RwLock::new(SecretString::new(token)))
+    }
+
+    pub fn get(&self) -> SecretString {
+        self.0.read().expect("poison").clone()
+    }
+
+    /// Returns a copy of the token, for example to set it as a header.
+    pub fn expose(&self) -> String {
+        self.0.read().expect("poison").expose_secret().clone()
+    }
+
+    pub fn set(&self, token: String) {
+        *self.0.write().expect("poison") = SecretString::new(token);
+    }
+}
+
+type RefreshFn = dyn Fn() -> BoxFuture<'static, Option<String>> + Send + Sync;
+
+/// A hook supplying a fresh token after a request was rejected as unauthorized. See
+/// [`Http::with_token_refresh`](super::Http::with_token_refresh).
+#[derive(Clone)]
+pub(crate) struct TokenRefresh(Arc<RefreshFn>);
+
+impl TokenRefresh {
+    pub fn new<F, Fut>(f: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<String>> + Send + 'static,
+    {
+        Self(Arc::new(move || Box::pin(f())))
+    }
+
+    pub async fn call(&self) -> Option<String> {
+        (self.0)().await
+    }
+}
+
+impl Debug for TokenRefresh {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenRefresh").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,174 @@
+//! Retry policies for failed HTTP requests.
+
+use crate::Error;
+use reqwest::Method;
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// Information about a failed request, given to a [`RetryPolicy`] to decide whether to retry it.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct FailedRequest<'a> {
+    /// The attempt that failed, starting at `1` for the initial request.
+    pub attempt: u32,
+    /// The HTTP method of the request.
+    pub method: &'a Method,
+    /// The path of the request, excluding the base URL.
+    pub path: &'a str,
+    /// The error the request failed with.
+    pub error: &'a Error,
+}
+
+impl<'a> FailedRequest<'a> {
+    /// Creates information about a failed request, for example to test a custom [`RetryPolicy`].
+    #[must_use]
+    pub const fn new(attempt: u32, method: &'a Method, path: &'a str, error: &'a Error) -> Self {
+        Self {
+            attempt,
+            method,
+            path,
+            error,
+        }
+    }
+}
+
+/// The decision of a [`RetryPolicy`] for a failed request.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RetryDecision {
+    /// Retry the request after the given delay.
+    Retry(Duration),
+    /// Do not retry the request, returning the error to the caller.
+    Stop,
+}
+
+/// Decides whether, and when, failed HTTP requests are retried.
+///
+/// The default policy is [`DefaultRetryPolicy`]. Custom policies can be set with
+/// [`Http::with_retry_policy`](super::Http::with_retry_policy).
+///
+/// # Example
+/// ```
+/// use adapt::http::{FailedRequest, Method, RetryDecision, RetryPolicy};
+///
+/// /// Never retries requests that are not idempotent.
+/// #[derive(Debug)]
+/// struct NoPostRetries;
+///
+/// impl RetryPolicy for NoPostRetries {
+///     fn decide(&self, request: &FailedRequest<'_>) -> RetryDecision {
+///         if request.method == Method::POST {
+///             return RetryDecision::Stop;
+///         }
+///         adapt::http::DefaultRetryPolicy::default().decide(request)
+///     }
+/// }
+/// ```
+pub trait RetryPolicy: Debug + Send + Sync {
+    /// Decides whether the given failed request should be retried.
+    fn decide(&self, request: &FailedRequest<'_>) -> RetryDecision;
+}
+
+/// The default retry policy.
+///
+/// Ratelimited requests are retried after the delay given by the API. Requests that failed to
+/// connect or timed out are retried with exponential backoff, but only if their method is
+/// idempotent. Other errors are never retried.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DefaultRetryPolicy {
+    /// The maximum number of attempts, including the initial request.
+    pub max_attempts: u32,
+    /// The delay before the first retry of a transient failure, doubled for each attempt.
+    pub base_delay: Duration,
+    /// The maximum delay before retrying a transient failure.
+    pub max_delay: Duration,
+}
+
+impl Default for DefaultRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy for DefaultRetryPolicy {
+    fn decide(&self, request: &FailedRequest<'_>) -> RetryDecision {
+        if request.attempt >= self.max_attempts {
+            return RetryDecision::Stop;
+        }
+        if let Some(delay) = request.error.retry_after() {
+            return RetryDecision::Retry(delay);
+        }
+
+        let idempotent = !matches!(*request.method, Method::POST | Method::PATCH);
+        match request.error {
+            Error::Reqwest(err) if idempotent && (err.is_connect() || err.is_timeout()) => {
+                let factor = 2_u32
+                    .checked_pow(request.attempt.saturating_sub(1))
+                    .unwrap_or(u32::MAX);
+                RetryDecision::Retry(self.base_delay.saturating_mul(factor).min(self.max_delay))
+            }
+            _ => RetryDecision::Stop,
+        }
+    }
+}
+
+/// A retry policy that never retries requests.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NeverRetry;
+
+impl RetryPolicy for NeverRetry {
+    fn decide(&self, _request: &FailedRequest<'_>) -> RetryDecision {
+        RetryDecision::Stop
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_policy() {
+        let policy = DefaultRetryPolicy::default();
+        let error = Error::Http(essence::Error::Ratelimited {
+            retry_after: 1.5,
+            ip: "127.0.0.1".to_string(),
+            message: "You are being ratelimited".to_string(),
+        });
+        let mut request = FailedRequest::new(1, &Method::POST, "/channels/1/messages", &error);
+        assert_eq!(
+            policy.decide(&request),
+            RetryDecision::Retry(Duration::from_millis(1500))
+        );
+
+        request.attempt = 3;
+        assert_eq!(policy.decide(&request), RetryDecision::Stop);
+    }
+
+    #[tokio::test]
+    async fn backoff_is_clamped() {
+        // Nothing listens on port 1, so connecting fails
+        let error = Error::Reqwest(
+            reqwest::get("http://127.0.0.1:1")
+                .await
+                .expect_err("connecting should fail"),
+        );
+        let policy = DefaultRetryPolicy {
+            max_attempts: u32::MAX,
+            ..DefaultRetryPolicy::default()
+        };
+
+        let request = FailedRequest::new(2, &Method::GET, "/users/me", &error);
+        assert_eq!(
+            policy.decide(&request),
+            RetryDecision::Retry(Duration::from_secs(1))
+        );
+        let request = FailedRequest::new(40, &Method::GET, "/users/me", &error);
+        assert_eq!(
+            policy.decide(&request),
+            RetryDecision::Retry(policy.max_delay)
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! Execute webhooks without a full client.
+//!
+//! A [`WebhookClient`] only needs the URL of a webhook: no token, gateway connection, or
+//! [`Http`](super::Http) client is required. This makes it suitable for logging pipelines and
+//! other tools which only need to post messages.
+
+use super::{
+    convey::{FileSource, FileUpload},
+    json, multipart_form, parse_response,
+};
+use crate::models::{prepare_message, IntoCreateMessage};
+use crate::Result;
+use reqwest::{Client, IntoUrl, RequestBuilder, Url};
+use serde::de::IgnoredAny;
+
+/// A lightweight client for executing a single webhook.
+///
+/// # Example
+/// ```no_run
+/// use adapt::http::WebhookClient;
+///
+/// # async fn run() -> adapt::Result<()> {
+/// let url = std::env::var("WEBHOOK_URL").expect("No webhook URL found");
+/// let webhook = WebhookClient::from_url(url)?;
+/// webhook.execute("Deployment finished").await?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug)]
+pub struct WebhookClient {
+    client: Client,
+    url: Url,
+}
+
+impl WebhookClient {
+    /// Creates a new webhook client from the URL of the webhook.
+    pub fn from_url(url: impl IntoUrl) -> Result<Self> {
+        Ok(Self::with_client(Client::new(), url.into_url()?))
+    }
+
+    /// Creates a new webhook client from the URL of the webhook, sending requests with the given
+    /// reqwest client. This allows reusing a connection pool shared with other requests.
+    #[must_use]
+    pub const fn with_client(client: Client, url: Url) -> Self {
+        Self { client, url }
+    }
+
+    /// Returns the URL of the webhook.
+    #[must_use]
+    pub const fn url(&self) -> &Url {
+        &self.url
+    }
+
+    async fn send(request: RequestBuilder) -> Result<()> {
+        let response = request.send().await?;
+        if response.status().is_success() {
+            return Ok(());
+        }
+
+        let status = response.status().as_u16();
+        parse_response::<IgnoredAny>(status, response.bytes().await?).map(drop)
+    }
+
+    /// Executes the webhook with the given message content and embeds.
+    ///
+    /// # Panics
+    /// * If the payload cannot be serialized.
+    pub async fn execute(&self, payload: impl IntoCreateMessage + Send) -> Result<()> {
+        let body = json::to_string(&prepare_message(payload, &[])?).unwrap();
+        let request = self
+            .client
+            .post(self.url.clone())
+            .body(body)
+            .header("Content-Type", "application/json");
+
+        Self::send(request).await
+    }
+
+    /// Executes the webhook with the given files attached. Files can be given as paths or as
+    /// in-memory [`FileUpload`](super::convey::FileUpload)s.
+    ///
+    /// # Panics
+    /// * If the payload cannot be serialized.
+    pub async fn execute_with_files(
+        &self,
+        files: impl IntoIterator<Item = FileSource> + Send,
+        payload: impl IntoCreateMessage + Send,
+    ) -> Result<()> {
+        let mut uploads = Vec::new();
+        for file in files {
+            uploads.push(file.into_upload().await?);
+        }
+
+        let sizes = uploads.iter().map(FileUpload::size).collect::<Vec<_>>();
+        let form = multipart_form(Some(&prepare_message(payload, &sizes)?), uploads)?;
+        Self::send(self.client.post(self.url.clone()).multipart(form)).await
+    }
+}
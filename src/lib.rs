@@ -8,14 +8,22 @@
 extern crate log;
 extern crate core;
 
+pub mod builder;
+pub mod cache;
 pub mod client;
 mod error;
 pub mod http;
 pub mod models;
 mod server;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
+pub mod tls;
+pub mod utils;
 #[cfg(feature = "ws")]
 pub mod ws;
 
+#[cfg(feature = "macros")]
+pub use adapt_macros::event_handler;
 pub use client::{Client, ClientOptions, Context, WithCtx};
 pub use error::{Error, Result};
 pub use essence;
@@ -27,5 +35,5 @@ pub mod prelude {
     pub use super::models::Id;
 
     #[cfg(feature = "ws")]
-    pub use super::ws::{EventConsumer, EventHandler, FallibleEventHandler};
+    pub use super::ws::{EventConsumer, EventHandler, FallibleEventHandler, ReadyEvent};
 }
@@ -0,0 +1,126 @@
+use crate::models::Id;
+use crate::{Result, WithCtx};
+use bytes::Bytes;
+use serde::Serialize;
+use std::path::PathBuf;
+
+crate::id_type! {
+    /// Represents an Adapt attachment by its ID.
+    pub struct AttachmentId: Attachment;
+}
+
+/// Represents a file attached to a message.
+#[derive(Clone, Debug, Serialize)]
+pub struct Attachment {
+    /// The ID of the attachment.
+    pub id: AttachmentId,
+    /// The name of the attached file.
+    pub filename: String,
+    /// The size of the attached file, in bytes.
+    pub size: u64,
+    /// The alt text of the attachment, if any.
+    pub alt: Option<String>,
+}
+
+impl Attachment {
+    /// Creates a new attachment from a raw [`essence::models::Attachment`].
+    #[must_use]
+    pub fn from_raw(attachment: essence::models::Attachment) -> Self {
+        Self {
+            id: attachment.id.into(),
+            filename: attachment.filename,
+            size: attachment.size,
+            alt: attachment.alt,
+        }
+    }
+
+    /// Converts this attachment back into a raw [`essence::models::Attachment`].
+    #[must_use]
+    pub fn into_raw(self) -> essence::models::Attachment {
+        essence::models::Attachment {
+            id: self.id.get(),
+            filename: self.filename,
+            size: self.size,
+            alt: self.alt,
+        }
+    }
+
+    /// Returns the path of the attachment, relative to the base URL of convey.
+    #[must_use]
+    pub fn path(&self) -> String {
+        format!("/attachments/{}/{}", self.id, self.filename)
+    }
+
+    /// Guesses the MIME type of the attachment from the extension of its filename, falling back
+    /// to `application/octet-stream` if it is unknown.
+    ///
+    /// # Note
+    /// Adapt does not report the dimensions of image or video attachments. To find them, fetch
+    /// the attachment with [`WithCtx<Attachment>::bytes`] and decode it.
+    #[must_use]
+    pub fn mime_type(&self) -> mime_guess::Mime {
+        mime_guess::from_path(&self.filename).first_or_octet_stream()
+    }
+
+    /// Returns whether the attachment is an image, according to [`Self::mime_type`].
+    #[must_use]
+    pub fn is_image(&self) -> bool {
+        self.mime_type().type_() == mime_guess::mime::IMAGE
+    }
+}
+
+impl WithCtx<Attachment> {
+    /// Returns the full URL of the attachment on convey.
+    #[must_use]
+    pub fn url(&self) -> String {
+        self.ctx.http().convey().object_url(self.path())
+    }
+
+    /// Fetches the contents of the attachment into memory.
+    pub async fn bytes(&self) -> Result<Bytes> {
+        self.ctx.http().convey().fetch(self.path()).await
+    }
+
+    /// Downloads the attachment into the file at `dest`, resuming from where a previous attempt
+    /// left off. Returns the number of bytes in the file.
+    pub async fn save_to(&self, dest: impl Into<PathBuf> + Send) -> Result<u64> {
+        self.ctx
+            .http()
+            .convey()
+            .download_resumable(self.path(), dest)
+            .await
+    }
+}
+
+impl From<Attachment> for essence::models::Attachment {
+    fn from(attachment: Attachment) -> Self {
+        attachment.into_raw()
+    }
+}
+
+crate::impl_common_traits!(Attachment);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mime_type() {
+        let attachment = Attachment {
+            id: AttachmentId::from(1),
+            filename: "cat.png".to_string(),
+            size: 1024,
+            alt: None,
+        };
+        assert_eq!(attachment.path(), "/attachments/1/cat.png");
+        assert_eq!(attachment.mime_type(), "image/png");
+        assert!(attachment.is_image());
+
+        let attachment = Attachment {
+            filename: "data".to_string(),
+            ..attachment
+        };
+        assert_eq!(attachment.mime_type(), "application/octet-stream");
+        assert!(!attachment.is_image());
+    }
+}
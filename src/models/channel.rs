@@ -1,7 +1,18 @@
-use crate::http::endpoints;
-use crate::models::message::IntoCreateMessage;
-use crate::models::{Id, Message, MessageId, PartialMessage};
+use crate::builder::{EditChannelBuilder, MessageHistoryQueryBuilder};
+use crate::client::outbox::{Outbox, QueuedMessage};
+use crate::http::{
+    convey::{FileSource, FileUpload},
+    endpoints,
+};
+use crate::models::message::{prepare_message, IntoCreateMessage};
+use crate::models::{GuildId, Id, Message, MessageHistory, MessageId, PartialMessage, UserId};
+#[cfg(feature = "ws")]
+use crate::ws::Event;
 use crate::{Context, Result, WithCtx};
+use essence::models::{ChannelType, DmChannelInfo, GuildChannelInfo, PermissionOverwrite};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::task::JoinHandle;
 
 crate::id_type! {
     /// Represents an Adapt channel by its ID.
@@ -28,15 +39,493 @@ impl WithCtx<ChannelId> {
             .with(self.inner().partial_message(message_id))
     }
 
+    /// Fetches this channel.
+    pub async fn fetch(&self) -> Result<WithCtx<Channel>> {
+        let channel = self
+            .ctx
+            .http()
+            .request(endpoints::GetChannel(self.get()))
+            .await?;
+
+        Ok(self.ctx.clone().with(Channel::from_raw(channel)))
+    }
+
     /// Creates a new message in this channel.
     pub async fn send(&self, payload: impl IntoCreateMessage + Send) -> Result<WithCtx<Message>> {
         let message = self
             .ctx
             .http()
             .request(endpoints::CreateMessage(self.get()))
-            .body(payload.into_create_message())
+            .body(prepare_message(payload, &[])?)
+            .await?;
+
+        Ok(self.ctx.clone().with(Message::from_raw(message)))
+    }
+
+    /// Creates a new message in this channel and waits for the gateway to confirm it, returning
+    /// the message as received in its `message_create` event. If no confirmation is received
+    /// within the given timeout, the message as returned by the API is returned instead.
+    ///
+    /// The message is matched to its event by its nonce. If the payload has no nonce, a unique
+    /// one is generated.
+    ///
+    /// When called from an event handler run by the
+    /// [inline](crate::ws::handler::DispatchExecutor::inline) executor, the confirmation cannot be
+    /// dispatched until the handler finishes, so the message as returned by the API is returned
+    /// without waiting.
+    #[cfg(feature = "ws")]
+    pub async fn send_and_confirm(
+        &self,
+        payload: impl IntoCreateMessage + Send,
+        timeout: Duration,
+    ) -> Result<WithCtx<Message>> {
+        let mut payload = prepare_message(payload, &[])?;
+        let nonce = payload
+            .nonce
+            .get_or_insert_with(Outbox::generate_nonce)
+            .clone();
+
+        // Subscribe before sending, since the event may arrive before the response
+        let events = self.ctx.subscribe_events().ok();
+        let message = self
+            .ctx
+            .http()
+            .request(endpoints::CreateMessage(self.get()))
+            .body(payload)
+            .await?;
+        let mut message = Message::from_raw(message);
+        message.nonce = Some(nonce.clone());
+
+        let Some(mut events) = events else {
+            return Ok(self.ctx.clone().with(message));
+        };
+        let confirmation = Context::recv_matching(
+            &mut events,
+            |event| {
+                matches!(event, Event::MessageCreate(message)
+                    if message.nonce.as_deref() == Some(nonce.as_str()))
+            },
+            timeout,
+        )
+        .await;
+
+        Ok(match confirmation {
+            Some(Event::MessageCreate(message)) => message,
+            _ => self.ctx.clone().with(message),
+        })
+    }
+
+    /// Queues a message to be sent to this channel, returning its nonce. Unlike [`Self::send`],
+    /// this returns immediately and the message is sent in the background, retrying across rate
+    /// limits and failures to connect until the API accepts or rejects it. If the connection
+    /// fails after the request was sent, the message is not retried, since it may have been
+    /// created.
+    ///
+    /// If the payload has a nonce, it is used to deduplicate the message: a message is not queued
+    /// again while a message with the same nonce is queued. Otherwise, a unique nonce is
+    /// generated. Configure an [`OutboxStore`](crate::client::outbox::OutboxStore) with
+    /// [`ClientOptions::outbox_store`](crate::ClientOptions::outbox_store) to keep queued messages
+    /// across restarts.
+    ///
+    /// # Errors
+    /// * If the payload is invalid. The message is not queued in this case.
+    ///
+    /// # Panics
+    /// * If called outside of a Tokio runtime.
+    pub fn send_queued(&self, payload: impl IntoCreateMessage) -> Result<String> {
+        let mut payload = prepare_message(payload, &[])?;
+        let nonce = payload
+            .nonce
+            .get_or_insert_with(Outbox::generate_nonce)
+            .clone();
+
+        self.ctx.outbox.enqueue(QueuedMessage {
+            nonce: nonce.clone(),
+            channel_id: self.get(),
+            payload,
+        });
+        self.ctx.outbox.ensure_running(&self.ctx);
+        Ok(nonce)
+    }
+
+    /// Creates a new message in this channel with the given files attached. Files can be given as
+    /// paths or as in-memory [`FileUpload`](crate::http::convey::FileUpload)s.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use adapt::http::convey::FileUpload;
+    /// # use adapt::{models::ChannelId, WithCtx};
+    ///
+    /// # async fn run(channel: WithCtx<ChannelId>) -> adapt::Result<()> {
+    /// channel
+    ///     .send_files(
+    ///         [
+    ///             "report.pdf".into(),
+    ///             FileUpload::from_bytes("data.csv", "a,b\n1,2").into(),
+    ///         ],
+    ///         "Here is the report",
+    ///     )
+    ///     .await?;
+    /// # Ok(()) }
+    /// ```
+    pub async fn send_files(
+        &self,
+        files: impl IntoIterator<Item = FileSource> + Send,
+        payload: impl IntoCreateMessage + Send,
+    ) -> Result<WithCtx<Message>> {
+        let mut uploads = Vec::new();
+        for file in files {
+            uploads.push(file.into_upload().await?);
+        }
+        let sizes = uploads.iter().map(FileUpload::size).collect::<Vec<_>>();
+
+        let message = self
+            .ctx
+            .http()
+            .request(endpoints::CreateMessage(self.get()))
+            .body(prepare_message(payload, &sizes)?)
+            .send_with_files(uploads)
             .await?;
 
         Ok(self.ctx.clone().with(Message::from_raw(message)))
     }
+
+    /// Triggers the typing indicator in this channel once.
+    pub async fn trigger_typing(&self) -> Result<()> {
+        self.ctx
+            .http()
+            .request(endpoints::TriggerTyping(self.get()))
+            .await
+    }
+
+    /// Triggers the typing indicator in this channel immediately, and keeps re-triggering it until
+    /// the returned guard is dropped. This is useful for commands that take a while to respond.
+    ///
+    /// Errors while triggering the typing indicator are logged and otherwise ignored.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use adapt::{models::ChannelId, WithCtx};
+    /// # async fn run(channel: WithCtx<ChannelId>) -> adapt::Result<()> {
+    /// let typing = channel.typing();
+    /// // do some long-running work...
+    /// drop(typing);
+    /// channel.send("Done!").await?;
+    /// # Ok(()) }
+    /// ```
+    pub fn typing(&self) -> TypingGuard {
+        let channel = self.clone();
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TypingGuard::INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(err) = channel.trigger_typing().await {
+                    warn!("Could not trigger typing indicator: {err:?}");
+                }
+            }
+        });
+        TypingGuard(task)
+    }
+
+    /// Returns a stream over the entire message history of this channel, which fetches pages of
+    /// messages lazily as they are consumed. See [`MessageHistory`] for more information.
+    pub fn history_stream(&self) -> MessageHistory {
+        MessageHistory::new(self.clone())
+    }
+
+    /// Fetches the message history of this channel, filtered by the given query.
+    ///
+    /// Queries for the messages [around](MessageHistoryQueryBuilder::around) a message make two
+    /// requests, one for each side of the message.
+    pub async fn history(
+        &self,
+        query: MessageHistoryQueryBuilder,
+    ) -> Result<Vec<WithCtx<Message>>> {
+        let oldest_first = query.is_oldest_first();
+        let messages = match query.clone().split_around()? {
+            Some((older, newer)) => {
+                let mut messages = match newer {
+                    Some(newer) => {
+                        let mut newer = self.fetch_history(newer).await?;
+                        newer.reverse();
+                        newer
+                    }
+                    None => Vec::new(),
+                };
+                messages.extend(self.fetch_history(older).await?);
+                if oldest_first {
+                    messages.reverse();
+                }
+                messages
+            }
+            None => self.fetch_history(query.build()?).await?,
+        };
+
+        Ok(messages
+            .into_iter()
+            .map(|message| self.ctx.clone().with(Message::from_raw(message)))
+            .collect())
+    }
+
+    async fn fetch_history(
+        &self,
+        query: essence::http::message::MessageHistoryQuery,
+    ) -> Result<Vec<essence::models::Message>> {
+        self.ctx
+            .http()
+            .request(endpoints::GetMessageHistory(self.get()))
+            .query(query)
+            .await
+    }
+
+    /// Edits the channel with the changes configured by the given function, returning the updated
+    /// channel.
+    pub async fn edit(
+        &self,
+        f: impl FnOnce(EditChannelBuilder) -> EditChannelBuilder + Send,
+    ) -> Result<WithCtx<Channel>> {
+        let channel = self
+            .ctx
+            .http()
+            .request(endpoints::EditChannel(self.get()))
+            .body(f(EditChannelBuilder::new()).build()?)
+            .await?;
+
+        Ok(self.ctx.clone().with(Channel::from_raw(channel)))
+    }
+
+    /// Sets the topic of this channel. See [`EditChannelBuilder::topic`].
+    pub async fn set_topic(&self, topic: impl Into<String> + Send) -> Result<WithCtx<Channel>> {
+        self.edit(|c| c.topic(topic)).await
+    }
+}
+
+/// A guard which keeps the typing indicator active in a channel until it is dropped. Created with
+/// [`WithCtx<ChannelId>::typing`].
+#[derive(Debug)]
+#[must_use = "the typing indicator stops when the guard is dropped"]
+pub struct TypingGuard(JoinHandle<()>);
+
+impl TypingGuard {
+    /// The interval at which the typing indicator is re-triggered.
+    pub const INTERVAL: Duration = Duration::from_secs(5);
+
+    /// Stops the typing indicator. This is equivalent to dropping the guard.
+    pub fn stop(self) {}
+}
+
+impl Drop for TypingGuard {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Represents an Adapt channel, which is either a guild channel or a DM channel.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Channel {
+    /// The ID of the channel.
+    pub id: ChannelId,
+    /// The ID of the guild the channel belongs to, or `None` if this is a DM channel.
+    pub guild_id: Option<GuildId>,
+    /// The type of the channel.
+    pub kind: ChannelType,
+    /// The name of the channel. This is `None` for DM channels.
+    pub name: Option<String>,
+    /// The topic of the channel, if any.
+    pub topic: Option<String>,
+    /// The position of the channel among its siblings. This is `None` for DM channels.
+    pub position: Option<u16>,
+    /// The ID of the category the channel is in, if any.
+    pub parent_id: Option<ChannelId>,
+    /// The permission overwrites of the channel, keyed by role or user ID. This is empty for DM
+    /// channels.
+    pub overwrites: Vec<PermissionOverwrite>,
+}
+
+impl Channel {
+    /// Creates a new channel from a raw [`essence::models::Channel`].
+    #[must_use]
+    pub fn from_raw(channel: essence::models::Channel) -> Self {
+        match channel {
+            essence::models::Channel::Guild(channel) => {
+                let (kind, topic) = match channel.info {
+                    GuildChannelInfo::Text { topic, .. } => (ChannelType::Text, topic),
+                    GuildChannelInfo::Announcement { topic, .. } => {
+                        (ChannelType::Announcement, topic)
+                    }
+                    GuildChannelInfo::Voice { .. } => (ChannelType::Voice, None),
+                    GuildChannelInfo::Category => (ChannelType::Category, None),
+                };
+
+                Self {
+                    id: channel.id.into(),
+                    guild_id: Some(channel.guild_id.into()),
+                    kind,
+                    name: Some(channel.name),
+                    topic,
+                    position: Some(channel.position),
+                    parent_id: channel.parent_id.map(Into::into),
+                    overwrites: channel.overwrites,
+                }
+            }
+            essence::models::Channel::Dm(channel) => DmChannel::from_raw(channel).into(),
+        }
+    }
+}
+
+impl WithCtx<Channel> {
+    /// Returns the ID of the channel.
+    pub fn id(&self) -> WithCtx<ChannelId> {
+        self.ctx.clone().with(self.inner().id)
+    }
+
+    /// Edits the channel with the changes configured by the given function. See
+    /// [`WithCtx<ChannelId>::edit`].
+    pub async fn edit(
+        &self,
+        f: impl FnOnce(EditChannelBuilder) -> EditChannelBuilder + Send,
+    ) -> Result<WithCtx<Channel>> {
+        self.id().edit(f).await
+    }
+}
+
+crate::impl_common_traits!(Channel);
+
+/// Represents a DM channel with another user or a group DM channel.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DmChannel {
+    /// The ID of the channel.
+    pub id: ChannelId,
+    /// The type of the channel, which is either [`ChannelType::Dm`] or [`ChannelType::Group`].
+    pub kind: ChannelType,
+    /// The name of the group. This is `None` for DM channels.
+    pub name: Option<String>,
+    /// The topic of the group, if any. This is `None` for DM channels.
+    pub topic: Option<String>,
+    /// The icon of the group, if any. This is `None` for DM channels.
+    pub icon: Option<String>,
+    /// The ID of the user who owns the group. This is `None` for DM channels.
+    pub owner_id: Option<UserId>,
+    /// The IDs of the recipients of the channel, including the client user.
+    pub recipient_ids: Vec<UserId>,
+    /// The ID of the last message sent in the channel, if any.
+    pub last_message_id: Option<MessageId>,
+}
+
+impl DmChannel {
+    /// Creates a new DM channel from a raw [`essence::models::DmChannel`].
+    #[must_use]
+    pub fn from_raw(channel: essence::models::DmChannel) -> Self {
+        let (kind, name, topic, icon, owner_id, recipient_ids) = match channel.info {
+            DmChannelInfo::Dm { recipient_ids } => (
+                ChannelType::Dm,
+                None,
+                None,
+                None,
+                None,
+                recipient_ids.to_vec(),
+            ),
+            DmChannelInfo::Group {
+                name,
+                topic,
+                icon,
+                owner_id,
+                recipient_ids,
+            } => (
+                ChannelType::Group,
+                Some(name),
+                topic,
+                icon,
+                Some(owner_id.into()),
+                recipient_ids,
+            ),
+        };
+
+        Self {
+            id: channel.id.into(),
+            kind,
+            name,
+            topic,
+            icon,
+            owner_id,
+            recipient_ids: recipient_ids.into_iter().map(Into::into).collect(),
+            last_message_id: channel.last_message_id.map(Into::into),
+        }
+    }
+
+    /// Whether this is a group DM channel.
+    #[must_use]
+    pub fn is_group(&self) -> bool {
+        self.kind == ChannelType::Group
+    }
+
+    /// Returns the ID of the other user in this DM channel, given the ID of the client user.
+    /// Returns `None` for group DM channels.
+    #[must_use]
+    pub fn recipient(&self, current_user_id: UserId) -> Option<UserId> {
+        if self.is_group() {
+            return None;
+        }
+        self.recipient_ids
+            .iter()
+            .copied()
+            .find(|id| *id != current_user_id)
+            // A DM channel with oneself lists the same user twice
+            .or_else(|| self.recipient_ids.first().copied())
+    }
+}
+
+impl WithCtx<DmChannel> {
+    /// Returns the ID of the channel.
+    pub fn id(&self) -> WithCtx<ChannelId> {
+        self.ctx.clone().with(self.inner().id)
+    }
+
+    /// Returns the other user in this DM channel. Returns `None` for group DM channels, or if the
+    /// client user is not known yet because the client is not ready.
+    pub fn recipient(&self) -> Option<WithCtx<UserId>> {
+        let current_user = self.ctx.cache().current_user()?;
+        let recipient = self.inner().recipient(current_user.id)?;
+        Some(self.ctx.clone().with(recipient))
+    }
+}
+
+impl From<DmChannel> for Channel {
+    fn from(channel: DmChannel) -> Self {
+        Self {
+            id: channel.id,
+            guild_id: None,
+            kind: channel.kind,
+            name: channel.name,
+            topic: channel.topic,
+            position: None,
+            parent_id: None,
+            overwrites: Vec::new(),
+        }
+    }
+}
+
+crate::impl_common_traits!(DmChannel);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dm_channel_recipient() {
+        let channel = DmChannel::from_raw(essence::models::DmChannel {
+            id: 1,
+            info: DmChannelInfo::Dm {
+                recipient_ids: [2, 3],
+            },
+            last_message_id: Some(4),
+        });
+        assert_eq!(channel.recipient(UserId::from(2)), Some(UserId::from(3)));
+        assert_eq!(channel.recipient(UserId::from(3)), Some(UserId::from(2)));
+        assert_eq!(channel.last_message_id, Some(MessageId::from(4)));
+
+        let channel = Channel::from(channel);
+        assert_eq!(channel.kind, ChannelType::Dm);
+        assert_eq!(channel.guild_id, None);
+    }
 }
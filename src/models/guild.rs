@@ -0,0 +1,586 @@
+use crate::builder::{
+    ChannelBuilder, EditGuildBuilder, GuildQueryBuilder, InviteBuilder, RoleBuilder,
+};
+use crate::http::endpoints;
+use crate::models::{Channel, ChannelId, Id, Invite, InviteCode, Member, Role, RoleId, UserId};
+use crate::{Context, Result, WithCtx};
+use essence::{http::guild::DeleteGuildPayload, models::GuildMemberCount};
+use serde::{Deserialize, Serialize};
+
+crate::id_type! {
+    /// Represents an Adapt guild by its ID.
+    pub struct GuildId: Guild;
+}
+
+impl GuildId {
+    /// Attaches a [`Context`] to this guild ID to allow it to access shared client state.
+    pub const fn with_ctx(self, ctx: Context) -> WithCtx<Self> {
+        ctx.with(self)
+    }
+}
+
+impl WithCtx<GuildId> {
+    /// Fetches this guild, including the related objects selected by the given query.
+    pub async fn fetch(&self, query: GuildQueryBuilder) -> Result<WithCtx<Guild>> {
+        let guild = self
+            .ctx
+            .http()
+            .request(endpoints::GetGuild(self.get()))
+            .query(query.build())
+            .await?;
+
+        Ok(self.ctx.clone().with(Guild::from_raw(guild)))
+    }
+
+    /// Fetches all channels in this guild.
+    pub async fn channels(&self) -> Result<Vec<WithCtx<Channel>>> {
+        let channels = self
+            .ctx
+            .http()
+            .request(endpoints::GetGuildChannels(self.get()))
+            .await?;
+
+        Ok(channels
+            .into_iter()
+            .map(|channel| self.ctx.clone().with(Channel::from_raw(channel)))
+            .collect())
+    }
+
+    /// Fetches all roles in this guild.
+    pub async fn roles(&self) -> Result<Vec<WithCtx<Role>>> {
+        let roles = self
+            .ctx
+            .http()
+            .request(endpoints::GetAllRoles(self.get()))
+            .await?;
+
+        Ok(roles
+            .into_iter()
+            .map(|role| self.ctx.clone().with(Role::from_raw(role)))
+            .collect())
+    }
+
+    /// Fetches all members of this guild.
+    pub async fn members(&self) -> Result<Vec<WithCtx<Member>>> {
+        let members = self
+            .ctx
+            .http()
+            .request(endpoints::GetAllMembers(self.get()))
+            .await?;
+
+        Ok(members
+            .into_iter()
+            .map(|member| self.ctx.clone().with(Member::from_raw(member)))
+            .collect())
+    }
+
+    /// Fetches the member of this guild with the given user ID.
+    pub async fn member(&self, user_id: UserId) -> Result<WithCtx<Member>> {
+        let member = self
+            .ctx
+            .http()
+            .request(endpoints::GetMember(self.get(), user_id.get()))
+            .await?;
+
+        Ok(self.ctx.clone().with(Member::from_raw(member)))
+    }
+
+    /// Fetches the client user as a member of this guild.
+    pub async fn me(&self) -> Result<WithCtx<Member>> {
+        let member = self
+            .ctx
+            .http()
+            .request(endpoints::GetAuthenticatedUserAsMember(self.get()))
+            .await?;
+
+        Ok(self.ctx.clone().with(Member::from_raw(member)))
+    }
+
+    /// Fetches all invites to this guild.
+    pub async fn invites(&self) -> Result<Vec<WithCtx<Invite>>> {
+        let invites = self
+            .ctx
+            .http()
+            .request(endpoints::GetGuildInvites(self.get()))
+            .await?;
+
+        Ok(invites
+            .into_iter()
+            .map(|invite| self.ctx.clone().with(Invite::from_raw(invite)))
+            .collect())
+    }
+
+    /// Fetches the vanity invite code of this guild, if it has one.
+    ///
+    /// Vanity codes cannot be changed through the API.
+    pub async fn vanity(&self) -> Result<Option<InviteCode>> {
+        Ok(self
+            .fetch(GuildQueryBuilder::new())
+            .await?
+            .into_inner()
+            .vanity)
+    }
+
+    /// Creates a new channel in this guild, configured by the given function.
+    ///
+    /// If a position is requested, the channels sharing the category of the new channel are
+    /// reordered after the channel is created.
+    ///
+    /// # Errors
+    /// * If the channel could not be created.
+    /// * If the channel could not be moved to the requested position. The created channel is
+    ///   deleted again in that case, and its ID is logged if that fails as well.
+    pub async fn create_channel(
+        &self,
+        f: impl FnOnce(ChannelBuilder) -> ChannelBuilder + Send,
+    ) -> Result<WithCtx<Channel>> {
+        let builder = f(ChannelBuilder::default());
+        let position = builder.requested_position();
+
+        let channel = self
+            .ctx
+            .http()
+            .request(endpoints::CreateGuildChannel(self.get()))
+            .body(builder.build()?)
+            .await?;
+        let mut channel = Channel::from_raw(channel);
+
+        if let Some(position) = position {
+            if let Err(err) = self.move_channel(channel.id, position).await {
+                let deleted = self
+                    .ctx
+                    .http()
+                    .request(endpoints::DeleteChannel(channel.id.get()))
+                    .await;
+                if let Err(delete_err) = deleted {
+                    warn!(
+                        "Could not delete channel {} after failing to move it: {delete_err}",
+                        channel.id,
+                    );
+                }
+                return Err(err);
+            }
+            channel.position = Some(position);
+        }
+        Ok(self.ctx.clone().with(channel))
+    }
+
+    /// Edits the guild with the changes configured by the given function, returning the updated
+    /// guild.
+    pub async fn edit(
+        &self,
+        f: impl FnOnce(EditGuildBuilder) -> EditGuildBuilder + Send,
+    ) -> Result<WithCtx<Guild>> {
+        let guild = self
+            .ctx
+            .http()
+            .request(endpoints::EditGuild(self.get()))
+            .body(f(EditGuildBuilder::new()).build()?)
+            .await?;
+
+        Ok(self.ctx.clone().with(Guild::from_raw(guild)))
+    }
+
+    /// Leaves this guild. The owner of a guild cannot leave it; see [`Self::delete`] instead.
+    pub async fn leave(&self) -> Result<()> {
+        self.ctx
+            .http()
+            .request(endpoints::LeaveGuild(self.get()))
+            .await
+    }
+
+    /// Deletes this guild. Only the owner of the guild can delete it.
+    ///
+    /// User accounts must confirm the deletion with their password, while bot accounts have no
+    /// password and should pass `None`. See [`WithCtx<Guild>::delete`] to check ownership before
+    /// making the request.
+    pub async fn delete(&self, password: Option<&str>) -> Result<()> {
+        self.ctx
+            .http()
+            .request(endpoints::DeleteGuild(self.get()))
+            .body(DeleteGuildPayload {
+                password: password.map(ToString::to_string),
+            })
+            .await
+    }
+
+    /// Creates a new role in this guild.
+    pub async fn create_role(&self, builder: RoleBuilder) -> Result<WithCtx<Role>> {
+        let role = self
+            .ctx
+            .http()
+            .request(endpoints::CreateRole(self.get()))
+            .body(builder.build()?)
+            .await?;
+
+        Ok(self.ctx.clone().with(Role::from_raw(role)))
+    }
+
+    /// Creates a new invite to this guild.
+    pub async fn create_invite(&self, builder: InviteBuilder) -> Result<WithCtx<Invite>> {
+        let invite = self
+            .ctx
+            .http()
+            .request(endpoints::CreateInviteToGuild(self.get()))
+            .body(builder.build()?)
+            .await?;
+
+        Ok(self.ctx.clone().with(Invite::from_raw(invite)))
+    }
+
+    /// Reorders the channels of this guild so that the given channels appear in the given order
+    /// among the other channels in their categories. Channels that are not given keep their
+    /// relative order, after the given channels.
+    ///
+    /// Channels cannot be moved between categories this way. The complete channel order of the
+    /// guild is sent in a single request, unless the order does not change.
+    pub async fn reorder_channels(
+        &self,
+        order: impl IntoIterator<Item = ChannelId> + Send,
+    ) -> Result<()> {
+        let channels = self.channels().await?;
+        let channels = channels.iter().map(WithCtx::inner).collect::<Vec<_>>();
+        let order = order.into_iter().collect::<Vec<_>>();
+
+        let Some(positions) = channel_order(&channels, &order) else {
+            return Ok(());
+        };
+        self.ctx
+            .http()
+            .request(endpoints::EditChannelPositions(self.get()))
+            .body(positions)
+            .await
+    }
+
+    /// Moves the given channel to the given position among the other channels in its category,
+    /// shifting the channels after it. Nothing is sent if the channel is already there.
+    pub async fn move_channel(&self, channel_id: ChannelId, position: u16) -> Result<()> {
+        let channels = self.channels().await?;
+        let Some(parent_id) = channels
+            .iter()
+            .find(|channel| channel.id == channel_id)
+            .map(|channel| channel.parent_id)
+        else {
+            return Ok(());
+        };
+
+        let mut siblings = channels
+            .iter()
+            .map(WithCtx::inner)
+            .filter(|channel| channel.parent_id == parent_id && channel.id != channel_id)
+            .collect::<Vec<_>>();
+        siblings.sort_by_key(|channel| (channel.position, channel.id));
+
+        let mut order = siblings
+            .into_iter()
+            .map(|channel| channel.id)
+            .collect::<Vec<_>>();
+        order.insert(usize::from(position).min(order.len()), channel_id);
+        self.reorder_channels(order).await
+    }
+
+    /// Fetches the IDs of all roles in this guild, ordered from the lowest to the highest
+    /// position. The default role, which shares its ID with the guild and cannot be moved, is not
+    /// included.
+    async fn role_order(&self) -> Result<Vec<RoleId>> {
+        let mut roles = self.roles().await?;
+        roles.retain(|role| role.id.get() != self.get());
+        roles.sort_by(|a, b| a.cmp_position(b));
+        Ok(roles.into_iter().map(|role| role.id).collect())
+    }
+
+    /// Sends the given role order, from the lowest to the highest position, unless it is the same
+    /// as the current order.
+    async fn submit_role_order(&self, current: &[RoleId], order: Vec<RoleId>) -> Result<()> {
+        if order == current {
+            return Ok(());
+        }
+        self.ctx
+            .http()
+            .request(endpoints::EditRolePositions(self.get()))
+            .body(order.into_iter().map(|id| id.get()).collect())
+            .await
+    }
+
+    /// Moves the given role to the given position in the role hierarchy, where `0` is the lowest
+    /// position above the default role. The roles in between are shifted to make room. Nothing is
+    /// sent if the role is already there, or if it is the default role.
+    pub async fn move_role(&self, role_id: RoleId, to_position: u16) -> Result<()> {
+        let current = self.role_order().await?;
+        if !current.contains(&role_id) {
+            return Ok(());
+        }
+
+        let mut order = current.clone();
+        order.retain(|id| *id != role_id);
+        order.insert(usize::from(to_position).min(order.len()), role_id);
+        self.submit_role_order(&current, order).await
+    }
+
+    /// Reorders the given roles so that they appear in the given order, from the lowest to the
+    /// highest position. The given roles are rearranged among the positions they currently
+    /// occupy, so roles that are not given keep their positions.
+    ///
+    /// Nothing is sent if the order does not change.
+    pub async fn reorder_roles(
+        &self,
+        order: impl IntoIterator<Item = RoleId> + Send,
+    ) -> Result<()> {
+        let current = self.role_order().await?;
+        let order = rearrange(&current, &order.into_iter().collect::<Vec<_>>());
+        self.submit_role_order(&current, order).await
+    }
+}
+
+/// Rearranges the given items among the slots of `current` they occupy, leaving other items in
+/// place. Items that are not in `current` are ignored.
+fn rearrange<T: Copy + PartialEq>(current: &[T], order: &[T]) -> Vec<T> {
+    let mut items = Vec::with_capacity(order.len());
+    for item in order {
+        if current.contains(item) && !items.contains(item) {
+            items.push(*item);
+        }
+    }
+
+    let mut items = items.into_iter();
+    current
+        .iter()
+        .map(|item| {
+            if order.contains(item) {
+                items.next().unwrap_or(*item)
+            } else {
+                *item
+            }
+        })
+        .collect()
+}
+
+/// Computes the complete channel order of the guild after applying the given order, or `None` if
+/// it does not change. Top-level channels come first, followed by the channels of each category in
+/// the order of the categories.
+fn channel_order(channels: &[&Channel], order: &[ChannelId]) -> Option<Vec<u64>> {
+    let mut sorted = channels.to_vec();
+    sorted.sort_by_key(|channel| (channel.position, channel.id));
+
+    let mut parents = vec![None];
+    parents.extend(
+        sorted
+            .iter()
+            .filter(|channel| channel.parent_id.is_none())
+            .map(|channel| Some(channel.id)),
+    );
+    // Channels whose category is unknown are kept as well
+    for channel in &sorted {
+        if !parents.contains(&channel.parent_id) {
+            parents.push(channel.parent_id);
+        }
+    }
+
+    let mut changed = false;
+    let mut positions = Vec::with_capacity(sorted.len());
+    for parent_id in parents {
+        let current = sorted
+            .iter()
+            .filter(|channel| channel.parent_id == parent_id)
+            .map(|channel| channel.id)
+            .collect::<Vec<_>>();
+
+        let mut desired = Vec::with_capacity(current.len());
+        for id in order.iter().chain(&current) {
+            if current.contains(id) && !desired.contains(id) {
+                desired.push(*id);
+            }
+        }
+        changed |= desired != current;
+        positions.extend(desired.into_iter().map(|id| id.get()));
+    }
+    changed.then_some(positions)
+}
+
+/// Represents an Adapt guild.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Guild {
+    /// The ID of the guild.
+    pub id: GuildId,
+    /// The name of the guild.
+    pub name: String,
+    /// The description of the guild, if any.
+    pub description: Option<String>,
+    /// The path to the icon of the guild on convey, if any.
+    pub icon: Option<String>,
+    /// The path to the banner of the guild on convey, if any.
+    pub banner: Option<String>,
+    /// The ID of the user who owns the guild.
+    pub owner_id: UserId,
+    /// The vanity invite code of the guild, if it has one.
+    pub vanity: Option<InviteCode>,
+    /// The number of members in the guild, if it was included in the response.
+    pub member_count: Option<GuildMemberCount>,
+    /// The channels in the guild, if they were requested.
+    pub channels: Option<Vec<Channel>>,
+    /// The members of the guild, if they were requested.
+    pub members: Option<Vec<Member>>,
+    /// The roles in the guild, if they were requested.
+    pub roles: Option<Vec<Role>>,
+}
+
+impl Guild {
+    /// Creates a new guild from a raw [`essence::models::Guild`].
+    #[must_use]
+    pub fn from_raw(guild: essence::models::Guild) -> Self {
+        Self {
+            channels: guild.channels.map(|channels| {
+                channels
+                    .into_iter()
+                    .map(|channel| Channel::from_raw(essence::models::Channel::Guild(channel)))
+                    .collect()
+            }),
+            members: guild
+                .members
+                .map(|members| members.into_iter().map(Member::from_raw).collect()),
+            roles: guild
+                .roles
+                .map(|roles| roles.into_iter().map(Role::from_raw).collect()),
+            ..Self::from_raw_partial(guild.partial)
+        }
+    }
+
+    /// Creates a new guild from a raw [`essence::models::PartialGuild`].
+    #[must_use]
+    pub fn from_raw_partial(guild: essence::models::PartialGuild) -> Self {
+        Self {
+            id: guild.id.into(),
+            name: guild.name,
+            description: guild.description,
+            icon: guild.icon,
+            banner: guild.banner,
+            owner_id: guild.owner_id.into(),
+            vanity: guild.vanity_url.as_deref().and_then(InviteCode::parse),
+            member_count: guild.member_count,
+            channels: None,
+            members: None,
+            roles: None,
+        }
+    }
+}
+
+impl WithCtx<Guild> {
+    /// Returns the ID of the guild.
+    pub fn id(&self) -> WithCtx<GuildId> {
+        self.ctx.clone().with(self.inner().id)
+    }
+
+    /// Creates a new channel in this guild, configured by the given function. See
+    /// [`WithCtx<GuildId>::create_channel`].
+    pub async fn create_channel(
+        &self,
+        f: impl FnOnce(ChannelBuilder) -> ChannelBuilder + Send,
+    ) -> Result<WithCtx<Channel>> {
+        self.id().create_channel(f).await
+    }
+
+    /// Whether the client user owns this guild.
+    pub fn is_owned(&self) -> Result<bool> {
+        Ok(self.ctx.http().user_id()? == self.owner_id)
+    }
+
+    /// Deletes this guild if the client user owns it. See [`WithCtx<GuildId>::delete`].
+    ///
+    /// # Errors
+    /// * An error for which [`Error::is_not_owner`](crate::Error::is_not_owner) is `true` if the
+    ///   client user does not own the guild, without making a request. Use
+    ///   [`WithCtx<GuildId>::leave`] to leave the guild instead.
+    pub async fn delete(&self, password: Option<&str>) -> Result<()> {
+        if !self.is_owned()? {
+            return Err(crate::Error::Http(essence::Error::NotOwner {
+                guild_id: self.id.get(),
+                message: "only the owner of a guild can delete it".to_string(),
+            }));
+        }
+        self.id().delete(password).await
+    }
+
+    /// Edits the guild with the changes configured by the given function. See
+    /// [`WithCtx<GuildId>::edit`].
+    pub async fn edit(
+        &self,
+        f: impl FnOnce(EditGuildBuilder) -> EditGuildBuilder + Send,
+    ) -> Result<WithCtx<Guild>> {
+        self.id().edit(f).await
+    }
+
+    /// Creates a new channel inside the given category at the given position among the channels
+    /// in that category, configured by the given function.
+    ///
+    /// The channels in the category are reordered after the channel is created, since the API
+    /// does not accept a position on creation.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use adapt::{models::{ChannelId, Guild}, WithCtx};
+    /// # async fn run(guild: WithCtx<Guild>, category: ChannelId) -> adapt::Result<()> {
+    /// let channel = guild
+    ///     .create_channel_in(category, 0, |channel| channel.name("announcements"))
+    ///     .await?;
+    /// # Ok(()) }
+    /// ```
+    pub async fn create_channel_in(
+        &self,
+        category_id: ChannelId,
+        position: u16,
+        f: impl FnOnce(ChannelBuilder) -> ChannelBuilder + Send,
+    ) -> Result<WithCtx<Channel>> {
+        self.id()
+            .create_channel(|builder| f(builder).parent(category_id).position(position))
+            .await
+    }
+}
+
+crate::impl_common_traits!(Guild);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use essence::models::ChannelType;
+
+    fn channel(id: u64, parent_id: Option<u64>, position: u16) -> Channel {
+        Channel {
+            id: ChannelId::from(id),
+            guild_id: Some(GuildId::from(1)),
+            kind: ChannelType::Text,
+            name: Some(format!("channel-{id}")),
+            topic: None,
+            position: Some(position),
+            parent_id: parent_id.map(ChannelId::from),
+            overwrites: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn complete_channel_order() {
+        let channels = [
+            channel(10, None, 0),
+            channel(11, None, 1),
+            channel(20, Some(10), 0),
+            channel(21, Some(10), 1),
+            channel(22, Some(10), 2),
+        ];
+        let channels = channels.iter().collect::<Vec<_>>();
+        let ids = |ids: &[u64]| ids.iter().copied().map(ChannelId::from).collect::<Vec<_>>();
+
+        // Every channel is included, grouped by category
+        assert_eq!(
+            channel_order(&channels, &ids(&[22, 20, 10])),
+            Some(vec![10, 11, 22, 20, 21]),
+        );
+        assert_eq!(channel_order(&channels, &ids(&[10, 20, 21])), None);
+        assert_eq!(channel_order(&channels, &ids(&[99])), None);
+    }
+
+    #[test]
+    fn rearrange_positions() {
+        assert_eq!(rearrange(&[1, 2, 3, 4], &[4, 2]), [1, 4, 3, 2]);
+        assert_eq!(rearrange(&[1, 2, 3, 4], &[3, 9, 1, 3]), [3, 2, 1, 4]);
+        assert_eq!(rearrange(&[1, 2, 3], &[]), [1, 2, 3]);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::builder::MessageHistoryQueryBuilder;
+use crate::models::{timestamp, ChannelId, Message, MessageId, Timestamp};
+use crate::{Result, WithCtx};
+use essence::snowflake::SnowflakeReader;
+use futures_util::stream::{self, BoxStream, Stream, StreamExt};
+use std::collections::VecDeque;
+use std::fmt::{self, Debug};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+type Predicate = Arc<dyn Fn(&Message) -> bool + Send + Sync>;
+
+/// A stream over the message history of a channel, which lazily fetches pages of messages as they
+/// are consumed. Obtained with [`WithCtx<ChannelId>::history_stream`].
+///
+/// By default, messages are streamed newest first, starting from the latest message. The stream
+/// can be configured with the builder methods on this type, which must be called before the
+/// stream is first polled.
+///
+/// # Example
+/// ```no_run
+/// use futures_util::TryStreamExt;
+/// # use adapt::{models::{ChannelId, MessageId}, WithCtx};
+///
+/// # async fn run(channel: WithCtx<ChannelId>, start: MessageId) -> adapt::Result<()> {
+/// let messages = channel
+///     .history_stream()
+///     .oldest_first()
+///     .after(start)
+///     .take_until(|message| message.content == "!stop")
+///     .try_collect::<Vec<_>>()
+///     .await?;
+/// # Ok(()) }
+/// ```
+#[must_use = "streams do nothing unless polled"]
+pub struct MessageHistory {
+    channel: WithCtx<ChannelId>,
+    oldest_first: bool,
+    after: Option<MessageId>,
+    before: Option<MessageId>,
+    before_timestamp: Option<Timestamp>,
+    until: Option<Predicate>,
+    page_size: u8,
+    inner: Option<BoxStream<'static, Result<WithCtx<Message>>>>,
+}
+
+impl Debug for MessageHistory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageHistory")
+            .field("channel", &self.channel)
+            .field("oldest_first", &self.oldest_first)
+            .field("after", &self.after)
+            .field("before", &self.before)
+            .field("before_timestamp", &self.before_timestamp)
+            .field("page_size", &self.page_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MessageHistory {
+    pub(crate) fn new(channel: WithCtx<ChannelId>) -> Self {
+        Self {
+            channel,
+            oldest_first: false,
+            after: None,
+            before: None,
+            before_timestamp: None,
+            until: None,
+            page_size: MessageHistoryQueryBuilder::MAX_LIMIT,
+            inner: None,
+        }
+    }
+
+    /// Streams the oldest messages first instead of the newest.
+    pub const fn oldest_first(mut self) -> Self {
+        self.oldest_first = true;
+        self
+    }
+
+    /// Only streams messages sent after the given message.
+    pub const fn after(mut self, message_id: MessageId) -> Self {
+        self.after = Some(message_id);
+        self
+    }
+
+    /// Only streams messages sent before the given message.
+    pub const fn before_message(mut self, message_id: MessageId) -> Self {
+        self.before = Some(message_id);
+        self
+    }
+
+    /// Only streams messages sent before the given time.
+    ///
+    /// The time is converted into the lowest message ID which could have been created at that
+    /// time, so no messages sent after it are fetched.
+    pub fn before(mut self, timestamp: Timestamp) -> Self {
+        self.before_timestamp = Some(timestamp);
+        self
+    }
+
+    /// Ends the stream at the first message that matches the given predicate. The matching message
+    /// is not yielded.
+    pub fn take_until(
+        mut self,
+        predicate: impl Fn(&Message) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.until = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Sets the number of messages fetched per request. Defaults to
+    /// [`MessageHistoryQueryBuilder::MAX_LIMIT`].
+    pub const fn page_size(mut self, page_size: u8) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Returns the lowest ID a snowflake created at the given time can have, so that every message
+    /// with a lower ID was sent before it.
+    fn snowflake_at(timestamp: &Timestamp) -> MessageId {
+        /// The number of bits below the creation time: the model type, node and increment.
+        const TIMESTAMP_SHIFT: u32 = 18;
+
+        let epoch = SnowflakeReader::new(0).timestamp_millis();
+        let millis = timestamp::to_millis(timestamp).saturating_sub(epoch);
+        let id = millis
+            .checked_shl(TIMESTAMP_SHIFT)
+            .filter(|id| id >> TIMESTAMP_SHIFT == millis)
+            .unwrap_or(u64::MAX);
+        MessageId::from(id)
+    }
+
+    fn into_stream(self) -> BoxStream<'static, Result<WithCtx<Message>>> {
+        struct State {
+            channel: WithCtx<ChannelId>,
+            oldest_first: bool,
+            after: Option<MessageId>,
+            before: Option<MessageId>,
+            page_size: u8,
+            buffer: VecDeque<Message>,
+            exhausted: bool,
+        }
+
+        let before_timestamp = self.before_timestamp.as_ref().map(Self::snowflake_at);
+        let before = match (self.before, before_timestamp) {
+            (Some(before), Some(bound)) => Some(before.min(bound)),
+            (before, bound) => before.or(bound),
+        };
+        let state = State {
+            channel: self.channel,
+            oldest_first: self.oldest_first,
+            after: self.after,
+            before,
+            page_size: self.page_size,
+            buffer: VecDeque::new(),
+            exhausted: false,
+        };
+
+        let messages = stream::try_unfold(state, |mut state| async move {
+            if state.buffer.is_empty() && !state.exhausted {
+                let mut query = MessageHistoryQueryBuilder::new().limit(state.page_size);
+                if state.oldest_first {
+                    query = query.oldest_first();
+                }
+                if let Some(after) = state.after {
+                    query = query.after(after);
+                }
+                if let Some(before) = state.before {
+                    query = query.before(before);
+                }
+
+                let page = state.channel.history(query).await?;
+                state.exhausted = page.len() < usize::from(state.page_size);
+
+                // Move the cursor past the last message of the page
+                if let Some(last) = page.last() {
+                    if state.oldest_first {
+                        state.after = Some(last.id);
+                    } else {
+                        state.before = Some(last.id);
+                    }
+                }
+                state
+                    .buffer
+                    .extend(page.into_iter().map(WithCtx::into_inner));
+            }
+
+            let Some(message) = state.buffer.pop_front() else {
+                return Ok(None);
+            };
+            let message = state.channel.ctx.clone().with(message);
+            Ok(Some((message, state)))
+        });
+
+        let until = self.until;
+        messages
+            .take_while(move |message| {
+                let keep = match message {
+                    Ok(message) => !until.as_ref().is_some_and(|until| until(message)),
+                    Err(_) => true,
+                };
+                async move { keep }
+            })
+            .boxed()
+    }
+}
+
+impl Stream for MessageHistory {
+    type Item = Result<WithCtx<Message>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.inner.is_none() {
+            let history = Self {
+                channel: self.channel.clone(),
+                oldest_first: self.oldest_first,
+                after: self.after,
+                before: self.before,
+                before_timestamp: self.before_timestamp,
+                until: self.until.take(),
+                page_size: self.page_size,
+                inner: None,
+            };
+            self.inner = Some(history.into_stream());
+        }
+
+        self.inner
+            .as_mut()
+            .expect("stream was initialized")
+            .poll_next_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Id;
+
+    #[test]
+    fn snowflake_at_timestamp() {
+        let epoch = SnowflakeReader::new(0).timestamp_millis();
+        let time = timestamp::from_millis(epoch + 60_000);
+
+        let bound = MessageHistory::snowflake_at(&time);
+        assert_eq!(bound.timestamp(), time);
+        assert!(MessageId::from(bound.get() - 1).timestamp() < time);
+        assert_eq!(
+            MessageHistory::snowflake_at(&timestamp::from_millis(0)),
+            MessageId::from(0)
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use crate::models::{GuildId, UserId};
+use crate::WithCtx;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+use std::hash::{Hash, Hasher};
+use url::Url;
+
+/// The code of an invite to an Adapt guild.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct InviteCode(String);
+
+impl InviteCode {
+    /// Parses an invite code from either a bare code (e.g. `abc`) or a full invite link (e.g.
+    /// `https://adapt.chat/invite/abc`). Returns `None` if the input is neither.
+    ///
+    /// Links are accepted from any host, so that links to self-hosted instances can be parsed.
+    ///
+    /// # Example
+    /// ```
+    /// use adapt::models::InviteCode;
+    ///
+    /// let code = InviteCode::parse("https://adapt.chat/invite/abc").unwrap();
+    /// assert_eq!(code.as_str(), "abc");
+    /// assert_eq!(InviteCode::parse("abc"), Some(code));
+    /// assert!(InviteCode::parse("https://adapt.chat/guilds/123").is_none());
+    /// ```
+    #[must_use]
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        let code = match Url::parse(input) {
+            Ok(url) => {
+                let mut segments = url.path_segments()?.filter(|segment| !segment.is_empty());
+                match (segments.next(), segments.next(), segments.next()) {
+                    (Some("invite" | "invites"), Some(code), None) => code.to_string(),
+                    _ => return None,
+                }
+            }
+            Err(_) => input.to_string(),
+        };
+
+        (!code.is_empty()
+            && code
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .then_some(Self(code))
+    }
+
+    /// Returns the code as a string.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the invite link for this code on the web app at the given base URL.
+    #[must_use]
+    pub fn url(&self, app_url: &str) -> String {
+        format!("{}/invite/{}", app_url.trim_end_matches('/'), self.0)
+    }
+}
+
+impl Display for InviteCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for InviteCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Represents an invite to an Adapt guild.
+#[derive(Clone, Debug, Serialize)]
+pub struct Invite {
+    /// The code of the invite.
+    pub code: String,
+    /// The ID of the guild the invite is for.
+    pub guild_id: GuildId,
+    /// The ID of the user who created the invite.
+    pub inviter_id: UserId,
+    /// The number of times the invite has been used.
+    pub uses: u32,
+    /// The maximum number of times the invite can be used, where `0` means unlimited.
+    pub max_uses: u32,
+    /// How long the invite is valid for after its creation, in seconds, where `0` means the
+    /// invite never expires.
+    pub max_age: u32,
+}
+
+impl Invite {
+    /// Creates a new invite from a raw [`essence::models::Invite`].
+    #[must_use]
+    pub fn from_raw(invite: essence::models::Invite) -> Self {
+        Self {
+            code: invite.code,
+            guild_id: invite.guild_id.into(),
+            inviter_id: invite.inviter_id.into(),
+            uses: invite.uses,
+            max_uses: invite.max_uses,
+            max_age: invite.max_age,
+        }
+    }
+
+    /// Returns the code of the invite as an [`InviteCode`].
+    #[must_use]
+    pub fn invite_code(&self) -> InviteCode {
+        InviteCode(self.code.clone())
+    }
+}
+
+impl WithCtx<Invite> {
+    /// Returns the link to this invite on the web app of the configured server.
+    #[must_use]
+    pub fn url(&self) -> String {
+        self.invite_code().url(self.ctx.http().app_url())
+    }
+
+    /// Returns the ID of the guild the invite is for.
+    pub fn guild_id(&self) -> WithCtx<GuildId> {
+        self.ctx.clone().with(self.inner().guild_id)
+    }
+}
+
+impl PartialEq for Invite {
+    fn eq(&self, other: &Self) -> bool {
+        self.code == other.code
+    }
+}
+
+impl Eq for Invite {}
+
+impl Hash for Invite {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.code.hash(state);
+    }
+}
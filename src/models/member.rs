@@ -0,0 +1,451 @@
+use crate::builder::EditMemberBuilder;
+use crate::http::endpoints;
+use crate::models::message::IntoCreateMessage;
+use crate::models::{
+    Channel, GuildId, Id, Message, PermissionPair, Permissions, Role, RoleId, UserId,
+};
+use crate::{Context, Result, WithCtx};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::future::IntoFuture;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+/// Represents an Adapt guild member by its user ID, aware of its parent guild ID.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[must_use = "this struct does nothing on its own"]
+pub struct PartialMember {
+    /// The ID of the user this member represents.
+    pub id: UserId,
+    /// The ID of the guild the member belongs to.
+    pub guild_id: GuildId,
+}
+
+impl PartialMember {
+    /// Creates a new partial member from a guild ID and user ID.
+    pub const fn new(guild_id: GuildId, user_id: UserId) -> Self {
+        Self {
+            id: user_id,
+            guild_id,
+        }
+    }
+
+    /// Adds context to the member, allowing it to access shared client state.
+    pub const fn with_ctx(self, ctx: Context) -> WithCtx<Self> {
+        ctx.with(self)
+    }
+}
+
+/// Represents a member of an Adapt guild.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Member {
+    /// The underlying partial member.
+    #[serde(flatten)]
+    partial: PartialMember,
+    /// The nickname of the member in the guild, if any.
+    pub nick: Option<String>,
+    /// The IDs of the roles assigned to the member.
+    pub roles: Vec<RoleId>,
+}
+
+impl Member {
+    /// Creates a new member from a raw [`essence::models::Member`].
+    #[must_use]
+    pub fn from_raw(member: essence::models::Member) -> Self {
+        Self {
+            partial: PartialMember::new(member.guild_id.into(), member.user.id().into()),
+            nick: member.nick,
+            roles: member
+                .roles
+                .unwrap_or_default()
+                .into_iter()
+                .map(RoleId::new_unchecked)
+                .collect(),
+        }
+    }
+
+    /// Creates a copyable [`PartialMember`] from this member.
+    pub const fn partial(&self) -> PartialMember {
+        self.partial
+    }
+
+    /// Returns the user ID of the member.
+    #[must_use]
+    pub const fn id(&self) -> UserId {
+        self.partial.id
+    }
+
+    /// Returns the ID of the guild the member belongs to.
+    #[must_use]
+    pub const fn guild_id(&self) -> GuildId {
+        self.partial.guild_id
+    }
+
+    /// Computes the permissions of this member from the given roles of the guild, optionally within
+    /// the given channel.
+    ///
+    /// Roles the member does not have are ignored, except for the default role, which shares its
+    /// ID with the guild and applies to every member. Roles are applied from the lowest to the
+    /// highest position, followed by the overwrites of the channel for those roles and finally the
+    /// overwrite for the member itself. The owner of the guild and members with the
+    /// [`ADMINISTRATOR`](Permissions::ADMINISTRATOR) permission have all permissions.
+    #[must_use]
+    pub fn permissions_with(
+        &self,
+        roles: &[Role],
+        owner_id: UserId,
+        channel: Option<&Channel>,
+    ) -> Permissions {
+        if self.id() == owner_id {
+            return Permissions::all();
+        }
+
+        let mut roles = roles
+            .iter()
+            .filter(|role| role.id.get() == self.guild_id().get() || self.roles.contains(&role.id))
+            .collect::<Vec<_>>();
+        roles.sort_by_key(|role| role.position);
+
+        let apply = |permissions: Permissions, pair: &PermissionPair| {
+            (permissions & !pair.deny) | pair.allow
+        };
+        let mut permissions = roles
+            .iter()
+            .fold(Permissions::empty(), |permissions, role| {
+                apply(permissions, &role.permissions)
+            });
+        if permissions.contains(Permissions::ADMINISTRATOR) {
+            return Permissions::all();
+        }
+
+        if let Some(channel) = channel {
+            for role in &roles {
+                if let Some(overwrite) = channel.overwrites.iter().find(|o| o.id == role.id.get()) {
+                    permissions = apply(permissions, &overwrite.permissions);
+                }
+            }
+            if let Some(overwrite) = channel.overwrites.iter().find(|o| o.id == self.id().get()) {
+                permissions = apply(permissions, &overwrite.permissions);
+            }
+        }
+        permissions
+    }
+
+    /// Returns the highest of the given roles of the guild which is assigned to this member,
+    /// according to [`Role::cmp_position`]. The default role is not considered, so this is `None`
+    /// if the member has no other roles.
+    #[must_use]
+    pub fn highest_role<'a>(&self, roles: &'a [Role]) -> Option<&'a Role> {
+        roles
+            .iter()
+            .filter(|role| self.roles.contains(&role.id))
+            .max_by(|a, b| a.cmp_position(b))
+    }
+
+    /// Whether this member can moderate the given member, for example kick or ban them, according
+    /// to the role hierarchy of the guild given by its roles and owner.
+    ///
+    /// No member can moderate themselves or the owner of the guild, and the owner can moderate
+    /// every other member. Otherwise, the highest role of this member must be higher than the
+    /// highest role of the other member. This does not check whether the member has the
+    /// permissions required for the moderation action.
+    #[must_use]
+    pub fn can_moderate_with(&self, other: &Self, roles: &[Role], owner_id: UserId) -> bool {
+        if self.id() == other.id() || other.id() == owner_id {
+            return false;
+        }
+        if self.id() == owner_id {
+            return true;
+        }
+
+        match (self.highest_role(roles), other.highest_role(roles)) {
+            (Some(role), Some(other)) => role.cmp_position(other) == Ordering::Greater,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    /// Computes the changes made to this member since the given previous state of the member.
+    #[must_use]
+    pub fn diff(&self, old: &Self) -> MemberDiff {
+        let old_roles = old.roles.iter().copied().collect::<HashSet<_>>();
+        let new_roles = self.roles.iter().copied().collect::<HashSet<_>>();
+
+        MemberDiff {
+            roles_added: self
+                .roles
+                .iter()
+                .filter(|role| !old_roles.contains(role))
+                .copied()
+                .collect(),
+            roles_removed: old
+                .roles
+                .iter()
+                .filter(|role| !new_roles.contains(role))
+                .copied()
+                .collect(),
+            nick: (self.nick != old.nick).then(|| (old.nick.clone(), self.nick.clone())),
+        }
+    }
+
+    /// Whether the given roles differ from the roles of this member, ignoring order and
+    /// duplicates.
+    fn roles_differ(&self, role_ids: &[RoleId]) -> bool {
+        let current = self.roles.iter().collect::<HashSet<_>>();
+        current != role_ids.iter().collect()
+    }
+}
+
+impl WithCtx<Member> {
+    /// Creates a copyable [`PartialMember`] from this member.
+    pub fn partial(&self) -> WithCtx<PartialMember> {
+        self.ctx.clone().with(self.inner().partial())
+    }
+
+    /// Computes the permissions of this member in the given channel. This fetches the roles and
+    /// the owner of the guild; see [`Member::permissions_with`] to compute permissions from known
+    /// roles instead.
+    pub async fn permissions_in(&self, channel: &Channel) -> Result<Permissions> {
+        let http = self.ctx.http();
+        let guild_id = self.guild_id().get();
+        let (roles, guild) = tokio::try_join!(
+            http.request(endpoints::GetAllRoles(guild_id)).into_future(),
+            http.request(endpoints::GetGuild(guild_id)).into_future(),
+        )?;
+        let roles = roles.into_iter().map(Role::from_raw).collect::<Vec<_>>();
+
+        Ok(self.permissions_with(&roles, guild.partial.owner_id.into(), Some(channel)))
+    }
+
+    /// Whether this member can moderate the given member according to the role hierarchy. This
+    /// fetches the roles and the owner of the guild; see [`Member::can_moderate_with`] for the rules
+    /// and to use known roles instead.
+    pub async fn can_moderate(&self, other: &Member) -> Result<bool> {
+        let http = self.ctx.http();
+        let guild_id = self.guild_id().get();
+        let (roles, guild) = tokio::try_join!(
+            http.request(endpoints::GetAllRoles(guild_id)).into_future(),
+            http.request(endpoints::GetGuild(guild_id)).into_future(),
+        )?;
+        let roles = roles.into_iter().map(Role::from_raw).collect::<Vec<_>>();
+
+        Ok(self.can_moderate_with(other, &roles, guild.partial.owner_id.into()))
+    }
+
+    /// Whether this member has all of the given permissions in the given channel. See
+    /// [`Self::permissions_in`].
+    pub async fn has_permission(
+        &self,
+        channel: &Channel,
+        permissions: Permissions,
+    ) -> Result<bool> {
+        Ok(self.permissions_in(channel).await?.contains(permissions))
+    }
+
+    /// Sends a direct message to the user this member represents, opening a DM channel with them
+    /// if necessary. See [`WithCtx<UserId>::direct_message`].
+    pub async fn direct_message(
+        &self,
+        payload: impl IntoCreateMessage + Send,
+    ) -> Result<WithCtx<Message>> {
+        self.ctx
+            .clone()
+            .with(self.id())
+            .direct_message(payload)
+            .await
+    }
+
+    /// Replaces the roles of the member with the given roles, returning the updated member. If the
+    /// member already has exactly these roles as currently known, no request is made.
+    pub async fn set_roles(
+        &self,
+        role_ids: impl IntoIterator<Item = RoleId> + Send,
+    ) -> Result<WithCtx<Member>> {
+        let role_ids = role_ids.into_iter().collect::<Vec<_>>();
+        if !self.roles_differ(&role_ids) {
+            return Ok(self.clone());
+        }
+        self.edit(|m| m.roles(role_ids)).await
+    }
+
+    /// Edits the member with the changes configured by the given function, returning the updated
+    /// member.
+    ///
+    /// Role deltas are applied to the roles of this member as currently known.
+    pub async fn edit(
+        &self,
+        f: impl FnOnce(EditMemberBuilder) -> EditMemberBuilder + Send,
+    ) -> Result<WithCtx<Member>> {
+        let payload = f(EditMemberBuilder::new()).build(&self.roles)?;
+        let member = self
+            .ctx
+            .http()
+            .request(endpoints::EditMember(self.guild_id.get(), self.id.get()))
+            .body(payload)
+            .await?;
+
+        Ok(self.ctx.clone().with(Member::from_raw(member)))
+    }
+}
+
+impl Deref for Member {
+    type Target = PartialMember;
+
+    fn deref(&self) -> &Self::Target {
+        &self.partial
+    }
+}
+
+impl PartialEq for Member {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial == other.partial
+    }
+}
+
+impl Eq for Member {}
+
+impl Hash for Member {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.partial.hash(state);
+    }
+}
+
+/// The changes made to a [`Member`] between two states, computed with [`Member::diff`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct MemberDiff {
+    /// The roles that were added to the member.
+    pub roles_added: Vec<RoleId>,
+    /// The roles that were removed from the member.
+    pub roles_removed: Vec<RoleId>,
+    /// The old and new nickname of the member, if it changed.
+    pub nick: Option<(Option<String>, Option<String>)>,
+}
+
+impl MemberDiff {
+    /// Returns whether anything tracked by the diff changed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.roles_added.is_empty() && self.roles_removed.is_empty() && self.nick.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ChannelId, PermissionOverwrite};
+    use essence::models::ChannelType;
+
+    fn member(nick: Option<&str>, roles: &[u64]) -> Member {
+        Member {
+            partial: PartialMember::new(GuildId::from(1), UserId::from(2)),
+            nick: nick.map(ToString::to_string),
+            roles: roles.iter().copied().map(RoleId::from).collect(),
+        }
+    }
+
+    #[test]
+    fn diff_roles_and_nick() {
+        let old = member(None, &[1, 2]);
+        let new = member(Some("nick"), &[2, 3]);
+        let diff = new.diff(&old);
+
+        assert_eq!(diff.roles_added, vec![RoleId::from(3)]);
+        assert_eq!(diff.roles_removed, vec![RoleId::from(1)]);
+        assert_eq!(diff.nick, Some((None, Some("nick".to_string()))));
+        assert!(new.diff(&new).is_empty());
+
+        assert!(!new.roles_differ(&[3.into(), 2.into(), 3.into()]));
+        assert!(new.roles_differ(&[2.into()]));
+    }
+
+    fn role(id: u64, position: u16, allow: Permissions, deny: Permissions) -> Role {
+        Role {
+            id: RoleId::from(id),
+            guild_id: GuildId::from(1),
+            name: String::new(),
+            color: None,
+            permissions: PermissionPair { allow, deny },
+            position,
+        }
+    }
+
+    #[test]
+    fn permissions_with_overwrites() {
+        let send = Permissions::SEND_MESSAGES;
+        let view = Permissions::VIEW_CHANNEL;
+        let roles = [
+            // The default role
+            role(1, 0, view | send, Permissions::empty()),
+            role(3, 1, Permissions::MANAGE_MESSAGES, Permissions::empty()),
+            role(4, 2, Permissions::ADMINISTRATOR, Permissions::empty()),
+        ];
+        let member = member(None, &[3]);
+
+        let base = member.permissions_with(&roles, UserId::from(9), None);
+        assert_eq!(base, view | send | Permissions::MANAGE_MESSAGES);
+        assert_eq!(
+            member.permissions_with(&roles, UserId::from(2), None),
+            Permissions::all()
+        );
+
+        let channel = Channel {
+            id: ChannelId::from(5),
+            guild_id: Some(GuildId::from(1)),
+            kind: ChannelType::Text,
+            name: None,
+            topic: None,
+            position: Some(0),
+            parent_id: None,
+            overwrites: vec![
+                PermissionOverwrite {
+                    id: 1,
+                    permissions: PermissionPair {
+                        allow: Permissions::empty(),
+                        deny: send,
+                    },
+                },
+                PermissionOverwrite {
+                    id: 2,
+                    permissions: PermissionPair {
+                        allow: send,
+                        deny: view,
+                    },
+                },
+            ],
+        };
+        let permissions = member.permissions_with(&roles, UserId::from(9), Some(&channel));
+        assert_eq!(permissions, send | Permissions::MANAGE_MESSAGES);
+    }
+
+    #[test]
+    fn moderation_hierarchy() {
+        let empty = Permissions::empty();
+        let roles = [
+            role(1, 0, empty, empty),
+            role(3, 1, empty, empty),
+            role(4, 1, empty, empty),
+        ];
+        let with_roles = |id: u64, roles: &[u64]| Member {
+            partial: PartialMember::new(GuildId::from(1), UserId::from(id)),
+            ..member(None, roles)
+        };
+        let owner = UserId::from(9);
+
+        // Equal positions are broken by age: role 3 is older than role 4
+        assert_eq!(roles[1].cmp_position(&roles[2]), Ordering::Greater);
+        assert_eq!(
+            with_roles(2, &[3, 4]).highest_role(&roles).unwrap().id,
+            RoleId::from(3)
+        );
+
+        let moderator = with_roles(2, &[3]);
+        let target = with_roles(5, &[4]);
+        assert!(moderator.can_moderate_with(&target, &roles, owner));
+        assert!(!target.can_moderate_with(&moderator, &roles, owner));
+        assert!(!moderator.can_moderate_with(&moderator, &roles, owner));
+        assert!(!moderator.can_moderate_with(&with_roles(9, &[]), &roles, owner));
+        assert!(with_roles(9, &[]).can_moderate_with(&moderator, &roles, owner));
+        assert!(target.can_moderate_with(&with_roles(6, &[]), &roles, owner));
+    }
+}
@@ -1,6 +1,8 @@
-use crate::http::endpoints;
-use crate::models::channel::ChannelId;
+use crate::builder::{validate_message, EditMessageBuilder, ValidationError};
+use crate::http::{convey::FileSource, endpoints};
+use crate::models::{channel::ChannelId, Attachment, Embed, Id, ReactionType, UserId};
 use crate::{Context, Result, WithCtx};
+use serde::Serialize;
 
 use essence::http::message::CreateMessagePayload;
 use std::ops::Deref;
@@ -15,9 +17,31 @@ crate::id_type! {
 }
 
 /// Represents anything that can be converted into a [`CreateMessagePayload`].
+///
+/// Payloads are validated with [`validate_message`] before they are sent, unless
+/// [`Self::skips_validation`] returns `true`.
 pub trait IntoCreateMessage {
     /// Converts the implementor into a message payload.
     fn into_create_message(self) -> CreateMessagePayload;
+
+    /// Whether client-side validation of the payload should be skipped. Defaults to `false`.
+    fn skips_validation(&self) -> bool {
+        false
+    }
+}
+
+/// Converts the given payload into a [`CreateMessagePayload`], validating it along with the sizes
+/// of the files attached to it unless the payload skips validation.
+pub(crate) fn prepare_message(
+    payload: impl IntoCreateMessage,
+    attachment_sizes: &[u64],
+) -> std::result::Result<CreateMessagePayload, ValidationError> {
+    let skip = payload.skips_validation();
+    let payload = payload.into_create_message();
+    if !skip {
+        validate_message(&payload, attachment_sizes)?;
+    }
+    Ok(payload)
 }
 
 impl IntoCreateMessage for CreateMessagePayload {
@@ -45,7 +69,7 @@ impl IntoCreateMessage for &str {
 }
 
 /// Represents an Adapt message by its ID, aware of its parent channel ID.
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize)]
 #[must_use = "this struct does nothing on its own"]
 pub struct PartialMessage {
     /// The ID of the message.
@@ -77,15 +101,53 @@ impl WithCtx<PartialMessage> {
             .request(endpoints::DeleteMessage(*self.channel_id, *self.id))
             .await
     }
+
+    /// Reacts to the message with the given emoji.
+    pub async fn react(&self, reaction: impl Into<ReactionType> + Send) -> Result<()> {
+        let emoji = reaction.into().to_path_segment();
+        self.ctx
+            .http()
+            .request(endpoints::AddReaction(*self.channel_id, *self.id, &emoji))
+            .await
+    }
+
+    /// Removes the reaction of the client user with the given emoji from the message.
+    pub async fn unreact(&self, reaction: impl Into<ReactionType> + Send) -> Result<()> {
+        let emoji = reaction.into().to_path_segment();
+        self.ctx
+            .http()
+            .request(endpoints::RemoveOwnReaction(
+                *self.channel_id,
+                *self.id,
+                &emoji,
+            ))
+            .await
+    }
 }
 
 /// Represents an Adapt message.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct Message {
     /// The underlying partial message.
+    #[serde(flatten)]
     partial: PartialMessage,
+    /// The ID of the author of the message, if any. This is `None` for system messages.
+    pub author_id: Option<UserId>,
     /// The text content of the message. This is an empty string if the message has no content.
     pub content: String,
+    /// The embeds of the message.
+    pub embeds: Vec<Embed>,
+    /// The files attached to the message.
+    pub attachments: Vec<Attachment>,
+    /// The IDs of the users mentioned in the message.
+    pub mentions: Vec<UserId>,
+    /// The nonce the message was sent with. This is only known for messages received through the
+    /// gateway which were sent with a nonce, and for messages returned by
+    /// [`WithCtx<ChannelId>::send_and_confirm`](crate::WithCtx::send_and_confirm).
+    pub nonce: Option<String>,
+    /// The raw flags of the message, kept for [`Self::into_raw`].
+    #[serde(skip)]
+    flags: u32,
 }
 
 impl Message {
@@ -94,7 +156,42 @@ impl Message {
     pub fn from_raw(message: essence::models::Message) -> Self {
         Self {
             partial: PartialMessage::new(message.channel_id.into(), message.id.into()),
+            author_id: message.author_id.map(Into::into),
             content: message.content.unwrap_or_default(),
+            embeds: message.embeds,
+            attachments: message
+                .attachments
+                .into_iter()
+                .map(Attachment::from_raw)
+                .collect(),
+            mentions: message.mentions.into_iter().map(Into::into).collect(),
+            nonce: None,
+            flags: message.flags,
+        }
+    }
+
+    /// Converts this message back into a raw [`essence::models::Message`], carrying over changes
+    /// made to the fields of this model.
+    ///
+    /// The author and edit timestamp of the raw message are not kept by this model, so they are
+    /// left empty. The author can be looked up by [`Self::author_id`] instead.
+    #[must_use]
+    pub fn into_raw(self) -> essence::models::Message {
+        essence::models::Message {
+            id: self.partial.id.get(),
+            channel_id: self.partial.channel_id.get(),
+            author_id: self.author_id.map(Into::into),
+            author: None,
+            content: (!self.content.is_empty()).then_some(self.content),
+            embeds: self.embeds,
+            attachments: self
+                .attachments
+                .into_iter()
+                .map(Attachment::into_raw)
+                .collect(),
+            flags: self.flags,
+            mentions: self.mentions.into_iter().map(Into::into).collect(),
+            edited_at: None,
         }
     }
 
@@ -116,6 +213,12 @@ impl Message {
     }
 }
 
+impl From<Message> for essence::models::Message {
+    fn from(message: Message) -> Self {
+        message.into_raw()
+    }
+}
+
 impl WithCtx<Message> {
     /// Creates a copyable [`PartialMessage`] from this message.
     pub fn partial(&self) -> WithCtx<PartialMessage> {
@@ -131,6 +234,45 @@ impl WithCtx<Message> {
     pub fn channel_id(&self) -> WithCtx<ChannelId> {
         self.ctx.clone().with(self.inner().channel_id())
     }
+
+    /// Returns the files attached to this message.
+    pub fn attachments(&self) -> impl Iterator<Item = WithCtx<Attachment>> + '_ {
+        self.attachments
+            .iter()
+            .map(|attachment| self.ctx.clone().with(attachment.clone()))
+    }
+
+    /// Edits the message with the changes configured by the given function, returning the updated
+    /// message. Fields that are not changed by the edit are left as they are.
+    ///
+    /// Added embeds are applied to the embeds of this message as currently known.
+    pub async fn edit(
+        &self,
+        f: impl FnOnce(EditMessageBuilder) -> EditMessageBuilder + Send,
+    ) -> Result<WithCtx<Message>> {
+        let payload = f(EditMessageBuilder::new()).build(self)?;
+        let message = self
+            .ctx
+            .http()
+            .request(endpoints::EditMessage(
+                *self.inner().channel_id(),
+                *self.inner().id(),
+            ))
+            .body(payload)
+            .await?;
+
+        Ok(self.ctx.clone().with(Message::from_raw(message)))
+    }
+
+    /// Sends a message with the given files attached to the channel this message belongs to. See
+    /// [`WithCtx<ChannelId>::send_files`] for more information.
+    pub async fn reply_with_files(
+        &self,
+        files: impl IntoIterator<Item = FileSource> + Send,
+        payload: impl IntoCreateMessage + Send,
+    ) -> Result<WithCtx<Message>> {
+        self.channel_id().send_files(files, payload).await
+    }
 }
 
 impl Deref for Message {
@@ -142,3 +284,32 @@ impl Deref for Message {
 }
 
 crate::impl_common_traits!(Message);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_round_trip() {
+        let raw = essence::models::Message {
+            id: 1,
+            channel_id: 2,
+            author_id: Some(3),
+            author: None,
+            content: Some("Hello".to_string()),
+            embeds: vec![Embed::default()],
+            attachments: Vec::new(),
+            flags: 4,
+            mentions: vec![3],
+            edited_at: None,
+        };
+
+        let mut message = Message::from_raw(raw.clone());
+        assert_eq!(message.clone().into_raw(), raw);
+
+        message.content.clear();
+        let edited = message.into_raw();
+        assert_eq!(edited.content, None);
+        assert_eq!(edited.mentions, raw.mentions);
+    }
+}
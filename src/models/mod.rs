@@ -1,10 +1,32 @@
+mod attachment;
 mod channel;
+mod guild;
+mod history;
+mod invite;
+mod member;
 mod message;
-
-pub use channel::ChannelId;
+mod reaction;
+mod relationship;
+mod role;
+mod user;
+
+pub use attachment::{Attachment, AttachmentId};
+pub use channel::{Channel, ChannelId, DmChannel, TypingGuard};
+pub use essence::models::{
+    Embed, GuildMemberCount, PermissionOverwrite, PermissionPair, Permissions,
+};
+pub use guild::{Guild, GuildId};
+pub use history::MessageHistory;
 pub use id::Id;
-pub use message::{Message, MessageId, PartialMessage};
+pub use invite::{Invite, InviteCode};
+pub use member::{Member, MemberDiff, PartialMember};
+pub(crate) use message::prepare_message;
+pub use message::{IntoCreateMessage, Message, MessageId, PartialMessage};
+pub use reaction::ReactionType;
+pub use relationship::{Relationship, RelationshipType, Relationships};
+pub use role::{Role, RoleId};
 pub use timestamp::Timestamp;
+pub use user::{User, UserId};
 
 #[macro_use]
 pub(crate) mod id {
@@ -61,7 +83,8 @@ pub(crate) mod id {
             $vis:vis struct $name:ident $(: $model_type:ident)?;
         ) => {
             $(#[$meta])*
-            #[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, Hash)]
+            #[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, Hash, ::serde::Serialize, ::serde::Deserialize)]
+            #[serde(transparent)]
             $vis struct $name(u64);
 
             impl $crate::models::Id for $name {
@@ -161,6 +184,24 @@ pub mod timestamp {
         }
     }
 
+    /// Returns the number of milliseconds since the Unix epoch of the given timestamp, or `0` if
+    /// it is before the epoch.
+    #[must_use]
+    pub fn to_millis(timestamp: &Timestamp) -> u64 {
+        #[cfg(feature = "chrono")]
+        {
+            u64::try_from(timestamp.timestamp_millis()).unwrap_or(0)
+        }
+        #[cfg(not(feature = "chrono"))]
+        {
+            timestamp
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |elapsed| {
+                    u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)
+                })
+        }
+    }
+
     /// The error type for parsing an ISO8601 timestamp.
     ///
     /// This is [`chrono::ParseError`] if the `chrono` feature is enabled, otherwise it is
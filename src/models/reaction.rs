@@ -0,0 +1,160 @@
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Serialize, Serializer};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// The characters which are escaped in a path segment: everything but unreserved characters.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// The emoji of a reaction, which is either a Unicode emoji or a custom emoji of a guild.
+///
+/// # Note
+/// The Adapt API does not yet expose endpoints for listing the custom emojis of a guild, so custom
+/// emojis cannot be looked up by name and must be referenced by their ID, for example with
+/// [`Self::custom`] or by parsing `<:name:id>`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ReactionType {
+    /// A Unicode emoji, such as `👍`.
+    Unicode(String),
+    /// A custom emoji of a guild.
+    Custom {
+        /// The ID of the emoji.
+        id: u64,
+        /// The name of the emoji.
+        name: String,
+        /// Whether the emoji is animated.
+        animated: bool,
+    },
+}
+
+impl ReactionType {
+    /// Creates a reaction type for a custom emoji.
+    #[must_use]
+    pub fn custom(id: u64, name: impl Into<String>) -> Self {
+        Self::Custom {
+            id,
+            name: name.into(),
+            animated: false,
+        }
+    }
+
+    /// Returns the reaction in the encoding expected by the API, percent-encoded for use in the
+    /// path of a request.
+    pub(crate) fn to_path_segment(&self) -> String {
+        utf8_percent_encode(&self.to_string(), PATH_SEGMENT).to_string()
+    }
+}
+
+/// Formats the reaction in the encoding expected by the API: the emoji itself for Unicode emojis,
+/// and `name:id` for custom emojis.
+impl Display for ReactionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unicode(emoji) => f.write_str(emoji),
+            Self::Custom { id, name, .. } => write!(f, "{name}:{id}"),
+        }
+    }
+}
+
+/// Parses a reaction from the encoding produced by [`Display`]. Custom emojis may also be given
+/// as `<:name:id>` or `<a:name:id>`, the latter marking the emoji as animated. Anything else is
+/// treated as a Unicode emoji.
+impl FromStr for ReactionType {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (inner, animated) = match s.strip_prefix('<').and_then(|s| s.strip_suffix('>')) {
+            Some(inner) => match inner.strip_prefix("a:") {
+                Some(inner) => (inner, true),
+                None => (inner.strip_prefix(':').unwrap_or(inner), false),
+            },
+            None => (s, false),
+        };
+
+        if let Some((name, id)) = inner.rsplit_once(':') {
+            if let Ok(id) = id.parse() {
+                return Ok(Self::Custom {
+                    id,
+                    name: name.to_string(),
+                    animated,
+                });
+            }
+        }
+        Ok(Self::Unicode(s.to_string()))
+    }
+}
+
+impl From<char> for ReactionType {
+    fn from(emoji: char) -> Self {
+        Self::Unicode(emoji.to_string())
+    }
+}
+
+impl From<&str> for ReactionType {
+    fn from(emoji: &str) -> Self {
+        let Ok(reaction) = emoji.parse();
+        reaction
+    }
+}
+
+/// Serializes the reaction as a string in the encoding produced by [`Display`].
+impl Serialize for ReactionType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl From<essence::models::PartialEmoji> for ReactionType {
+    fn from(emoji: essence::models::PartialEmoji) -> Self {
+        match emoji.id {
+            Some(id) => Self::custom(id, emoji.name),
+            None => Self::Unicode(emoji.name),
+        }
+    }
+}
+
+impl From<essence::models::CustomEmoji> for ReactionType {
+    fn from(emoji: essence::models::CustomEmoji) -> Self {
+        Self::custom(emoji.id, emoji.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        assert_eq!(
+            ReactionType::from("👍"),
+            ReactionType::Unicode("👍".to_string())
+        );
+        assert_eq!(
+            ReactionType::from("blob:123"),
+            ReactionType::custom(123, "blob")
+        );
+        assert_eq!(
+            ReactionType::from("<a:blob:123>"),
+            ReactionType::Custom {
+                id: 123,
+                name: "blob".to_string(),
+                animated: true,
+            }
+        );
+        assert_eq!(ReactionType::custom(123, "blob").to_string(), "blob:123");
+        assert_eq!(ReactionType::from('👍').to_string(), "👍");
+    }
+
+    #[test]
+    fn path_segment() {
+        assert_eq!(ReactionType::from('👍').to_path_segment(), "%F0%9F%91%8D");
+        assert_eq!(
+            ReactionType::custom(123, "blob_2").to_path_segment(),
+            "blob_2%3A123"
+        );
+    }
+}
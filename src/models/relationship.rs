@@ -0,0 +1,109 @@
+use crate::models::{User, UserId};
+use crate::WithCtx;
+use serde::Serialize;
+use std::hash::{Hash, Hasher};
+
+pub use essence::models::RelationshipType;
+
+/// Represents a relationship between the client user and another user, such as a friendship, a
+/// pending friend request, or a block.
+#[derive(Clone, Debug, Serialize)]
+pub struct Relationship {
+    /// The other user in the relationship.
+    pub user: User,
+    /// The type of the relationship.
+    pub kind: RelationshipType,
+}
+
+impl Relationship {
+    /// Creates a new relationship from a raw [`essence::models::Relationship`].
+    #[must_use]
+    pub fn from_raw(relationship: essence::models::Relationship) -> Self {
+        Self {
+            user: User::from_raw(relationship.user),
+            kind: relationship.kind,
+        }
+    }
+
+    /// Returns the ID of the other user in the relationship.
+    #[must_use]
+    pub const fn user_id(&self) -> UserId {
+        self.user.id
+    }
+}
+
+impl WithCtx<Relationship> {
+    /// Returns the ID of the other user in the relationship.
+    pub fn user_id(&self) -> WithCtx<UserId> {
+        self.ctx.clone().with(self.inner().user_id())
+    }
+}
+
+impl PartialEq for Relationship {
+    fn eq(&self, other: &Self) -> bool {
+        self.user.id == other.user.id
+    }
+}
+
+impl Eq for Relationship {}
+
+impl Hash for Relationship {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.user.id.hash(state);
+    }
+}
+
+/// The relationships of the client user, split by type. Obtained with
+/// [`Context::relationships`][crate::Context::relationships].
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Relationships {
+    friends: Vec<WithCtx<Relationship>>,
+    incoming: Vec<WithCtx<Relationship>>,
+    outgoing: Vec<WithCtx<Relationship>>,
+    blocked: Vec<WithCtx<Relationship>>,
+}
+
+impl Relationships {
+    /// Splits the given relationships by type.
+    pub fn new(relationships: impl IntoIterator<Item = WithCtx<Relationship>>) -> Self {
+        let mut slf = Self::default();
+        for relationship in relationships {
+            match relationship.kind {
+                RelationshipType::Friend => slf.friends.push(relationship),
+                RelationshipType::Incoming => slf.incoming.push(relationship),
+                RelationshipType::Outgoing => slf.outgoing.push(relationship),
+                RelationshipType::Blocked => slf.blocked.push(relationship),
+            }
+        }
+        slf
+    }
+
+    /// Returns the friends of the client user.
+    pub fn friends(&self) -> &[WithCtx<Relationship>] {
+        &self.friends
+    }
+
+    /// Returns the pending friend requests sent to the client user.
+    pub fn incoming(&self) -> &[WithCtx<Relationship>] {
+        &self.incoming
+    }
+
+    /// Returns the pending friend requests sent by the client user.
+    pub fn outgoing(&self) -> &[WithCtx<Relationship>] {
+        &self.outgoing
+    }
+
+    /// Returns the users blocked by the client user.
+    pub fn blocked(&self) -> &[WithCtx<Relationship>] {
+        &self.blocked
+    }
+
+    /// Returns an iterator over all relationships, regardless of type.
+    pub fn iter(&self) -> impl Iterator<Item = &WithCtx<Relationship>> {
+        self.friends
+            .iter()
+            .chain(&self.incoming)
+            .chain(&self.outgoing)
+            .chain(&self.blocked)
+    }
+}
@@ -0,0 +1,116 @@
+use crate::http::endpoints;
+use crate::models::{GuildId, Id, Member, PermissionPair, UserId};
+use crate::{Context, Result, WithCtx};
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+crate::id_type! {
+    /// Represents an Adapt role by its ID.
+    pub struct RoleId: Role;
+}
+
+impl RoleId {
+    /// Attaches a [`Context`] to this role ID to allow it to access shared client state.
+    pub const fn with_ctx(self, ctx: Context) -> WithCtx<Self> {
+        ctx.with(self)
+    }
+}
+
+impl WithCtx<RoleId> {
+    /// The maximum number of members [`Self::assign_to_many`] edits concurrently.
+    pub const ASSIGN_CONCURRENCY: usize = 4;
+
+    /// Assigns this role to each of the given members of the given guild, editing up to
+    /// [`Self::ASSIGN_CONCURRENCY`] members at a time. Members who already have the role are
+    /// left untouched.
+    ///
+    /// Since the roles of a member can only be replaced as a whole, each member is fetched first.
+    /// Returns the result for each member in the given order, so that one failure does not stop
+    /// the role from being assigned to the remaining members.
+    pub async fn assign_to_many(
+        &self,
+        guild_id: GuildId,
+        member_ids: impl IntoIterator<Item = UserId> + Send,
+    ) -> Vec<(UserId, Result<()>)> {
+        let role_id = *self.inner();
+        let assign = |user_id: UserId| async move {
+            let http = self.ctx.http();
+            let member = http
+                .request(endpoints::GetMember(guild_id.get(), user_id.get()))
+                .await?;
+            let member = self.ctx.clone().with(Member::from_raw(member));
+            if !member.roles.contains(&role_id) {
+                let _ = member.edit(|m| m.add_role(role_id)).await?;
+            }
+            Ok(())
+        };
+
+        let member_ids = member_ids.into_iter().collect::<Vec<_>>();
+        stream::iter(member_ids)
+            .map(|user_id| async move { (user_id, assign(user_id).await) })
+            .buffered(Self::ASSIGN_CONCURRENCY)
+            .collect()
+            .await
+    }
+}
+
+/// Represents a role in an Adapt guild.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Role {
+    /// The ID of the role.
+    pub id: RoleId,
+    /// The ID of the guild the role belongs to.
+    pub guild_id: GuildId,
+    /// The name of the role.
+    pub name: String,
+    /// The color of the role as an RGB integer, if any.
+    pub color: Option<u32>,
+    /// The permissions allowed and denied by the role.
+    pub permissions: PermissionPair,
+    /// The position of the role in the role hierarchy of the guild. Higher positions take
+    /// precedence.
+    pub position: u16,
+}
+
+impl Role {
+    /// Creates a new role from a raw [`essence::models::Role`].
+    #[must_use]
+    pub fn from_raw(role: essence::models::Role) -> Self {
+        Self {
+            id: role.id.into(),
+            guild_id: role.guild_id.into(),
+            name: role.name,
+            color: role.color,
+            permissions: role.permissions,
+            position: role.position,
+        }
+    }
+
+    /// Compares the positions of this role and the given role in the role hierarchy. Roles with a
+    /// higher position compare as greater. If the positions are equal, the older role (the role
+    /// with the lower ID) compares as greater.
+    #[must_use]
+    pub fn cmp_position(&self, other: &Self) -> Ordering {
+        self.position
+            .cmp(&other.position)
+            .then_with(|| other.id.get().cmp(&self.id.get()))
+    }
+}
+
+impl WithCtx<Role> {
+    /// Returns the ID of the role.
+    pub fn id(&self) -> WithCtx<RoleId> {
+        self.ctx.clone().with(self.inner().id)
+    }
+
+    /// Assigns this role to each of the given members. See [`WithCtx<RoleId>::assign_to_many`].
+    pub async fn assign_to_many(
+        &self,
+        member_ids: impl IntoIterator<Item = UserId> + Send,
+    ) -> Vec<(UserId, Result<()>)> {
+        self.id().assign_to_many(self.guild_id, member_ids).await
+    }
+}
+
+crate::impl_common_traits!(Role);
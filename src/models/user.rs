@@ -0,0 +1,168 @@
+use crate::http::endpoints;
+use crate::models::message::IntoCreateMessage;
+use crate::models::{ChannelId, DmChannel, Id, Message, Relationship};
+use crate::{Context, Result, WithCtx};
+use essence::http::{channel::CreateDmChannelPayload, user::SendFriendRequestPayload};
+use serde::{Deserialize, Serialize};
+
+crate::id_type! {
+    /// Represents an Adapt user by its ID.
+    pub struct UserId: User;
+}
+
+impl UserId {
+    /// Attaches a [`Context`] to this user ID to allow it to access shared client state.
+    pub const fn with_ctx(self, ctx: Context) -> WithCtx<Self> {
+        ctx.with(self)
+    }
+}
+
+impl WithCtx<UserId> {
+    /// Fetches the user with this ID.
+    pub async fn fetch(&self) -> Result<WithCtx<User>> {
+        let user = self
+            .ctx
+            .http()
+            .request(endpoints::GetUser(self.get()))
+            .await?;
+
+        Ok(self.ctx.clone().with(User::from_raw(user)))
+    }
+
+    /// Returns the DM channel with this user, opening it if necessary. The opened channel is
+    /// stored in the [cache](crate::Context::cache), so subsequent calls do not make any
+    /// requests. No request is made either if the channel was received over the gateway.
+    pub async fn dm_channel(&self) -> Result<WithCtx<ChannelId>> {
+        if let Some(channel) = self.ctx.cache().dm_channel_with(*self.inner()).await {
+            return Ok(self.ctx.clone().with(channel.id));
+        }
+
+        let channel = self
+            .ctx
+            .http()
+            .request(endpoints::CreateDmChannel)
+            .body(CreateDmChannelPayload::Dm {
+                recipient_id: self.get(),
+            })
+            .await?;
+        let channel = DmChannel::from_raw(channel);
+        let channel_id = channel.id;
+
+        self.ctx.cache().insert_dm_channel(channel).await;
+        Ok(self.ctx.clone().with(channel_id))
+    }
+
+    /// Sends a direct message to this user, opening a DM channel with them if necessary.
+    pub async fn direct_message(
+        &self,
+        payload: impl IntoCreateMessage + Send,
+    ) -> Result<WithCtx<Message>> {
+        self.dm_channel().await?.send(payload).await
+    }
+
+    /// Sends a friend request to this user.
+    ///
+    /// Friend requests are sent by username, so this fetches the user first.
+    pub async fn send_friend_request(&self) -> Result<WithCtx<Relationship>> {
+        let user = self.fetch().await?;
+        let relationship = self
+            .ctx
+            .http()
+            .request(endpoints::SendFriendRequest)
+            .body(SendFriendRequestPayload {
+                username: user.into_inner().username,
+            })
+            .await?;
+
+        Ok(self.ctx.clone().with(Relationship::from_raw(relationship)))
+    }
+
+    /// Accepts an incoming friend request from this user.
+    pub async fn accept_friend_request(&self) -> Result<WithCtx<Relationship>> {
+        let relationship = self
+            .ctx
+            .http()
+            .request(endpoints::AcceptFriendRequest(self.get()))
+            .await?;
+
+        Ok(self.ctx.clone().with(Relationship::from_raw(relationship)))
+    }
+
+    /// Blocks this user.
+    pub async fn block(&self) -> Result<WithCtx<Relationship>> {
+        let relationship = self
+            .ctx
+            .http()
+            .request(endpoints::BlockUser(self.get()))
+            .await?;
+
+        Ok(self.ctx.clone().with(Relationship::from_raw(relationship)))
+    }
+
+    /// Deletes the relationship with this user, whatever it is: unfriends, unblocks, or
+    /// cancels/declines a pending friend request.
+    pub async fn delete_relationship(&self) -> Result<()> {
+        self.ctx
+            .http()
+            .request(endpoints::DeleteRelationship(self.get()))
+            .await
+    }
+
+    /// Unblocks this user. This is an alias for [`Self::delete_relationship`].
+    pub async fn unblock(&self) -> Result<()> {
+        self.delete_relationship().await
+    }
+
+    /// Removes this user as a friend. This is an alias for [`Self::delete_relationship`].
+    pub async fn remove_friend(&self) -> Result<()> {
+        self.delete_relationship().await
+    }
+}
+
+/// Represents an Adapt user.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct User {
+    /// The ID of the user.
+    pub id: UserId,
+    /// The username of the user.
+    pub username: String,
+    /// The display name of the user, if any. This is shown instead of the username when set.
+    pub display_name: Option<String>,
+    /// The path to the avatar of the user on convey, if any.
+    pub avatar: Option<String>,
+    /// The path to the banner of the user on convey, if any.
+    pub banner: Option<String>,
+    /// The biography of the user, if any.
+    pub bio: Option<String>,
+}
+
+impl User {
+    /// Creates a new user from a raw [`essence::models::User`].
+    #[must_use]
+    pub fn from_raw(user: essence::models::User) -> Self {
+        Self {
+            id: user.id.into(),
+            username: user.username,
+            display_name: user.display_name,
+            avatar: user.avatar,
+            banner: user.banner,
+            bio: user.bio,
+        }
+    }
+
+    /// Returns the name of the user as it should be displayed: the display name if it is set,
+    /// otherwise the username.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.username)
+    }
+}
+
+impl WithCtx<User> {
+    /// Returns the ID of the user.
+    pub fn id(&self) -> WithCtx<UserId> {
+        self.ctx.clone().with(self.inner().id)
+    }
+}
+
+crate::impl_common_traits!(User);
@@ -10,6 +10,8 @@ pub struct Server<'a> {
     pub harmony: &'a str,
     /// The base URL for convey, Adapt's CDN.
     pub convey: &'a str,
+    /// The base URL for the web app, used to build links such as invite URLs.
+    pub app: &'a str,
 }
 
 impl Default for Server<'static> {
@@ -25,6 +27,7 @@ impl Server<'static> {
             api: "https://api.adapt.chat",
             harmony: "wss://harmony.adapt.chat",
             convey: "https://convey.adapt.chat",
+            app: "https://adapt.chat",
         }
     }
 
@@ -34,6 +37,7 @@ impl Server<'static> {
             api: "http://localhost:8077",
             harmony: "ws://localhost:8076",
             convey: "http://localhost:8078",
+            app: "http://localhost:3000",
         }
     }
 }
@@ -0,0 +1,275 @@
+//! Utilities for unit testing bot logic without connecting to Adapt.
+//!
+//! A [`MockApi`] serves the REST API from a local socket, answering requests with preconfigured
+//! responses and recording every request it receives. The [`Context`] it creates can be used to
+//! drive event handlers directly with synthetic events, after which the resulting HTTP calls can
+//! be asserted on.
+//!
+//! # Example
+//! ```no_run
+//! use adapt::http::Method;
+//! use adapt::testing::MockApi;
+//! use adapt::ws::{EventHandler, InboundMessage};
+//!
+//! # async fn run(handler: impl EventHandler, message: adapt::essence::models::Message) {
+//! let api = MockApi::start().await.unwrap();
+//! api.respond(Method::POST, "/channels/1/messages", 200, &message);
+//!
+//! let events = api
+//!     .resolve(InboundMessage::MessageCreate { message, nonce: None })
+//!     .await;
+//! for event in events {
+//!     api.inject(&handler, event).await;
+//! }
+//!
+//! let requests = api.requests();
+//! assert_eq!(requests[0].path, "/channels/1/messages");
+//! # }
+//! ```
+
+use crate::http::{Http, Method};
+#[cfg(feature = "ws")]
+use crate::ws::{Event, EventConsumer, InboundMessage};
+use crate::Context;
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// A request received by a [`MockApi`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedRequest {
+    /// The method of the request.
+    pub method: Method,
+    /// The path of the request, excluding the query string.
+    pub path: String,
+    /// The query string of the request, if any.
+    pub query: Option<String>,
+    /// The JSON body of the request, if any.
+    pub body: Option<serde_json::Value>,
+}
+
+#[derive(Clone, Debug)]
+struct Route {
+    method: Method,
+    path: String,
+    status: u16,
+    body: String,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    routes: Vec<Route>,
+    requests: Vec<RecordedRequest>,
+}
+
+/// A mocked REST API served from a local socket.
+///
+/// Requests that do not match any configured route are answered with `200 OK` and a `null` body,
+/// which is accepted by endpoints that do not return anything.
+#[derive(Debug)]
+pub struct MockApi {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    server: JoinHandle<()>,
+}
+
+impl MockApi {
+    /// Starts serving a new mocked API on an unused local port.
+    pub async fn start() -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState::default()));
+
+        let server_state = state.clone();
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = server_state.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = Self::serve(stream, &state).await {
+                        warn!("Mock API could not serve request: {err}");
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            addr,
+            state,
+            server,
+        })
+    }
+
+    /// Responds to requests with the given method and path with the given status code and JSON
+    /// body. Routes configured later take precedence over earlier ones.
+    ///
+    /// # Panics
+    /// * If the body cannot be serialized.
+    pub fn respond(
+        &self,
+        method: Method,
+        path: impl Into<String>,
+        status: u16,
+        body: impl Serialize,
+    ) {
+        self.state.lock().expect("poison").routes.push(Route {
+            method,
+            path: path.into(),
+            status,
+            body: serde_json::to_string(&body).expect("mock response should serialize"),
+        });
+    }
+
+    /// Returns the base URL of the mocked API.
+    #[must_use]
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Creates an HTTP client which sends its requests to the mocked API.
+    pub fn http(&self) -> Http {
+        Http::from_token_and_uri("mock-token", self.url().as_str())
+    }
+
+    /// Creates a [`Context`] whose HTTP client sends its requests to the mocked API. The context
+    /// has no gateway connection.
+    #[must_use]
+    pub fn context(&self) -> Context {
+        Context::new(Arc::new(self.http()))
+    }
+
+    /// Returns all requests received so far, in the order they were received.
+    #[must_use]
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().expect("poison").requests.clone()
+    }
+
+    /// Returns and clears all requests received so far.
+    pub fn take_requests(&self) -> Vec<RecordedRequest> {
+        std::mem::take(&mut self.state.lock().expect("poison").requests)
+    }
+
+    /// Resolves a raw gateway message into the events that would be dispatched to handlers,
+    /// attached to a [`context`](Self::context) of this mocked API.
+    #[cfg(feature = "ws")]
+    pub async fn resolve(&self, message: InboundMessage) -> Vec<Event> {
+        let ctx = self.context();
+        let replaced = ctx.cache.apply(&message).await;
+        let mut events = Vec::new();
+        crate::ws::event::populate(ctx, message, replaced, &mut events);
+        events
+    }
+
+    /// Passes the given event to the consumer, waiting for it to be handled.
+    #[cfg(feature = "ws")]
+    pub async fn inject(&self, consumer: &impl EventConsumer, event: Event) {
+        consumer.handle_event(event).await;
+    }
+
+    async fn serve(stream: TcpStream, state: &Mutex<MockState>) -> std::io::Result<()> {
+        let mut reader = BufReader::new(stream);
+
+        let mut line = String::new();
+        reader.read_line(&mut line).await?;
+        let mut parts = line.split_whitespace();
+        let method = parts
+            .next()
+            .and_then(|method| Method::from_bytes(method.as_bytes()).ok())
+            .unwrap_or_default();
+        let target = parts.next().unwrap_or("/").to_string();
+
+        let mut content_length = 0;
+        loop {
+            line.clear();
+            reader.read_line(&mut line).await?;
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).await?;
+
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path.to_string(), Some(query.to_string())),
+            None => (target, None),
+        };
+        let (status, response) = {
+            let mut state = state.lock().expect("poison");
+            let route = state
+                .routes
+                .iter()
+                .rev()
+                .find(|route| route.method == method && route.path == path)
+                .map_or_else(
+                    || (200, "null".to_string()),
+                    |route| (route.status, route.body.clone()),
+                );
+
+            state.requests.push(RecordedRequest {
+                method,
+                path,
+                query,
+                body: serde_json::from_slice(&body).ok(),
+            });
+            route
+        };
+
+        let response = format!(
+            "HTTP/1.1 {status} MOCK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+            response.len(),
+        );
+        let mut stream = reader.into_inner();
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await
+    }
+}
+
+impl Drop for MockApi {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::endpoints;
+
+    #[tokio::test]
+    async fn mock_api() {
+        let api = MockApi::start().await.unwrap();
+        api.respond(
+            Method::DELETE,
+            "/channels/1/messages/2",
+            404,
+            serde_json::json!({
+                "type": "NotFound",
+                "entity": "message",
+                "message": "Message not found",
+            }),
+        );
+
+        let http = api.http();
+        http.request(endpoints::TriggerTyping(1)).await.unwrap();
+        let err = http
+            .request(endpoints::DeleteMessage(1, 2))
+            .await
+            .unwrap_err();
+        assert!(err.is_not_found());
+
+        let requests = api.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].method, Method::POST);
+        assert_eq!(requests[0].path, "/channels/1/typing");
+        assert_eq!(requests[1].method, Method::DELETE);
+    }
+}
@@ -0,0 +1,110 @@
+//! TLS configuration for connecting to self-hosted Adapt instances, for example ones that use
+//! certificates issued by a private certificate authority.
+
+/// The encoding of a [`Certificate`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Encoding {
+    Pem,
+    Der,
+}
+
+/// A trusted root certificate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Certificate {
+    data: Vec<u8>,
+    encoding: Encoding,
+}
+
+impl Certificate {
+    /// Parses a certificate from its PEM encoding.
+    pub fn from_pem(pem: impl Into<Vec<u8>>) -> crate::Result<Self> {
+        let data = pem.into();
+        reqwest::Certificate::from_pem(&data)?;
+
+        Ok(Self {
+            data,
+            encoding: Encoding::Pem,
+        })
+    }
+
+    /// Parses a certificate from its DER encoding.
+    pub fn from_der(der: impl Into<Vec<u8>>) -> crate::Result<Self> {
+        let data = der.into();
+        reqwest::Certificate::from_der(&data)?;
+
+        Ok(Self {
+            data,
+            encoding: Encoding::Der,
+        })
+    }
+
+    pub(crate) fn to_reqwest(&self) -> reqwest::Certificate {
+        match self.encoding {
+            Encoding::Pem => reqwest::Certificate::from_pem(&self.data),
+            Encoding::Der => reqwest::Certificate::from_der(&self.data),
+        }
+        .expect("certificate was validated on creation")
+    }
+
+    #[cfg(feature = "ws")]
+    pub(crate) fn to_native(&self) -> Result<native_tls::Certificate, native_tls::Error> {
+        match self.encoding {
+            Encoding::Pem => native_tls::Certificate::from_pem(&self.data),
+            Encoding::Der => native_tls::Certificate::from_der(&self.data),
+        }
+    }
+}
+
+/// TLS options shared by the HTTP client and the gateway connection.
+///
+/// # Example
+/// ```no_run
+/// use adapt::tls::{Certificate, TlsOptions};
+///
+/// # fn run() -> adapt::Result<()> {
+/// let ca = Certificate::from_pem(std::fs::read("ca.pem")?)?;
+/// // Only trust certificates issued by the private CA
+/// let tls = TlsOptions::new().add_root_certificate(ca).disable_built_in_roots();
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[must_use]
+pub struct TlsOptions {
+    pub(crate) root_certificates: Vec<Certificate>,
+    pub(crate) built_in_roots: bool,
+}
+
+impl TlsOptions {
+    /// Creates new TLS options which trust the built-in root certificates.
+    pub const fn new() -> Self {
+        Self {
+            root_certificates: Vec::new(),
+            built_in_roots: true,
+        }
+    }
+
+    /// Trusts the given root certificate in addition to any other trusted roots.
+    pub fn add_root_certificate(mut self, certificate: Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    /// Stops trusting the built-in root certificates, so that only the root certificates added
+    /// with [`Self::add_root_certificate`] are trusted. This effectively pins the trusted
+    /// certificates.
+    pub const fn disable_built_in_roots(mut self) -> Self {
+        self.built_in_roots = false;
+        self
+    }
+
+    /// Builds a TLS connector for the gateway connection.
+    #[cfg(feature = "ws")]
+    pub(crate) fn native_connector(&self) -> Result<native_tls::TlsConnector, native_tls::Error> {
+        let mut builder = native_tls::TlsConnector::builder();
+        builder.disable_built_in_roots(!self.built_in_roots);
+        for certificate in &self.root_certificates {
+            builder.add_root_certificate(certificate.to_native()?);
+        }
+        builder.build()
+    }
+}
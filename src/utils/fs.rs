@@ -0,0 +1,15 @@
+//! Filesystem helpers for the file-backed stores.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Writes the given contents to the file at the given path atomically, by writing them to a
+/// temporary file next to it and renaming that over the file. A crash while writing leaves
+/// either the previous or the new contents, but never a partially written file.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temp = PathBuf::from(path).into_os_string();
+    temp.push(".tmp");
+
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, path)
+}
@@ -0,0 +1,180 @@
+//! Lazily-initialized state stored separately for each guild.
+
+use crate::models::GuildId;
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug},
+    future::Future,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::OnceCell;
+
+type Slot<T> = Arc<OnceCell<Arc<T>>>;
+
+/// Stores a value of type `T` for each guild, initialized asynchronously the first time it is
+/// requested for that guild, for example by loading per-guild settings from a database.
+///
+/// Cloning a `GuildLocal` is cheap and shares the underlying storage, so it can be created once
+/// and moved into every handler that needs it. Values are handed out as [`Arc`]s; use interior
+/// mutability (for example a [`tokio::sync::RwLock`]) for state that changes after
+/// initialization.
+///
+/// # Example
+/// ```no_run
+/// use adapt::utils::guild_local::GuildLocal;
+/// # use adapt::models::GuildId;
+///
+/// struct Settings {
+///     prefix: String,
+/// }
+///
+/// # async fn run(settings: GuildLocal<Settings>, guild_id: GuildId) {
+/// let settings = settings
+///     .get_or_init(guild_id, || async {
+///         Settings {
+///             prefix: "!".to_string(),
+///         }
+///     })
+///     .await;
+/// assert_eq!(settings.prefix, "!");
+/// # }
+/// ```
+pub struct GuildLocal<T> {
+    slots: Arc<Mutex<HashMap<GuildId, Slot<T>>>>,
+}
+
+impl<T> GuildLocal<T> {
+    /// Creates a new, empty storage.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            slots: Arc::default(),
+        }
+    }
+
+    fn slot(&self, guild_id: GuildId) -> Slot<T> {
+        self.slots
+            .lock()
+            .expect("poison")
+            .entry(guild_id)
+            .or_default()
+            .clone()
+    }
+
+    /// Returns the value for the given guild, if it has been initialized.
+    #[must_use]
+    pub fn get(&self, guild_id: GuildId) -> Option<Arc<T>> {
+        let slots = self.slots.lock().expect("poison");
+        slots.get(&guild_id)?.get().cloned()
+    }
+
+    /// Returns the value for the given guild, initializing it with the given function if it has
+    /// not been initialized yet.
+    ///
+    /// If the value is being initialized concurrently, this waits for that initialization to
+    /// finish instead of initializing it again.
+    pub async fn get_or_init<F, Fut>(&self, guild_id: GuildId, init: F) -> Arc<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let slot = self.slot(guild_id);
+        slot.get_or_init(|| async { Arc::new(init().await) })
+            .await
+            .clone()
+    }
+
+    /// Returns the value for the given guild, initializing it with the given fallible function if
+    /// it has not been initialized yet. If initialization fails, the error is returned and the
+    /// next call tries again.
+    pub async fn get_or_try_init<F, Fut, E>(&self, guild_id: GuildId, init: F) -> Result<Arc<T>, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let slot = self.slot(guild_id);
+        slot.get_or_try_init(|| async { init().await.map(Arc::new) })
+            .await
+            .cloned()
+    }
+
+    /// Sets the value for the given guild, returning the previous value if there was one.
+    pub fn insert(&self, guild_id: GuildId, value: T) -> Option<Arc<T>> {
+        let slot = Arc::new(OnceCell::new_with(Some(Arc::new(value))));
+        let previous = self.slots.lock().expect("poison").insert(guild_id, slot)?;
+        previous.get().cloned()
+    }
+
+    /// Removes the value for the given guild, for example when the client leaves it. The value is
+    /// initialized again the next time it is requested.
+    pub fn remove(&self, guild_id: GuildId) -> Option<Arc<T>> {
+        let previous = self.slots.lock().expect("poison").remove(&guild_id)?;
+        previous.get().cloned()
+    }
+
+    /// Returns the IDs of all guilds whose value has been initialized.
+    #[must_use]
+    pub fn guild_ids(&self) -> Vec<GuildId> {
+        let slots = self.slots.lock().expect("poison");
+        slots
+            .iter()
+            .filter(|(_, slot)| slot.initialized())
+            .map(|(guild_id, _)| *guild_id)
+            .collect()
+    }
+}
+
+impl<T> Clone for GuildLocal<T> {
+    fn clone(&self) -> Self {
+        Self {
+            slots: self.slots.clone(),
+        }
+    }
+}
+
+impl<T> Default for GuildLocal<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Debug for GuildLocal<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GuildLocal")
+            .field("guilds", &self.guild_ids())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn init_once() {
+        let local = GuildLocal::new();
+        let calls = AtomicUsize::new(0);
+        let guild_id = GuildId::from(1);
+        let init = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            tokio::task::yield_now().await;
+            "settings"
+        };
+
+        let (a, b) = tokio::join!(
+            local.get_or_init(guild_id, init),
+            local.get_or_init(guild_id, init),
+        );
+        assert_eq!((*a, *b), ("settings", "settings"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        assert!(local
+            .get_or_try_init(GuildId::from(2), || async { Err::<&str, _>(()) })
+            .await
+            .is_err());
+        assert_eq!(local.guild_ids(), [guild_id]);
+        assert_eq!(local.remove(guild_id).as_deref(), Some(&"settings"));
+        assert!(local.get(guild_id).is_none());
+    }
+}
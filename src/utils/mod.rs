@@ -0,0 +1,7 @@
+//! Miscellaneous utilities for working with Adapt.
+
+pub(crate) mod fs;
+pub mod guild_local;
+#[cfg(feature = "ws")]
+pub mod paginator;
+pub mod token;
@@ -0,0 +1,266 @@
+//! Interactive pagination of a list of entries within a single message.
+//!
+//! Adapt does not dispatch reaction events over the gateway, so a [`Paginator`] is navigated with
+//! short text commands instead: users reply with `next`, `prev`, or `stop` in the same channel.
+//! These command messages are deleted when possible to keep the channel tidy.
+
+use crate::models::{ChannelId, Message, UserId};
+use crate::ws::{self, Event};
+use crate::{Context, Result, WithCtx};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+/// A navigation command sent by a user.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Command {
+    Previous,
+    Next,
+    Stop,
+}
+
+impl Command {
+    fn parse(content: &str) -> Option<Self> {
+        match content.trim().to_ascii_lowercase().as_str() {
+            "prev" | "previous" | "back" => Some(Self::Previous),
+            "next" => Some(Self::Next),
+            "stop" => Some(Self::Stop),
+            _ => None,
+        }
+    }
+}
+
+/// Pages through a list of entries within a single message.
+///
+/// # Example
+/// ```no_run
+/// use adapt::utils::paginator::Paginator;
+/// use std::time::Duration;
+///
+/// # async fn run(channel: adapt::WithCtx<adapt::models::ChannelId>) -> adapt::Result<()> {
+/// Paginator::new(["First page", "Second page", "Third page"])
+///     .timeout(Duration::from_secs(120))
+///     .run(channel)
+///     .await?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug)]
+#[must_use = "call `run` to send the paginator"]
+pub struct Paginator {
+    pages: Vec<String>,
+    timeout: Duration,
+    user: Option<UserId>,
+    delete_on_timeout: bool,
+}
+
+impl Paginator {
+    /// The default time to wait for a navigation command before the paginator expires.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Creates a new paginator over the given pages.
+    pub fn new(pages: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            pages: pages.into_iter().map(Into::into).collect(),
+            timeout: Self::DEFAULT_TIMEOUT,
+            user: None,
+            delete_on_timeout: false,
+        }
+    }
+
+    /// Sets how long to wait for a navigation command before the paginator expires. The timeout
+    /// is reset after every command. Defaults to [`Self::DEFAULT_TIMEOUT`].
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Only accepts navigation commands from the given user. By default, anyone in the channel
+    /// can navigate the paginator.
+    pub const fn user(mut self, user_id: UserId) -> Self {
+        self.user = Some(user_id);
+        self
+    }
+
+    /// Whether to delete the paginator message once it expires or is stopped. Otherwise, the
+    /// current page is kept without the navigation hint. Defaults to `false`.
+    pub const fn delete_on_timeout(mut self, delete: bool) -> Self {
+        self.delete_on_timeout = delete;
+        self
+    }
+
+    fn render(&self, index: usize) -> String {
+        format!(
+            "{}\n\n*Page {} of {} \u{2014} reply `prev`, `next`, or `stop` to navigate*",
+            self.pages[index],
+            index + 1,
+            self.pages.len(),
+        )
+    }
+
+    async fn edit(message: &WithCtx<Message>, content: String) -> Result<()> {
+        message.edit(|e| e.content(content)).await.map(drop)
+    }
+
+    /// Sends the paginator to the given channel and handles navigation until it expires or is
+    /// stopped. Does nothing if there are no pages.
+    ///
+    /// The client must be connected to the gateway to receive navigation commands.
+    ///
+    /// # Errors
+    /// * [`ws::Error::BlockingDispatch`] if called from an event handler run by the
+    ///   [inline](crate::ws::handler::DispatchExecutor::inline) executor, which cannot receive
+    ///   navigation commands until the handler finishes. Use [`Self::spawn`] instead.
+    pub async fn run(self, channel: WithCtx<ChannelId>) -> Result<()> {
+        let events = channel.ctx.subscribe_events()?;
+        self.navigate(channel, events).await
+    }
+
+    /// Sends the paginator to the given channel and handles navigation on a new task, which can
+    /// be awaited to wait for the paginator to expire or be stopped. Unlike [`Self::run`], this
+    /// can be used from any event handler.
+    ///
+    /// # Panics
+    /// * If called outside of a Tokio runtime.
+    pub fn spawn(self, channel: WithCtx<ChannelId>) -> JoinHandle<Result<()>> {
+        // Subscribe before spawning, so that no command sent in the meantime is missed
+        let events = channel.ctx.events.subscribe();
+        tokio::spawn(self.navigate(channel, events))
+    }
+
+    async fn navigate(
+        self,
+        channel: WithCtx<ChannelId>,
+        mut events: broadcast::Receiver<Event>,
+    ) -> Result<()> {
+        if self.pages.is_empty() {
+            return Ok(());
+        }
+
+        if self.pages.len() == 1 {
+            let _ = channel.send(self.pages[0].as_str()).await?;
+            return Ok(());
+        }
+
+        let message = channel.send(self.render(0)).await?;
+
+        let mut index = 0_usize;
+        loop {
+            let event = Context::recv_matching(
+                &mut events,
+                |event| match event {
+                    Event::MessageCreate(command) => {
+                        command.inner().channel_id() == *channel.inner()
+                            && self.user.is_none_or(|user| command.author_id == Some(user))
+                            && Command::parse(&command.content).is_some()
+                    }
+                    _ => false,
+                },
+                self.timeout,
+            )
+            .await;
+
+            let Some(Event::MessageCreate(command)) = event else {
+                break;
+            };
+            // Missing permissions to delete the command are not fatal
+            let _ = command.partial().delete().await;
+
+            index = match Command::parse(&command.content) {
+                Some(Command::Previous) => index.saturating_sub(1),
+                Some(Command::Next) => (index + 1).min(self.pages.len() - 1),
+                _ => break,
+            };
+            Self::edit(&message, self.render(index)).await?;
+        }
+
+        if self.delete_on_timeout {
+            message.partial().delete().await
+        } else {
+            Self::edit(&message, self.pages[index].clone()).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_command() {
+        assert_eq!(Command::parse(" Next "), Some(Command::Next));
+        assert_eq!(Command::parse("back"), Some(Command::Previous));
+        assert_eq!(Command::parse("STOP"), Some(Command::Stop));
+        assert_eq!(Command::parse("next page"), None);
+    }
+
+    #[tokio::test]
+    async fn navigate_from_handler() {
+        use crate::http::Method;
+        use crate::testing::MockApi;
+        use crate::ws::handler::DispatchExecutor;
+
+        let message = |id: u64, content: &str| essence::models::Message {
+            id,
+            channel_id: 1,
+            author_id: Some(3),
+            author: None,
+            content: Some(content.to_string()),
+            embeds: Vec::new(),
+            attachments: Vec::new(),
+            flags: 0,
+            mentions: Vec::new(),
+            edited_at: None,
+        };
+        let api = MockApi::start().await.unwrap();
+        api.respond(Method::POST, "/channels/1/messages", 200, message(2, ""));
+        api.respond(Method::PATCH, "/channels/1/messages/2", 200, message(2, ""));
+        let ctx = api.context();
+        let channel = ctx.clone().with(ChannelId::from(1));
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        DispatchExecutor::inline()
+            .execute(
+                None,
+                Box::pin(async move {
+                    let paginator = Paginator::new(["First", "Second"]);
+                    let blocked = paginator.clone().run(channel.clone()).await;
+                    assert!(matches!(
+                        blocked,
+                        Err(crate::Error::Harmony(ws::Error::BlockingDispatch))
+                    ));
+                    let _ = tx.send(paginator.spawn(channel));
+                }),
+            )
+            .await;
+        let navigation = rx.await.unwrap();
+
+        for (id, command) in [(4, "next"), (5, "stop")] {
+            let command = Message::from_raw(message(id, command));
+            let _ = ctx
+                .events
+                .send(Event::MessageCreate(ctx.clone().with(command)));
+        }
+        navigation.await.unwrap().unwrap();
+
+        let requests = api.requests();
+        let paths = requests
+            .iter()
+            .map(|request| (request.method.as_str(), request.path.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                ("POST", "/channels/1/messages"),
+                ("DELETE", "/channels/1/messages/4"),
+                ("PATCH", "/channels/1/messages/2"),
+                ("DELETE", "/channels/1/messages/5"),
+                ("PATCH", "/channels/1/messages/2"),
+            ]
+        );
+        assert_eq!(
+            requests[2].body.as_ref().unwrap()["content"],
+            Paginator::new(["First", "Second"]).render(1)
+        );
+        assert_eq!(requests[4].body.as_ref().unwrap()["content"], "Second");
+    }
+}
@@ -0,0 +1,123 @@
+//! Utilities for inspecting Adapt authentication tokens.
+//!
+//! An Adapt token consists of three segments separated by periods (`.`):
+//!
+//! 1. The ID of the user (or bot) the token belongs to, as a base64-encoded decimal string.
+//! 2. The time the token was issued, in milliseconds since the Unix epoch, encoded the same way.
+//! 3. A random secret.
+//!
+//! Only the first two segments can be decoded client-side, and doing so does **not** verify that
+//! the token is valid; only the Adapt API can do that.
+//!
+//! # Expiry
+//! Tokens carry no expiry time and stay valid until they are revoked, so they never need to be
+//! refreshed, and the API provides no way to do so. To rotate a token, log in again with
+//! [`TokenRetrievalMethod::Revoke`](crate::http::TokenRetrievalMethod::Revoke), which revokes all
+//! previous tokens of the user.
+
+use crate::models::{timestamp, Id, Timestamp, UserId};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use std::fmt::{self, Display};
+
+/// An error that occurs while parsing a token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TokenError {
+    /// The token does not consist of three non-empty segments separated by periods.
+    Malformed,
+    /// The given segment of the token could not be decoded into an integer.
+    InvalidSegment(&'static str),
+    /// The token contains characters which cannot be sent in a header.
+    InvalidCharacters,
+}
+
+impl Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed => f.write_str("token must consist of three segments separated by `.`"),
+            Self::InvalidSegment(segment) => {
+                write!(f, "the {segment} segment of the token is invalid")
+            }
+            Self::InvalidCharacters => {
+                f.write_str("token contains characters which cannot be sent in a header")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TokenError {}
+
+fn decode_segment(segment: &str, name: &'static str) -> Result<u64, TokenError> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(segment.trim_end_matches('='))
+        .map_err(|_| TokenError::InvalidSegment(name))?;
+
+    std::str::from_utf8(&bytes)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or(TokenError::InvalidSegment(name))
+}
+
+/// Parses the given token, returning the ID of the user it belongs to and the time it was issued.
+///
+/// This is useful for knowing the ID of the client before identifying with the gateway, or for
+/// rejecting obviously invalid tokens early.
+///
+/// # Example
+/// ```
+/// use adapt::utils::token;
+///
+/// assert!(token::parse("not a token").is_err());
+/// ```
+pub fn parse(token: impl AsRef<str>) -> crate::Result<(UserId, Timestamp)> {
+    let mut segments = token.as_ref().split('.');
+
+    let (Some(id), Some(issued_at), Some(secret), None) = (
+        segments.next(),
+        segments.next(),
+        segments.next(),
+        segments.next(),
+    ) else {
+        return Err(TokenError::Malformed.into());
+    };
+    if id.is_empty() || issued_at.is_empty() || secret.is_empty() {
+        return Err(TokenError::Malformed.into());
+    }
+
+    let id = decode_segment(id, "user ID")?;
+    let issued_at = decode_segment(issued_at, "timestamp")?;
+
+    Ok((UserId::new_unchecked(id), timestamp::from_millis(issued_at)))
+}
+
+/// Parses only the user ID out of the given token.
+///
+/// See [`parse`] for more information.
+pub fn user_id(token: impl AsRef<str>) -> crate::Result<UserId> {
+    parse(token).map(|(id, _)| id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(n: u64) -> String {
+        URL_SAFE_NO_PAD.encode(n.to_string())
+    }
+
+    #[test]
+    fn parse_valid_token() {
+        let token = format!("{}.{}.secret", encode(1234), encode(5678));
+        let (id, issued_at) = parse(token).unwrap();
+
+        assert_eq!(id, 1234);
+        assert_eq!(issued_at, timestamp::from_millis(5678));
+    }
+
+    #[test]
+    fn parse_invalid_token() {
+        assert!(parse("").is_err());
+        assert!(parse("a.b").is_err());
+        assert!(parse(format!("{}..secret", encode(1))).is_err());
+        assert!(parse(format!("!!.{}.secret", encode(1))).is_err());
+    }
+}
@@ -1,6 +1,11 @@
-use crate::Server;
+use super::handler::{DispatchExecutor, Watchdog};
+use super::session::{SessionStore, SessionStoreHandle};
+use super::shard::{ShardCoordinator, ShardCoordinatorHandle};
+use super::tap::{FrameTap, RawFrame, UndecodableFrame, UndecodableHook};
+use crate::{tls::TlsOptions, Server};
 use essence::models::{Device, PresenceStatus};
 use secrecy::SecretString;
+use std::sync::Arc;
 use url::Url;
 
 /// A trait for types that can be converted into a valid URL for harmony.
@@ -42,6 +47,27 @@ pub struct ConnectOptions {
     pub custom_status: Option<String>,
     /// The device to identify as. Defaults to [`Device::Desktop`].
     pub device: Device,
+    /// The store in which the session is persisted, if any.
+    pub(crate) session_store: Option<SessionStoreHandle>,
+    /// Whether to attempt resuming the stored session instead of identifying.
+    pub(crate) resume_sessions: bool,
+    /// The coordinator used to claim a shard and gate identifies, if any.
+    pub(crate) shard_coordinator: Option<ShardCoordinatorHandle>,
+    /// The version of the gateway protocol to request, if any. Defaults to `None`, which lets the
+    /// gateway choose.
+    pub version: Option<u8>,
+    /// Extra query parameters appended to the gateway URL.
+    pub(crate) query: Vec<(String, String)>,
+    /// The TLS options used when connecting to the gateway, if any.
+    pub(crate) tls: Option<TlsOptions>,
+    /// The watchdog used to detect slow event handlers, if any.
+    pub(crate) watchdog: Option<Watchdog>,
+    /// Determines where event consumers run.
+    pub(crate) executor: DispatchExecutor,
+    /// The hook receiving every raw frame exchanged with the gateway, if any.
+    pub(crate) frame_tap: Option<FrameTap>,
+    /// The hook receiving frames which could not be decoded, if any.
+    pub(crate) undecodable_hook: Option<UndecodableHook>,
 }
 
 impl ConnectOptions {
@@ -54,6 +80,16 @@ impl ConnectOptions {
             status: PresenceStatus::Online,
             custom_status: None,
             device: Device::Desktop,
+            session_store: None,
+            resume_sessions: false,
+            shard_coordinator: None,
+            version: None,
+            query: Vec::new(),
+            tls: None,
+            watchdog: None,
+            executor: DispatchExecutor::inline(),
+            frame_tap: None,
+            undecodable_hook: None,
         }
     }
 
@@ -84,4 +120,137 @@ impl ConnectOptions {
         self.device = device;
         self
     }
+
+    /// Sets the version of the gateway protocol to request, sent as the `v` query parameter. This
+    /// is useful for targeting older or newer versions of harmony on self-hosted instances.
+    #[inline]
+    pub const fn version(mut self, version: u8) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Appends an extra query parameter to the gateway URL.
+    #[inline]
+    pub fn query_param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.query.push((key.into(), value.into()));
+        self
+    }
+
+    /// Sets the TLS options used when connecting to the gateway, for example to trust the
+    /// certificate authority of a self-hosted instance.
+    #[inline]
+    pub fn tls(mut self, tls: TlsOptions) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Sets the watchdog used to detect event handlers that take too long to handle an event.
+    #[inline]
+    pub fn watchdog(mut self, watchdog: Watchdog) -> Self {
+        self.watchdog = Some(watchdog);
+        self
+    }
+
+    /// Sets where event consumers run when an event is dispatched. Defaults to
+    /// [`DispatchExecutor::inline`].
+    #[inline]
+    pub fn dispatch_executor(mut self, executor: DispatchExecutor) -> Self {
+        self.executor = executor;
+        self
+    }
+
+    /// Sets a hook which receives every raw frame sent to and received from the gateway, along
+    /// with the name of its operation. This is intended for protocol debugging and for recording
+    /// traffic, and is called from within the connection loop, so it should return quickly.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use adapt::ws::ConnectOptions;
+    ///
+    /// let options = ConnectOptions::new("token").frame_tap(|frame| {
+    ///     eprintln!("{:?} {:?} ({} bytes)", frame.direction, frame.op, frame.bytes.len());
+    /// });
+    /// ```
+    #[inline]
+    pub fn frame_tap(mut self, tap: impl Fn(&RawFrame) + Send + Sync + 'static) -> Self {
+        self.frame_tap = Some(FrameTap(Arc::new(tap)));
+        self
+    }
+
+    /// Sets a hook which receives every frame from the gateway that could not be decoded. Such
+    /// frames are always logged and skipped without closing the connection.
+    #[inline]
+    pub fn on_undecodable_frame(
+        mut self,
+        hook: impl Fn(&UndecodableFrame) + Send + Sync + 'static,
+    ) -> Self {
+        self.undecodable_hook = Some(UndecodableHook(Arc::new(hook)));
+        self
+    }
+
+    /// Returns the full URL to connect to, including the query parameters for the encoding, the
+    /// protocol version, and any extra query parameters.
+    #[must_use]
+    pub fn connect_url(&self) -> Url {
+        let mut url = self.url.clone();
+        {
+            let mut query = url.query_pairs_mut();
+            query.clear().append_pair("format", "msgpack");
+            if let Some(version) = self.version {
+                query.append_pair("v", &version.to_string());
+            }
+            for (key, value) in &self.query {
+                query.append_pair(key, value);
+            }
+        }
+        url
+    }
+
+    /// Sets the store in which the gateway session is persisted. The stored session is only used
+    /// to resume if [`Self::resume_sessions`] is enabled.
+    #[inline]
+    pub fn session_store(mut self, store: impl SessionStore + 'static) -> Self {
+        self.session_store = Some(SessionStoreHandle(Arc::new(store)));
+        self
+    }
+
+    /// Sets whether the client attempts to resume the session in the
+    /// [session store](Self::session_store) when connecting instead of identifying from scratch.
+    /// Defaults to `false`.
+    ///
+    /// Harmony does not currently support resuming sessions: the `resume` frame sent by the client
+    /// is not part of its protocol. Only enable this for gateways which accept it. If the gateway
+    /// sends no event within [`Connection::RESUME_TIMEOUT`](super::Connection::RESUME_TIMEOUT)
+    /// after the resume, the stored session is cleared and the client identifies instead.
+    #[inline]
+    pub const fn resume_sessions(mut self, enabled: bool) -> Self {
+        self.resume_sessions = enabled;
+        self
+    }
+
+    /// Sets the coordinator used to claim a shard when the client starts and to wait for
+    /// permission before every identify. See [`shard`][super::shard] for more information.
+    #[inline]
+    pub fn shard_coordinator(mut self, coordinator: impl ShardCoordinator + 'static) -> Self {
+        self.shard_coordinator = Some(ShardCoordinatorHandle(Arc::new(coordinator)));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connect_url() {
+        let options = ConnectOptions::new("token")
+            .url("wss://harmony.example.com".to_string())
+            .version(2)
+            .query_param("compress", "none");
+
+        assert_eq!(
+            options.connect_url().as_str(),
+            "wss://harmony.example.com/?format=msgpack&v=2&compress=none"
+        );
+    }
 }
@@ -1,13 +1,23 @@
 use super::{
+    handler::{DispatchExecutor, Watchdog},
+    publish::{self, PublishedEvent},
+    session::{SessionStoreHandle, SessionWriter},
+    stats::StatsRecorder,
+    tap::{EventTag, FrameDirection, FrameTap, UndecodableFrame, UndecodableHook},
     ClientAction, ConnectOptions, ConnectionAction, ConsumerVec, Error, InboundMessage,
-    OutboundMessage, PartialIdentify, Result,
+    OutboundMessage, PartialIdentify, PublisherVec, Result, Resume, SessionInfo,
 };
-use crate::ws::event::populate;
+use crate::ws::event::{channel_of, dispatch_events, resolve, run_dispatch, Event};
 use crate::Context;
 use essence::models::PresenceStatus;
 use futures_util::{future::JoinAll, SinkExt, StreamExt};
 use rmp_serde::to_vec_named;
 use secrecy::SecretString;
+use serde::Serialize;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 use std::time::{Duration, Instant};
 use tokio::time::timeout;
 use tokio::{
@@ -15,25 +25,60 @@ use tokio::{
     sync::mpsc::{Receiver, Sender},
 };
 use tokio_tungstenite::{
-    connect_async_with_config,
-    tungstenite::{protocol::WebSocketConfig, Message},
-    MaybeTlsStream, WebSocketStream,
+    connect_async_tls_with_config,
+    tungstenite::{
+        protocol::{CloseFrame, WebSocketConfig},
+        Message,
+    },
+    Connector, MaybeTlsStream, WebSocketStream,
 };
 
 /// Manages a single connection to Harmony.
 ///
 /// A connection is
 pub struct Connection {
+    id: u64,
     ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
     token: SecretString,
     identify: PartialIdentify,
     last_heartbeat_sent: Instant,
     latency: Option<Duration>,
-    #[allow(dead_code)]
     client_tx: Sender<ClientAction>,
     runner_rx: Receiver<ConnectionAction>,
     consumers: ConsumerVec,
+    publishers: PublisherVec,
     context: Context,
+    session_store: Option<SessionStoreHandle>,
+    session_writer: Option<SessionWriter>,
+    session: Option<SessionInfo>,
+    /// The sequence number of the last dispatch event received.
+    sequence: u64,
+    resume_sessions: bool,
+    resuming: bool,
+    /// When to give up on the pending resume and identify instead.
+    resume_deadline: Option<Instant>,
+    stats: Arc<StatsRecorder>,
+    watchdog: Option<Watchdog>,
+    executor: DispatchExecutor,
+    frame_tap: Option<FrameTap>,
+    undecodable_hook: Option<UndecodableHook>,
+}
+
+/// The source of unique connection IDs, used to correlate log records of a connection.
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Logs a record tagged with the connection ID, shard, and session ID of the given connection as
+/// structured key-values.
+macro_rules! log_conn {
+    ($level:ident, $conn:expr, $($arg:tt)+) => {
+        $level!(
+            connection_id = $conn.id,
+            shard_id = $conn.context.shard_id(),
+            shard_count = $conn.context.shard_count(),
+            session_id = $conn.session.as_ref().map(|session| session.session_id.as_str());
+            $($arg)+
+        )
+    };
 }
 
 impl Connection {
@@ -48,27 +93,38 @@ impl Connection {
     /// within this duration, the event will be ignored.
     pub const ACQUIRE_TIMEOUT: Duration = Duration::from_millis(500);
 
+    /// The time to wait for the first event after requesting to resume a session. If no event is
+    /// received within this duration, the client identifies instead.
+    pub const RESUME_TIMEOUT: Duration = Duration::from_secs(5);
+
     /// Initializes a new client and connects to the gateway.
     pub(crate) async fn new(
-        mut options: ConnectOptions,
+        options: ConnectOptions,
         client_tx: Sender<ClientAction>,
         runner_rx: Receiver<ConnectionAction>,
         consumers: ConsumerVec,
+        publishers: PublisherVec,
         context: Context,
+        stats: Arc<StatsRecorder>,
     ) -> Result<Self> {
-        options.url.set_query(Some("format=msgpack"));
-        let (stream, _) = connect_async_with_config(
-            options.url.as_str(),
+        let connector = match &options.tls {
+            Some(tls) => Some(Connector::NativeTls(tls.native_connector()?)),
+            None => None,
+        };
+        let (stream, _) = connect_async_tls_with_config(
+            options.connect_url().as_str(),
             Some(WebSocketConfig {
                 max_message_size: None,
                 max_frame_size: None,
                 ..Default::default()
             }),
             false,
+            connector,
         )
         .await?;
 
         Ok(Self {
+            id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
             ws: stream,
             token: options.token,
             identify: PartialIdentify {
@@ -81,12 +137,50 @@ impl Connection {
             client_tx,
             runner_rx,
             consumers,
+            publishers,
             context,
+            session_writer: options.session_store.clone().map(SessionWriter::spawn),
+            session_store: options.session_store,
+            session: None,
+            sequence: 0,
+            resume_sessions: options.resume_sessions,
+            resuming: false,
+            resume_deadline: None,
+            stats,
+            watchdog: options.watchdog,
+            executor: options.executor,
+            frame_tap: options.frame_tap,
+            undecodable_hook: options.undecodable_hook,
         })
     }
 
-    async fn send(&mut self, value: &OutboundMessage) -> Result<()> {
-        self.ws.send(Message::Binary(to_vec_named(value)?)).await?;
+    /// Returns the ID of this connection, which is unique within the process. This is attached to
+    /// every log record of the connection as the `connection_id` key-value.
+    #[must_use]
+    pub const fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Wraps an error which ended this connection with the fields identifying the connection.
+    pub(super) fn error_context(&self, err: Error) -> Error {
+        Error::Connection {
+            connection_id: self.id,
+            shard: self.context.shard,
+            session_id: self
+                .session
+                .as_ref()
+                .map(|session| session.session_id.clone()),
+            source: Box::new(err),
+        }
+    }
+
+    async fn send(&mut self, value: &impl Serialize) -> Result<()> {
+        let payload = to_vec_named(value)?;
+        if let Some(tap) = &self.frame_tap {
+            let tag = rmp_serde::from_slice::<EventTag>(&payload).ok();
+            tap.tap(FrameDirection::Outbound, tag.as_ref(), &payload);
+        }
+        self.ws.send(Message::Binary(payload)).await?;
 
         Ok(())
     }
@@ -100,8 +194,36 @@ impl Connection {
             Ok(None) | Err(_) => return Ok(None),
         };
 
+        self.stats.record_bytes(message.len());
         let decoded = match message {
-            Message::Binary(bytes) => rmp_serde::from_slice(&bytes)?,
+            Message::Binary(bytes) => {
+                // The tag is decoded once and shared by the statistics, the sequence, and the tap
+                let tag = rmp_serde::from_slice::<EventTag>(&bytes).ok();
+                if let Some(tap) = &self.frame_tap {
+                    tap.tap(FrameDirection::Inbound, tag.as_ref(), &bytes);
+                }
+                if let Some(tag) = &tag {
+                    self.stats.record_event(&tag.event);
+                    // Skipped events still count, so that a resume continues after them
+                    self.advance_sequence(tag);
+                }
+                match rmp_serde::from_slice(&bytes) {
+                    Ok(decoded) => decoded,
+                    Err(error) => {
+                        // A single unknown event should not tear down the whole session
+                        let op = tag.as_ref().map(|tag| tag.event.as_str());
+                        log_conn!(warn, self, "Skipping undecodable {op:?} event: {error}");
+                        if let Some(hook) = &self.undecodable_hook {
+                            (hook.0)(&UndecodableFrame {
+                                op,
+                                bytes: &bytes,
+                                error: &error,
+                            });
+                        }
+                        return Ok(None);
+                    }
+                }
+            }
             Message::Text(_) => return Err(Error::UnexpectedMessageType),
             Message::Close(frame) => return Err(Error::Closed(frame)),
             _ => return Ok(None),
@@ -110,16 +232,88 @@ impl Connection {
         Ok(Some(decoded))
     }
 
+    /// Advances the sequence past the given frame, using the sequence number sent by the gateway
+    /// if there is one. Frames which are not dispatch events are not counted.
+    fn advance_sequence(&mut self, tag: &EventTag) {
+        if !tag.is_dispatch() {
+            return;
+        }
+        self.sequence = match tag.seq {
+            Some(seq) => seq,
+            // A new session starts counting from its `ready` event
+            None if tag.event == "ready" => 0,
+            None => self.sequence + 1,
+        };
+        if let Some(session) = &mut self.session {
+            session.sequence = self.sequence;
+        }
+    }
+
     /// Sends an identify message to the gateway.
     pub async fn send_identify(&mut self) -> Result<()> {
-        debug!("Sending identify");
+        log_conn!(debug, self, "Sending identify");
         let identify = self.identify.clone().into_identify(&self.token);
         self.send(&identify).await
     }
 
+    /// Sends a request to resume the given session to the gateway.
+    pub async fn send_resume(&mut self, session: SessionInfo) -> Result<()> {
+        log_conn!(
+            debug,
+            self,
+            "Attempting to resume session {}",
+            session.session_id
+        );
+        let token = self.token.clone();
+        self.send(&Resume::new(&token, &session)).await?;
+
+        self.sequence = session.sequence;
+        self.session = Some(session);
+        self.resuming = true;
+        self.resume_deadline = Some(Instant::now() + Self::RESUME_TIMEOUT);
+        Ok(())
+    }
+
+    /// Identifies from scratch if the pending resume was not answered in time, clearing the
+    /// stored session.
+    async fn check_resume_deadline(&mut self) -> Result<()> {
+        if !self
+            .resume_deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Ok(());
+        }
+        log_conn!(
+            warn,
+            self,
+            "Session was not resumed in time, identifying instead"
+        );
+        self.resume_deadline = None;
+        self.resuming = false;
+        self.session = None;
+        self.sequence = 0;
+        if let Some(writer) = &self.session_writer {
+            writer.clear();
+        }
+        self.send_identify().await
+    }
+
+    /// Returns the current session, if the connection has received a `ready` event.
+    #[must_use]
+    pub const fn session(&self) -> Option<&SessionInfo> {
+        self.session.as_ref()
+    }
+
+    /// Persists the current session to the session store in the background, if there is one.
+    fn save_session(&self) {
+        if let (Some(writer), Some(session)) = (&self.session_writer, &self.session) {
+            writer.save(session);
+        }
+    }
+
     /// Sends a heartbeat to the gateway.
     pub async fn send_heartbeat(&mut self) -> Result<()> {
-        debug!("Sending heartbeat");
+        log_conn!(debug, self, "Sending heartbeat");
         self.send(&OutboundMessage::Ping).await?;
         self.last_heartbeat_sent = Instant::now();
         Ok(())
@@ -138,52 +332,144 @@ impl Connection {
         self.send(&payload).await
     }
 
+    async fn publish(&self, message: &InboundMessage) {
+        let publishers = Arc::clone(&*self.publishers.lock().await);
+        if publishers.is_empty() {
+            return;
+        }
+
+        let payload = match publish::encode(message) {
+            Ok(payload) => payload,
+            Err(err) => {
+                log_conn!(warn, self, "Could not encode event for publishers: {err:?}");
+                return;
+            }
+        };
+        let event = PublishedEvent {
+            message: message.clone(),
+            payload,
+        };
+        publishers
+            .iter()
+            .map(|publisher| publisher.dyn_publish(event.clone()))
+            .collect::<JoinAll<_>>()
+            .await;
+    }
+
     async fn handle_message(&mut self, message: InboundMessage) -> Result<()> {
         match message {
             InboundMessage::Ping => {
                 self.send(&OutboundMessage::Pong).await?;
-                debug!("Acknowledged ping");
+                log_conn!(debug, self, "Acknowledged ping");
             }
             InboundMessage::Pong => {
-                self.latency = Some(self.last_heartbeat_sent.elapsed());
-                debug!("Heartbeat acknowledged, latency: {:?}", self.latency);
+                let latency = self.last_heartbeat_sent.elapsed();
+                self.latency = Some(latency);
+                self.stats.record_latency(latency);
+                log_conn!(
+                    debug,
+                    self,
+                    "Heartbeat acknowledged, latency: {:?}",
+                    self.latency
+                );
             }
             event => {
-                let mut events = Vec::with_capacity(4);
-                populate(self.context.clone(), event, &mut events);
-
-                if !events.is_empty() {
-                    debug!("Attempting to dispatch event");
-                    let consumers = timeout(Self::ACQUIRE_TIMEOUT, self.consumers.lock()).await;
-                    if let Ok(mut consumers) = consumers {
-                        for event in events {
-                            consumers
-                                .iter_mut()
-                                .map(|consumer| consumer.dyn_handle_event(event.clone()))
-                                .collect::<JoinAll<_>>()
-                                .await;
-                        }
-                    } else {
-                        warn!("Could not acquire lock to dispatch event");
-                    }
+                if let InboundMessage::Ready { session_id, .. } = &event {
+                    self.session = Some(SessionInfo {
+                        session_id: session_id.clone(),
+                        sequence: self.sequence,
+                    });
+                    self.save_session();
                 }
+                self.resuming = false;
+                self.resume_deadline = None;
+                if let InboundMessage::MessageCreate {
+                    nonce: Some(nonce), ..
+                } = &event
+                {
+                    self.context.outbox.acknowledge(nonce);
+                }
+                // The cache is updated before dispatching so that it is current for handlers,
+                // regardless of how the dispatch executor orders them
+                let replaced = self.context.cache.apply(&event).await;
+                self.publish(&event).await;
+
+                let mut context = self.context.clone();
+                context.sequence = self.session.as_ref().map(|session| session.sequence);
+
+                let channel_id = channel_of(&event);
+                // Waiters are woken here rather than in the executor, so that a handler waiting
+                // for a later event in its channel is not queued in front of that event
+                let events = resolve(&context, event, replaced);
+                let consumers = self.consumers.clone();
+                let watchdog = self.watchdog.clone();
+                let stats = self.stats.clone();
+                stats.enter_dispatch();
+                let task = async move {
+                    if !run_dispatch(&consumers, events, watchdog.as_ref()).await {
+                        stats.record_dropped();
+                    }
+                    stats.exit_dispatch();
+                };
+                self.executor.execute(channel_id, Box::pin(task)).await;
             }
         }
         Ok(())
     }
 
+    /// Dispatches a [`Event::Disconnected`] event after the gateway closed the connection.
+    pub(super) async fn dispatch_disconnect(&self, frame: Option<CloseFrame<'static>>) {
+        let event = Event::Disconnected(self.context.clone(), frame.map(Into::into));
+        dispatch_events(
+            &self.context,
+            &self.consumers,
+            vec![event],
+            self.watchdog.as_ref(),
+        )
+        .await;
+    }
+
     /// Runs the main loop for this session.
     pub async fn run(&mut self) -> Result<()> {
         if !matches!(self.poll().await?, Some(InboundMessage::Hello)) {
             return Err(Error::NoHello);
         }
 
-        self.send_identify().await?;
+        let stored = match self.session_store.clone() {
+            Some(store) if self.resume_sessions => {
+                tokio::task::spawn_blocking(move || store.0.load())
+                    .await
+                    .ok()
+                    .flatten()
+            }
+            _ => None,
+        };
+        match stored {
+            Some(session) => self.send_resume(session).await?,
+            None => self.send_identify().await?,
+        }
+
+        let result = self.run_loop().await;
+        if let Err(err) = &result {
+            log_conn!(warn, self, "Connection error: {err:?}");
+        }
+        if result.is_err() && self.resuming {
+            // The session could not be resumed, so identify from scratch next time
+            if let Some(writer) = &self.session_writer {
+                writer.clear();
+            }
+        }
+        result
+    }
+
+    async fn run_loop(&mut self) -> Result<()> {
         loop {
             // Send heartbeats at consistent intervals
             if self.last_heartbeat_sent.elapsed() >= Self::HEARTBEAT_INTERVAL {
                 self.send_heartbeat().await?;
+                self.save_session();
             }
+            self.check_resume_deadline().await?;
 
             if let Ok(action) = self.runner_rx.try_recv() {
                 match action {
@@ -193,9 +479,27 @@ impl Connection {
                     } => {
                         self.send_update_presence(status, custom_status).await?;
                     }
-                    ConnectionAction::Close => {
-                        debug!("Received close action, shutting down connection...");
+                    ConnectionAction::Reconnect => {
+                        log_conn!(
+                            debug,
+                            self,
+                            "Received reconnect action, restarting connection..."
+                        );
+                        self.save_session();
                         self.ws.close(None).await?;
+                        // If the client is gone, there is nothing left to reconnect
+                        self.client_tx.send(ClientAction::Reconnect).await.ok();
+                        return Ok(());
+                    }
+                    ConnectionAction::Close(frame) => {
+                        log_conn!(
+                            debug,
+                            self,
+                            "Received close action, shutting down connection..."
+                        );
+                        self.save_session();
+                        self.ws.close(frame).await?;
+                        self.client_tx.send(ClientAction::Close(None)).await.ok();
                         return Ok(());
                     }
                 }
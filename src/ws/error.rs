@@ -1,4 +1,4 @@
-use crate::ws::ConnectionAction;
+use crate::ws::{ConnectionAction, ShardInfo};
 use tokio::sync::mpsc::error::SendError;
 use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 
@@ -24,6 +24,43 @@ pub enum Error {
     NoHello,
     /// There is no open connection to the gateway.
     NoConnection,
+    /// Waiting for an event was attempted from a handler run by the
+    /// [inline](crate::ws::handler::DispatchExecutor::inline) executor, which cannot receive any
+    /// event until the handler finishes.
+    BlockingDispatch,
+    /// All shards are already claimed by other processes.
+    NoShardAvailable,
+    /// The shard could not be released because it is not claimed by this process.
+    ShardNotClaimed(crate::ws::ShardInfo),
+    /// An I/O error occured, for example while communicating with a shard coordinator.
+    Io(std::io::Error),
+    /// The TLS connector could not be configured, for example because of an invalid root
+    /// certificate.
+    Tls(native_tls::Error),
+    /// An error ended a gateway connection. This carries the same identifying fields as the log
+    /// records of the connection.
+    Connection {
+        /// The ID of the connection, unique within the process.
+        connection_id: u64,
+        /// The shard of the connection, if the client is sharded.
+        shard: Option<ShardInfo>,
+        /// The ID of the session of the connection, if one was established.
+        session_id: Option<String>,
+        /// The error which ended the connection.
+        source: Box<Error>,
+    },
+}
+
+impl From<native_tls::Error> for Error {
+    fn from(err: native_tls::Error) -> Self {
+        Self::Tls(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
 }
 
 impl From<tokio_tungstenite::tungstenite::Error> for Error {
@@ -1,23 +1,575 @@
-use super::InboundMessage;
-use crate::models::Message;
-use crate::{Context, WithCtx};
+use super::{
+    handler::{run_consumers, Watchdog},
+    Connection, ConsumerVec, InboundMessage,
+};
+use crate::models::{
+    ChannelId, DmChannel, Guild, Member, Message, PartialMessage, ReactionType, Relationship,
+    Relationships, User, UserId,
+};
+use crate::{cache::Replaced, client::ReadyState, Context, WithCtx};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use std::sync::Arc;
+use tokio::time::timeout;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 
 /// Represents a resolved dispatch event received from the gateway.
+///
+/// # Ordering
+/// Events from a single connection are dispatched in the order they were received: every consumer
+/// finishes handling an event before any consumer receives the next one. For example, the
+/// creation of a message is always handled before an edit to it. Use [`Event::sequence`] to
+/// order events that were collected elsewhere, for example through
+/// [`Context::wait_for`].
+///
+/// This only holds for all events with the default
+/// [`DispatchExecutor`](super::handler::DispatchExecutor). The
+/// [spawn](super::handler::DispatchExecutor::spawn) and
+/// [worker pool](super::handler::DispatchExecutor::worker_pool) executors handle events
+/// concurrently, but still handle the message and channel events of each channel in order.
+/// Events are passed to [`Context::wait_for`] as soon as they are received, before the handlers of
+/// earlier events finish, so a handler can wait for a later event in its own channel.
+///
+/// # Serialization
+/// Events serialize into an object with the [name](Event::name) of the event as `type`, the
+/// [sequence number](Event::sequence) as `sequence`, and the payload as `data`, so that they can be
+/// forwarded to logs or analytics systems. Contexts are not serialized.
 #[non_exhaustive]
 #[derive(Clone, Debug)]
 pub enum Event {
-    /// The client is ready to receive events.
-    Ready(Context),
+    /// The client is ready to receive events. Includes the initial state sent by the gateway.
+    Ready(Context, ReadyEvent),
     /// A resolvable message was sent.
     MessageCreate(WithCtx<Message>),
+    /// A message was edited.
+    MessageUpdate {
+        /// The previous state of the message, if known. This is taken from the message cache if
+        /// the message is still cached.
+        old: Option<Message>,
+        /// The edited message.
+        new: WithCtx<Message>,
+    },
+    /// A message was deleted.
+    MessageDelete {
+        /// The deleted message.
+        message: WithCtx<PartialMessage>,
+        /// The state of the message before it was deleted, if it was still in the message cache.
+        old: Option<Message>,
+    },
+    /// A reaction was added to a message.
+    ReactionAdd {
+        /// The message the reaction was added to.
+        message: WithCtx<PartialMessage>,
+        /// The ID of the user who added the reaction.
+        user_id: UserId,
+        /// The emoji of the reaction.
+        emoji: ReactionType,
+    },
+    /// A reaction was removed from a message.
+    ReactionRemove {
+        /// The message the reaction was removed from.
+        message: WithCtx<PartialMessage>,
+        /// The ID of the user whose reaction was removed.
+        user_id: UserId,
+        /// The emoji of the reaction.
+        emoji: ReactionType,
+    },
+    /// A user was updated, for example when they change their username or avatar.
+    UserUpdate {
+        /// The previous state of the user.
+        old: User,
+        /// The updated user.
+        new: WithCtx<User>,
+    },
+    /// A DM or group DM channel was created, or the client user was added to a group DM channel.
+    DmChannelCreate(WithCtx<DmChannel>),
+    /// A guild member was updated.
+    MemberUpdate {
+        /// The previous state of the member, if known.
+        old: Option<Member>,
+        /// The updated member. See [`Member::diff`] to compute what changed.
+        new: WithCtx<Member>,
+    },
+    /// The gateway closed the connection. The client reconnects automatically afterwards.
+    Disconnected(Context, Option<CloseInfo>),
 }
 
-pub fn populate(ctx: Context, event: InboundMessage, pending: &mut Vec<Event>) {
+/// The close code and reason sent by the gateway when it closed the connection.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct CloseInfo {
+    /// The close code of the connection.
+    pub code: u16,
+    /// The reason the connection was closed. This may be empty.
+    pub reason: String,
+}
+
+impl From<CloseFrame<'_>> for CloseInfo {
+    fn from(frame: CloseFrame<'_>) -> Self {
+        Self {
+            code: frame.code.into(),
+            reason: frame.reason.into_owned(),
+        }
+    }
+}
+
+/// The initial state of the client user, sent by the gateway once the client is ready to receive
+/// events.
+#[derive(Clone, Debug, Serialize)]
+#[non_exhaustive]
+pub struct ReadyEvent {
+    /// The ID of the session. This can be used to resume the session after disconnecting.
+    pub session_id: String,
+    /// The user the client is logged in as.
+    pub user: WithCtx<User>,
+    /// The guilds the client user is a member of.
+    pub guilds: Vec<WithCtx<Guild>>,
+    /// The relationships of the client user.
+    pub relationships: Relationships,
+    /// The DM and group DM channels the client user is a recipient of.
+    pub dm_channels: Vec<WithCtx<DmChannel>>,
+}
+
+impl Event {
+    /// Returns the name of the event, for example `message_create`.
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Ready(..) => "ready",
+            Self::MessageCreate(_) => "message_create",
+            Self::MessageUpdate { .. } => "message_update",
+            Self::MessageDelete { .. } => "message_delete",
+            Self::ReactionAdd { .. } => "reaction_add",
+            Self::ReactionRemove { .. } => "reaction_remove",
+            Self::UserUpdate { .. } => "user_update",
+            Self::DmChannelCreate(_) => "dm_channel_create",
+            Self::MemberUpdate { .. } => "member_update",
+            Self::Disconnected(..) => "disconnected",
+        }
+    }
+
+    /// Returns the context the event was dispatched with.
+    #[must_use]
+    pub fn context(&self) -> &Context {
+        match self {
+            Self::Ready(ctx, _) | Self::Disconnected(ctx, _) => ctx,
+            Self::MessageCreate(message) => &message.ctx,
+            Self::MessageDelete { message, .. }
+            | Self::ReactionAdd { message, .. }
+            | Self::ReactionRemove { message, .. } => &message.ctx,
+            Self::MessageUpdate { new, .. } => &new.ctx,
+            Self::UserUpdate { new, .. } => &new.ctx,
+            Self::DmChannelCreate(channel) => &channel.ctx,
+            Self::MemberUpdate { new, .. } => &new.ctx,
+        }
+    }
+
+    /// Returns the sequence number of the gateway event this event was resolved from. See
+    /// [`Context::sequence`].
+    #[must_use]
+    pub fn sequence(&self) -> Option<u64> {
+        self.context().sequence()
+    }
+}
+
+impl Serialize for Event {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Update<'a, Old, New> {
+            old: &'a Old,
+            new: &'a New,
+        }
+
+        #[derive(Serialize)]
+        struct Delete<'a, T> {
+            message: &'a T,
+            old: &'a Option<Message>,
+        }
+
+        #[derive(Serialize)]
+        struct Reaction<'a> {
+            message: &'a WithCtx<PartialMessage>,
+            user_id: UserId,
+            emoji: &'a ReactionType,
+        }
+
+        let mut state = serializer.serialize_struct("Event", 3)?;
+        state.serialize_field("type", self.name())?;
+        state.serialize_field("sequence", &self.sequence())?;
+        match self {
+            Self::Ready(_, ready) => state.serialize_field("data", ready)?,
+            Self::MessageCreate(message) => state.serialize_field("data", message)?,
+            Self::MessageUpdate { old, new } => {
+                state.serialize_field("data", &Update { old, new })?;
+            }
+            Self::MessageDelete { message, old } => {
+                state.serialize_field("data", &Delete { message, old })?;
+            }
+            Self::ReactionAdd {
+                message,
+                user_id,
+                emoji,
+            }
+            | Self::ReactionRemove {
+                message,
+                user_id,
+                emoji,
+            } => {
+                let user_id = *user_id;
+                state.serialize_field(
+                    "data",
+                    &Reaction {
+                        message,
+                        user_id,
+                        emoji,
+                    },
+                )?;
+            }
+            Self::UserUpdate { old, new } => {
+                state.serialize_field("data", &Update { old, new })?;
+            }
+            Self::DmChannelCreate(channel) => state.serialize_field("data", channel)?,
+            Self::MemberUpdate { old, new } => {
+                state.serialize_field("data", &Update { old, new })?;
+            }
+            Self::Disconnected(_, close) => state.serialize_field("data", close)?,
+        }
+        state.end()
+    }
+}
+
+/// Resolves the given raw event into `pending`. `replaced` is the cached state the event replaced,
+/// as returned by [`Cache::apply`](crate::cache::Cache::apply).
+pub fn populate(ctx: Context, event: InboundMessage, replaced: Replaced, pending: &mut Vec<Event>) {
     match event {
-        InboundMessage::Ready { .. } => pending.push(Event::Ready(ctx)),
-        InboundMessage::MessageCreate { message, .. } => {
-            pending.push(Event::MessageCreate(ctx.with(Message::from_raw(message))));
+        InboundMessage::Ready {
+            session_id,
+            user,
+            guilds,
+            dm_channels,
+            relationships,
+            ..
+        } => {
+            let ready =
+                ReadyEvent {
+                    session_id,
+                    user: ctx.clone().with(User::from_raw(user.user)),
+                    guilds: guilds
+                        .into_iter()
+                        .map(|guild| ctx.clone().with(Guild::from_raw(guild)))
+                        .collect(),
+                    relationships: Relationships::new(relationships.into_iter().map(
+                        |relationship| ctx.clone().with(Relationship::from_raw(relationship)),
+                    )),
+                    dm_channels: dm_channels
+                        .into_iter()
+                        .map(|channel| ctx.clone().with(DmChannel::from_raw(channel)))
+                        .collect(),
+                };
+            pending.push(Event::Ready(ctx, ready));
+        }
+        InboundMessage::MessageCreate { message, nonce } => {
+            let mut message = Message::from_raw(message);
+            message.nonce = nonce;
+            pending.push(Event::MessageCreate(ctx.with(message)));
+        }
+        InboundMessage::MessageUpdate { before, after } => {
+            let old = match replaced {
+                Replaced::Message(old) => old,
+                _ => Message::from_raw(before),
+            };
+            pending.push(Event::MessageUpdate {
+                old: Some(old),
+                new: ctx.with(Message::from_raw(after)),
+            });
+        }
+        InboundMessage::MessageDelete {
+            message_id,
+            channel_id,
+        } => {
+            let old = match replaced {
+                Replaced::Message(old) => Some(old),
+                _ => None,
+            };
+            pending.push(Event::MessageDelete {
+                message: ctx.with(PartialMessage::new(channel_id.into(), message_id.into())),
+                old,
+            });
+        }
+        InboundMessage::MessageReactionAdd {
+            message_id,
+            channel_id,
+            user_id,
+            emoji,
+        } => {
+            pending.push(Event::ReactionAdd {
+                message: ctx.with(PartialMessage::new(channel_id.into(), message_id.into())),
+                user_id: user_id.into(),
+                emoji: emoji.into(),
+            });
+        }
+        InboundMessage::MessageReactionRemove {
+            message_id,
+            channel_id,
+            user_id,
+            emoji,
+        } => {
+            pending.push(Event::ReactionRemove {
+                message: ctx.with(PartialMessage::new(channel_id.into(), message_id.into())),
+                user_id: user_id.into(),
+                emoji: emoji.into(),
+            });
+        }
+        InboundMessage::UserUpdate { before, after } => {
+            pending.push(Event::UserUpdate {
+                old: User::from_raw(before),
+                new: ctx.with(User::from_raw(after)),
+            });
+        }
+        InboundMessage::ChannelCreate {
+            channel: essence::models::Channel::Dm(channel),
+            ..
+        } => {
+            pending.push(Event::DmChannelCreate(
+                ctx.with(DmChannel::from_raw(channel)),
+            ));
+        }
+        InboundMessage::MemberUpdate { before, after } => {
+            pending.push(Event::MemberUpdate {
+                old: Some(Member::from_raw(before)),
+                new: ctx.with(Member::from_raw(after)),
+            });
         }
         _ => (),
     }
 }
+
+/// Returns the channel the given raw event belongs to, if any. Events in the same channel are
+/// dispatched in order by every executor.
+pub(super) fn channel_of(event: &InboundMessage) -> Option<ChannelId> {
+    let channel_id = match event {
+        InboundMessage::MessageCreate { message, .. } => message.channel_id,
+        InboundMessage::MessageUpdate { after, .. } => after.channel_id,
+        InboundMessage::MessageDelete { channel_id, .. }
+        | InboundMessage::MessageReactionAdd { channel_id, .. }
+        | InboundMessage::MessageReactionRemove { channel_id, .. }
+        | InboundMessage::ChannelDelete { channel_id }
+        | InboundMessage::ChannelAck { channel_id, .. } => *channel_id,
+        _ => return None,
+    };
+    Some(channel_id.into())
+}
+
+/// Resolves the given raw event and broadcasts the resulting events to everything waiting for
+/// events, returning them to be passed to [`run_dispatch`].
+///
+/// This runs on the task maintaining the connection rather than in the dispatch executor, so that
+/// handlers waiting for an event are woken even while earlier events are still being handled.
+pub(super) fn resolve(ctx: &Context, message: InboundMessage, replaced: Replaced) -> Vec<Event> {
+    if matches!(message, InboundMessage::Ready { .. }) {
+        ctx.ready.send_replace(ReadyState::Ready);
+    }
+
+    let mut events = Vec::with_capacity(4);
+    populate(ctx.clone(), message, replaced, &mut events);
+    broadcast(ctx, &events);
+    events
+}
+
+fn broadcast(ctx: &Context, events: &[Event]) {
+    for event in events {
+        // Only fails if nothing is waiting for events
+        let _ = ctx.events.send(event.clone());
+    }
+}
+
+/// Resolves the given raw event and dispatches the resulting events to all consumers.
+///
+/// Returns `false` if the events were dropped because the consumers could not be acquired in time.
+pub(crate) async fn dispatch(
+    ctx: &Context,
+    consumers: &ConsumerVec,
+    message: InboundMessage,
+    replaced: Replaced,
+    watchdog: Option<&Watchdog>,
+) -> bool {
+    let events = resolve(ctx, message, replaced);
+    run_dispatch(consumers, events, watchdog).await
+}
+
+/// Dispatches the given resolved events to all consumers.
+///
+/// Returns `false` if the events were dropped because the consumers could not be acquired in time.
+pub(super) async fn dispatch_events(
+    ctx: &Context,
+    consumers: &ConsumerVec,
+    events: Vec<Event>,
+    watchdog: Option<&Watchdog>,
+) -> bool {
+    broadcast(ctx, &events);
+    run_dispatch(consumers, events, watchdog).await
+}
+
+/// Runs all consumers for the given events, which have already been broadcast by [`resolve`].
+///
+/// Returns `false` if the events were dropped because the consumers could not be acquired in time.
+pub(super) async fn run_dispatch(
+    consumers: &ConsumerVec,
+    events: Vec<Event>,
+    watchdog: Option<&Watchdog>,
+) -> bool {
+    if !events.is_empty() {
+        debug!("Attempting to dispatch event");
+        let consumers = timeout(Connection::ACQUIRE_TIMEOUT, consumers.lock()).await;
+        // Release the lock before running any consumer so that other dispatch tasks are not
+        // blocked by slow handlers
+        if let Ok(consumers) = consumers.map(|consumers| Arc::clone(&consumers)) {
+            for event in events {
+                run_consumers(&consumers, &event, watchdog).await;
+            }
+        } else {
+            warn!("Could not acquire lock to dispatch event");
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Http;
+    use crate::ws::handler::{self, ConsumerOptions, DispatchExecutor, RegisteredConsumer};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::sync::{mpsc, Barrier, Mutex};
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_consumers_do_not_block_dispatch() {
+        let ctx = Context::new(Arc::new(Http::from_token("")));
+        let barrier = Arc::new(Barrier::new(2));
+        let handled = Arc::new(AtomicUsize::new(0));
+
+        let consumer = handler::from_fn({
+            let (barrier, handled) = (barrier.clone(), handled.clone());
+            move |_| {
+                let (barrier, handled) = (barrier.clone(), handled.clone());
+                async move {
+                    // Only passes once both events are being handled at the same time
+                    barrier.wait().await;
+                    tokio::time::sleep(Connection::ACQUIRE_TIMEOUT * 4).await;
+                    handled.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        });
+        let consumers: ConsumerVec = Arc::new(Mutex::new(Arc::new([RegisteredConsumer {
+            consumer: Arc::new(consumer),
+            options: ConsumerOptions::new(),
+        }])));
+
+        let executor = DispatchExecutor::spawn();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        for _ in 0..2 {
+            let (ctx, consumers, tx) = (ctx.clone(), consumers.clone(), tx.clone());
+            executor
+                .execute(
+                    None,
+                    Box::pin(async move {
+                        let events = vec![Event::Disconnected(ctx.clone(), None)];
+                        tx.send(dispatch_events(&ctx, &consumers, events, None).await)
+                            .unwrap();
+                    }),
+                )
+                .await;
+        }
+
+        let dispatched = timeout(Duration::from_secs(60), async {
+            [rx.recv().await.unwrap(), rx.recv().await.unwrap()]
+        })
+        .await
+        .expect("events were handled one at a time");
+        assert_eq!(dispatched, [true, true]);
+        assert_eq!(handled.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn handler_waits_for_next_event_in_its_channel() {
+        let ctx = Context::new(Arc::new(Http::from_token("")));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let consumer = handler::from_fn(move |event| {
+            let tx = tx.clone();
+            async move {
+                let Event::MessageCreate(message) = event else {
+                    return;
+                };
+                if message.content == "question" {
+                    let reply = message
+                        .ctx
+                        .wait_for(
+                            |event| matches!(event, Event::MessageCreate(m) if m.content == "yes"),
+                            Duration::from_secs(5),
+                        )
+                        .await;
+                    tx.send(reply.is_ok_and(|reply| reply.is_some())).unwrap();
+                }
+            }
+        });
+        let consumers: ConsumerVec = Arc::new(Mutex::new(Arc::new([RegisteredConsumer {
+            consumer: Arc::new(consumer),
+            options: ConsumerOptions::new(),
+        }])));
+
+        // Mirrors how the connection dispatches each event it receives
+        let executor = DispatchExecutor::spawn();
+        for (id, content) in [(1, "question"), (2, "yes")] {
+            let message = InboundMessage::MessageCreate {
+                message: essence::models::Message {
+                    id,
+                    channel_id: 1,
+                    author_id: None,
+                    author: None,
+                    content: Some(content.to_string()),
+                    embeds: Vec::new(),
+                    attachments: Vec::new(),
+                    flags: 0,
+                    mentions: Vec::new(),
+                    edited_at: None,
+                },
+                nonce: None,
+            };
+            let channel_id = channel_of(&message);
+            let events = resolve(&ctx, message, Replaced::None);
+            let consumers = consumers.clone();
+            executor
+                .execute(
+                    channel_id,
+                    Box::pin(async move {
+                        run_dispatch(&consumers, events, None).await;
+                    }),
+                )
+                .await;
+            // Let the handler start waiting before the next event is received
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert!(rx.recv().await.unwrap(), "the reply was not received");
+    }
+
+    #[test]
+    fn serialize_event() {
+        let ctx = Context::new(Arc::new(Http::from_token("")));
+        let close = CloseInfo {
+            code: 1000,
+            reason: String::new(),
+        };
+        let event = serde_json::to_value(Event::Disconnected(ctx, Some(close))).unwrap();
+
+        assert_eq!(
+            event,
+            serde_json::json!({
+                "type": "disconnected",
+                "sequence": null,
+                "data": { "code": 1000, "reason": "" },
+            })
+        );
+    }
+}
@@ -0,0 +1,70 @@
+//! Marker types for each kind of [`Event`], used to register closures for a single event type
+//! with [`Client::on`](crate::Client::on).
+
+use super::{CloseInfo, Event, ReadyEvent};
+use crate::models::{DmChannel, Member, Message, PartialMessage, ReactionType, User, UserId};
+use crate::{Context, WithCtx};
+
+/// A kind of [`Event`] which can be extracted from an event.
+pub trait EventKind: Send + Sync + 'static {
+    /// The data carried by events of this kind.
+    type Payload: Send;
+
+    /// Extracts the payload of the event if it is of this kind.
+    fn extract(event: Event) -> Option<Self::Payload>;
+}
+
+macro_rules! event_kinds {
+    ($(
+        $(#[$doc:meta])*
+        $name:ident($payload:ty) = $pat:pat => $value:expr;
+    )*) => {
+        $(
+            $(#[$doc])*
+            #[derive(Copy, Clone, Debug)]
+            pub struct $name;
+
+            impl EventKind for $name {
+                type Payload = $payload;
+
+                fn extract(event: Event) -> Option<Self::Payload> {
+                    #[allow(unreachable_patterns)]
+                    match event {
+                        $pat => Some($value),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+event_kinds! {
+    /// The client is ready to receive events. The payload is the context of the client and the
+    /// initial state sent by the gateway.
+    Ready((Context, ReadyEvent)) = Event::Ready(ctx, ready) => (ctx, ready);
+    /// A message was sent.
+    MessageCreate(WithCtx<Message>) = Event::MessageCreate(message) => message;
+    /// A message was edited. The payload is the previous state of the message, if known, and the
+    /// edited message.
+    MessageUpdate((Option<Message>, WithCtx<Message>)) = Event::MessageUpdate { old, new } => (old, new);
+    /// A message was deleted. The payload is the deleted message and its state before it was
+    /// deleted, if it was cached.
+    MessageDelete((WithCtx<PartialMessage>, Option<Message>)) = Event::MessageDelete { message, old } => (message, old);
+    /// A reaction was added to a message. The payload is the message, the ID of the user who
+    /// reacted, and the emoji of the reaction.
+    ReactionAdd((WithCtx<PartialMessage>, UserId, ReactionType)) = Event::ReactionAdd { message, user_id, emoji } => (message, user_id, emoji);
+    /// A reaction was removed from a message. The payload is the message, the ID of the user whose
+    /// reaction was removed, and the emoji of the reaction.
+    ReactionRemove((WithCtx<PartialMessage>, UserId, ReactionType)) = Event::ReactionRemove { message, user_id, emoji } => (message, user_id, emoji);
+    /// A user was updated. The payload is the previous and updated state of the user.
+    UserUpdate((User, WithCtx<User>)) = Event::UserUpdate { old, new } => (old, new);
+    /// A DM or group DM channel was created.
+    DmChannelCreate(WithCtx<DmChannel>) = Event::DmChannelCreate(channel) => channel;
+    /// A guild member was updated. The payload is the previous state of the member, if known, and
+    /// the updated member.
+    MemberUpdate((Option<Member>, WithCtx<Member>)) = Event::MemberUpdate { old, new } => (old, new);
+    /// The gateway closed the connection. The payload is the context of the client and the close
+    /// code and reason, if any.
+    Disconnected((Context, Option<CloseInfo>)) = Event::Disconnected(ctx, close) => (ctx, close);
+}
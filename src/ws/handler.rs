@@ -1,8 +1,21 @@
-use futures_util::future::BoxFuture;
+use futures_util::future::{BoxFuture, JoinAll};
+use std::collections::HashMap;
+use std::fmt::Debug;
 use std::future::{Future, IntoFuture};
+use std::marker::PhantomData;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+use std::time::Duration;
+use tokio::sync::{oneshot, Semaphore};
+use tokio::time::{timeout, Instant};
 
-use super::Event;
-use crate::{models::Message, Context, WithCtx};
+use super::{events::EventKind, CloseInfo, Event, ReadyEvent};
+use crate::{
+    models::{ChannelId, DmChannel, Member, Message, PartialMessage, ReactionType, User, UserId},
+    Context, WithCtx,
+};
 
 /// Represents a generic event consumer for gateway dispatch events.
 pub trait EventConsumer: Send + Sync {
@@ -22,6 +35,37 @@ where
     }
 }
 
+struct StatefulFnConsumer<S, F>(Arc<S>, F);
+
+impl<S, F, Fut: IntoFuture> EventConsumer for StatefulFnConsumer<S, F>
+where
+    S: Send + Sync,
+    F: Fn(Arc<S>, Event) -> Fut + Send + Sync,
+    Fut::IntoFuture: Send,
+{
+    async fn handle_event(&self, event: Event) {
+        (self.1)(self.0.clone(), event).await;
+    }
+}
+
+struct TryFnConsumer<F, H>(F, H);
+
+impl<F, H, Fut: IntoFuture<Output = Result<(), E>>, HFut: IntoFuture, E> EventConsumer
+    for TryFnConsumer<F, H>
+where
+    F: Fn(Event) -> Fut + Send + Sync,
+    H: Fn(E) -> HFut + Send + Sync,
+    Fut::IntoFuture: Send,
+    HFut::IntoFuture: Send,
+    E: Send,
+{
+    async fn handle_event(&self, event: Event) {
+        if let Err(why) = (self.0)(event).await {
+            (self.1)(why).await;
+        }
+    }
+}
+
 macro_rules! all_the_tuples {
     ($name:ident) => {
         $name!(T1, T2);
@@ -59,12 +103,409 @@ all_the_tuples!(impl_compound_handlers);
 
 pub(crate) trait EventConsumerErased: Send + Sync {
     fn dyn_handle_event(&self, event: Event) -> BoxFuture<()>;
+
+    /// The type name of the consumer, used to identify it in reports.
+    fn type_name(&self) -> &'static str;
 }
 
 impl<T: EventConsumer> EventConsumerErased for T {
     fn dyn_handle_event(&self, event: Event) -> BoxFuture<()> {
         Box::pin(EventConsumer::handle_event(self, event))
     }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+}
+
+/// Options for how an event consumer is run relative to other consumers, given when registering
+/// the consumer with [`Client::add_handler_with`](crate::Client::add_handler_with).
+///
+/// Consumers are run in descending order of priority, and consumers with equal priority are run in
+/// the order they were registered. By default, consumers have a priority of `0` and run in
+/// parallel with adjacent parallel consumers of the same priority. Sequential consumers run to
+/// completion before any consumer after them is started.
+///
+/// # Example
+/// ```no_run
+/// use adapt::ws::handler::{self, ConsumerOptions};
+///
+/// # fn run(client: adapt::Client) {
+/// // Runs before, and finishes before, any other consumer
+/// client.add_handler_with(
+///     handler::from_fn(|event| async move { /* update state */ }),
+///     ConsumerOptions::new().priority(100).sequential(),
+/// );
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[must_use]
+pub struct ConsumerOptions {
+    priority: i32,
+    sequential: bool,
+}
+
+impl ConsumerOptions {
+    /// Creates new consumer options with the default priority of `0` and parallel execution.
+    pub const fn new() -> Self {
+        Self {
+            priority: 0,
+            sequential: false,
+        }
+    }
+
+    /// Sets the priority of the consumer. Consumers with a higher priority are run first.
+    pub const fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Runs the consumer to completion before any consumer after it is started.
+    pub const fn sequential(mut self) -> Self {
+        self.sequential = true;
+        self
+    }
+
+    /// Runs the consumer in parallel with adjacent parallel consumers of the same priority. This is
+    /// the default.
+    pub const fn parallel(mut self) -> Self {
+        self.sequential = false;
+        self
+    }
+}
+
+/// An event consumer registered along with its options.
+#[derive(Clone)]
+pub(crate) struct RegisteredConsumer {
+    pub consumer: Arc<dyn EventConsumerErased>,
+    pub options: ConsumerOptions,
+}
+
+impl RegisteredConsumer {
+    /// Inserts the consumer into the list, keeping it ordered by descending priority and
+    /// registration order.
+    pub fn insert_into(self, consumers: &mut Vec<Self>) {
+        let index = consumers
+            .iter()
+            .position(|other| other.options.priority < self.options.priority)
+            .unwrap_or(consumers.len());
+        consumers.insert(index, self);
+    }
+}
+
+/// A handler that has been handling an event for longer than the [`Watchdog`] threshold.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct SlowHandler {
+    /// The type name of the event consumer, for example the type implementing [`EventHandler`].
+    pub handler: &'static str,
+    /// The name of the event that is being handled, for example `message_create`.
+    pub event: &'static str,
+    /// The sequence number of the event that is being handled, if known.
+    pub sequence: Option<u64>,
+    /// How long the handler had been handling the event when it was reported.
+    pub elapsed: Duration,
+}
+
+/// Detects event consumers that take longer than a threshold to handle an event.
+///
+/// A handler is reported once its threshold passes while it is still running, so handlers which
+/// never finish are reported as well. Slow handlers are always logged as warnings, and are
+/// additionally reported to the hook given by [`Self::on_slow_handler`], if any.
+///
+/// # Example
+/// ```no_run
+/// use adapt::ws::{handler::Watchdog, ConnectOptions};
+/// use std::time::Duration;
+///
+/// let options = ConnectOptions::new("token").watchdog(
+///     Watchdog::new(Duration::from_secs(1)).on_slow_handler(|slow| {
+///         eprintln!("{} is still handling {} after {:?}", slow.handler, slow.event, slow.elapsed);
+///     }),
+/// );
+/// ```
+#[derive(Clone)]
+#[must_use]
+pub struct Watchdog {
+    threshold: Duration,
+    hook: Option<SlowHandlerHook>,
+}
+
+type SlowHandlerHook = Arc<dyn Fn(&SlowHandler) + Send + Sync>;
+
+impl Watchdog {
+    /// Creates a new watchdog which reports handlers taking longer than the given threshold.
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            hook: None,
+        }
+    }
+
+    /// Sets the hook called whenever a slow handler is detected.
+    pub fn on_slow_handler(mut self, hook: impl Fn(&SlowHandler) + Send + Sync + 'static) -> Self {
+        self.hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Returns the threshold after which a handler is considered slow.
+    #[must_use]
+    pub const fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    async fn watch(&self, handler: &'static str, event: &Event, mut future: BoxFuture<'_, ()>) {
+        let start = Instant::now();
+        if timeout(self.threshold, &mut future).await.is_ok() {
+            return;
+        }
+
+        let slow = SlowHandler {
+            handler,
+            event: event.name(),
+            sequence: event.sequence(),
+            elapsed: start.elapsed(),
+        };
+        warn!(
+            "Handler {handler} is still handling {} event (sequence {:?}) after {:?}",
+            slow.event, slow.sequence, slow.elapsed,
+        );
+        if let Some(hook) = &self.hook {
+            hook(&slow);
+        }
+
+        future.await;
+        warn!(
+            "Handler {handler} finished handling {} event after {:?}",
+            slow.event,
+            start.elapsed(),
+        );
+    }
+}
+
+impl Debug for Watchdog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Watchdog")
+            .field("threshold", &self.threshold)
+            .finish_non_exhaustive()
+    }
+}
+
+tokio::task_local! {
+    /// Set while consumers run on the task maintaining the connection.
+    static INLINE_DISPATCH: ();
+}
+
+/// Returns whether the current task is running consumers for the [inline](DispatchExecutor::inline)
+/// executor, in which case no further events are dispatched until it finishes.
+pub(crate) fn is_dispatching_inline() -> bool {
+    INLINE_DISPATCH.try_with(|()| ()).is_ok()
+}
+
+/// Runs the dispatches for each channel one after another, in the order they were submitted,
+/// while dispatches for different channels may run concurrently.
+#[derive(Default)]
+struct ChannelLanes {
+    /// The last submitted dispatch of every channel with a dispatch in progress, along with a
+    /// receiver which resolves once that dispatch finishes.
+    tails: Mutex<HashMap<ChannelId, (u64, oneshot::Receiver<()>)>>,
+    next_id: AtomicU64,
+}
+
+impl ChannelLanes {
+    /// Wraps the given dispatch so that it only starts once the previous dispatch for the same
+    /// channel has finished. Must be called in the order events are received.
+    fn sequence(
+        self: &Arc<Self>,
+        channel_id: Option<ChannelId>,
+        future: BoxFuture<'static, ()>,
+    ) -> BoxFuture<'static, ()> {
+        let Some(channel_id) = channel_id else {
+            return future;
+        };
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        // Dropping the sender, including when the dispatch panics, releases the next dispatch
+        let (done, rx) = oneshot::channel::<()>();
+        let previous = self
+            .tails
+            .lock()
+            .expect("poison")
+            .insert(channel_id, (id, rx));
+
+        let lanes = Arc::clone(self);
+        Box::pin(async move {
+            if let Some((_, previous)) = previous {
+                let _ = previous.await;
+            }
+            future.await;
+
+            let mut tails = lanes.tails.lock().expect("poison");
+            // Remove the lane unless another dispatch for the channel is already queued
+            if tails.get(&channel_id).is_some_and(|(tail, _)| *tail == id) {
+                tails.remove(&channel_id);
+            }
+            drop(tails);
+            drop(done);
+        })
+    }
+}
+
+#[derive(Clone)]
+enum ExecutorKind {
+    Inline,
+    Spawn(Arc<ChannelLanes>),
+    WorkerPool(Arc<Semaphore>, Arc<ChannelLanes>),
+    Custom(
+        Arc<dyn Fn(BoxFuture<'static, ()>) + Send + Sync>,
+        Arc<ChannelLanes>,
+    ),
+}
+
+/// Determines where event consumers run when the gateway dispatches an event. Set with
+/// [`ConnectOptions::dispatch_executor`](super::ConnectOptions::dispatch_executor).
+///
+/// By default, events are dispatched [inline](Self::inline), which guarantees that events are
+/// handled in the order they were received.
+///
+/// # Example
+/// ```no_run
+/// use adapt::ws::{handler::DispatchExecutor, ConnectOptions};
+///
+/// // Handle up to 8 events at a time
+/// let options = ConnectOptions::new("token").dispatch_executor(DispatchExecutor::worker_pool(8));
+/// ```
+#[derive(Clone)]
+#[must_use]
+pub struct DispatchExecutor(ExecutorKind);
+
+impl DispatchExecutor {
+    /// Runs consumers on the task maintaining the connection, which waits for every event to be
+    /// handled before receiving the next one. This preserves the order of events, but slow
+    /// handlers delay heartbeats. This is the default.
+    ///
+    /// Since no other event is dispatched while a handler runs, handlers cannot wait for events
+    /// with [`Context::wait_for`](crate::Context::wait_for); spawn a task to do so instead.
+    pub const fn inline() -> Self {
+        Self(ExecutorKind::Inline)
+    }
+
+    /// Spawns a new Tokio task for every event. Events are handled concurrently without bound,
+    /// except that events in the same channel are handled one after another, in the order they
+    /// were received. Events which do not belong to a channel may be handled out of order.
+    pub fn spawn() -> Self {
+        Self(ExecutorKind::Spawn(Arc::default()))
+    }
+
+    /// Spawns a new Tokio task for every event, with at most `workers` events being handled at a
+    /// time. Once all workers are busy, the connection waits for one to become free before
+    /// receiving the next event. With a single worker, events are handled in order.
+    ///
+    /// As with [`Self::spawn`], events in the same channel are handled in the order they were
+    /// received. An event waiting for an earlier event in its channel occupies a worker meanwhile.
+    ///
+    /// Handlers waiting for events with [`Context::wait_for`](crate::Context::wait_for) occupy a
+    /// worker meanwhile, but still receive the awaited events while every worker is busy.
+    ///
+    /// # Panics
+    /// * If `workers` is zero.
+    pub fn worker_pool(workers: usize) -> Self {
+        assert!(workers > 0, "a worker pool needs at least one worker");
+        Self(ExecutorKind::WorkerPool(
+            Arc::new(Semaphore::new(workers)),
+            Arc::default(),
+        ))
+    }
+
+    /// Passes the future handling every event to the given function, which is responsible for
+    /// running it, for example on a dedicated runtime.
+    ///
+    /// As with [`Self::spawn`], the future of an event in a channel only starts handling it once
+    /// the earlier events in that channel have been handled, so futures may wait for each other.
+    pub fn custom(f: impl Fn(BoxFuture<'static, ()>) + Send + Sync + 'static) -> Self {
+        Self(ExecutorKind::Custom(Arc::new(f), Arc::default()))
+    }
+
+    /// Runs the given dispatch future according to this executor. `channel_id` is the channel the
+    /// dispatched event belongs to, if any, which orders it after earlier events in that channel.
+    pub(crate) async fn execute(
+        &self,
+        channel_id: Option<ChannelId>,
+        future: BoxFuture<'static, ()>,
+    ) {
+        match &self.0 {
+            ExecutorKind::Inline => INLINE_DISPATCH.scope((), future).await,
+            ExecutorKind::Spawn(lanes) => drop(tokio::spawn(lanes.sequence(channel_id, future))),
+            ExecutorKind::WorkerPool(workers, lanes) => {
+                let permit = workers
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .expect("worker pool is never closed");
+                let future = lanes.sequence(channel_id, future);
+                tokio::spawn(async move {
+                    future.await;
+                    drop(permit);
+                });
+            }
+            ExecutorKind::Custom(f, lanes) => f(lanes.sequence(channel_id, future)),
+        }
+    }
+}
+
+impl Default for DispatchExecutor {
+    fn default() -> Self {
+        Self::inline()
+    }
+}
+
+impl Debug for DispatchExecutor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match &self.0 {
+            ExecutorKind::Inline => "Inline",
+            ExecutorKind::Spawn(_) => "Spawn",
+            ExecutorKind::WorkerPool(..) => "WorkerPool",
+            ExecutorKind::Custom(..) => "Custom",
+        };
+        f.debug_tuple("DispatchExecutor").field(&kind).finish()
+    }
+}
+
+/// Runs the given event through all consumers, respecting their priorities and execution modes.
+///
+/// If a watchdog is given, consumers taking longer than its threshold are reported.
+pub(crate) async fn run_consumers(
+    consumers: &[RegisteredConsumer],
+    event: &Event,
+    watchdog: Option<&Watchdog>,
+) {
+    let mut batch = Vec::new();
+    let mut batch_priority = None;
+
+    for registered in consumers {
+        let ConsumerOptions {
+            priority,
+            sequential,
+        } = registered.options;
+
+        if sequential || batch_priority != Some(priority) {
+            batch.drain(..).collect::<JoinAll<_>>().await;
+            batch_priority = None;
+        }
+        let future = registered.consumer.dyn_handle_event(event.clone());
+        let future: BoxFuture<()> = match watchdog {
+            Some(watchdog) => {
+                Box::pin(watchdog.watch(registered.consumer.type_name(), event, future))
+            }
+            None => future,
+        };
+        if sequential {
+            future.await;
+        } else {
+            batch.push(future);
+            batch_priority = Some(priority);
+        }
+    }
+    batch.into_iter().collect::<JoinAll<_>>().await;
 }
 
 /// Creates a raw event consumer from a function.
@@ -89,10 +530,114 @@ where
     FnConsumer(f)
 }
 
+/// An asynchronous callback receiving the payload of an event, implemented for all closures
+/// returning a future. See [`on`].
+pub trait EventCallback<P>: Fn(P) -> <Self as EventCallback<P>>::Future + Send + Sync {
+    /// The future returned by the callback.
+    type Future: IntoFuture<IntoFuture: Send>;
+}
+
+impl<P, F, Fut> EventCallback<P> for F
+where
+    F: Fn(P) -> Fut + Send + Sync,
+    Fut: IntoFuture,
+    Fut::IntoFuture: Send,
+{
+    type Future = Fut;
+}
+
+struct KindFnConsumer<K, F>(F, PhantomData<fn() -> K>);
+
+impl<K: EventKind, F: EventCallback<K::Payload>> EventConsumer for KindFnConsumer<K, F> {
+    async fn handle_event(&self, event: Event) {
+        if let Some(payload) = K::extract(event) {
+            (self.0)(payload).await;
+        }
+    }
+}
+
+/// Creates a raw event consumer from a function which is only called for events of kind `K`.
+///
+/// # Example
+/// ```no_run
+/// use adapt::ws::{events, handler};
+///
+/// let handler = handler::on::<events::MessageCreate>(|message| async move {
+///     println!("Received message: {:?}", message.content);
+/// });
+/// ```
+///
+/// # See Also
+/// * [`Client::on`](crate::Client::on): Registers such a consumer directly.
+pub fn on<K: EventKind>(f: impl EventCallback<K::Payload>) -> impl EventConsumer {
+    KindFnConsumer::<K, _>(f, PhantomData)
+}
+
+/// Creates a raw event consumer from a function which receives shared state along with each event.
+///
+/// # Example
+/// ```no_run
+/// use adapt::ws::handler;
+/// use std::sync::atomic::{AtomicU64, Ordering};
+///
+/// let handler = handler::from_fn_with_state(AtomicU64::new(0), |count, event| async move {
+///     let count = count.fetch_add(1, Ordering::Relaxed) + 1;
+///     println!("Received event #{count}: {event:?}");
+/// });
+/// ```
+///
+/// # See Also
+/// * [`from_fn`]: For functions without state.
+/// * [`EventHandler`]: A trait for organizing event handler logic.
+pub fn from_fn_with_state<S, F, Fut: IntoFuture>(state: S, f: F) -> impl EventConsumer
+where
+    S: Send + Sync,
+    F: Fn(Arc<S>, Event) -> Fut + Send + Sync,
+    Fut::IntoFuture: Send,
+{
+    StatefulFnConsumer(Arc::new(state), f)
+}
+
+/// Creates a raw event consumer from a fallible function. Errors returned by the function are
+/// passed to the `on_error` callback.
+///
+/// # Example
+/// ```no_run
+/// use adapt::ws::{handler, Event};
+///
+/// let handler = handler::from_try_fn(
+///     |event| async move {
+///         if let Event::MessageCreate(message) = event {
+///             message.channel_id().send("Pong!").await?;
+///         }
+///         Ok::<_, adapt::Error>(())
+///     },
+///     |error| async move {
+///         eprintln!("Error while handling event: {error:?}");
+///     },
+/// );
+/// ```
+///
+/// # See Also
+/// * [`from_fn`]: For infallible functions.
+/// * [`FallibleEventHandler`]: A trait for organizing event handler logic with error handling.
+pub fn from_try_fn<F, H, Fut, HFut, E>(f: F, on_error: H) -> impl EventConsumer
+where
+    F: Fn(Event) -> Fut + Send + Sync,
+    H: Fn(E) -> HFut + Send + Sync,
+    Fut: IntoFuture<Output = Result<(), E>>,
+    HFut: IntoFuture,
+    Fut::IntoFuture: Send,
+    HFut::IntoFuture: Send,
+    E: Send,
+{
+    TryFnConsumer(f, on_error)
+}
+
 macro_rules! define_event_handlers {
     ($(
         $(#[$doc:meta])*
-        $pat:pat => $name:ident($($param:ident: $ty:ty),*);
+        $event:ident: $pat:pat => $name:ident($($param:ident: $ty:ty),*);
     )*) => {
         /// Receives and handles events from the gateway such that event handlers can be fallible.
         ///
@@ -161,13 +706,248 @@ macro_rules! define_event_handlers {
                 }
             )*
         }
+
+        /// Expands to the [`EventHandler`] method handling the event with the given name, which is
+        /// either the name of the event or the name of the method, by calling the given function.
+        /// Used by the `event_handler` attribute macro.
+        #[doc(hidden)]
+        #[macro_export]
+        macro_rules! __event_handler_method {
+            (@method $$method:ident ($$($$params:tt)*) $$function:ident ($$($$args:tt)*)) => {
+                async fn $$method(&self, $$($$params)*) {
+                    $$function($$($$args)*).await
+                }
+            };
+            $(
+                ($event $$($$rest:tt)*) => {
+                    $crate::__event_handler_method!(@method $name $$($$rest)*);
+                };
+                ($name $$($$rest:tt)*) => {
+                    $crate::__event_handler_method!(@method $name $$($$rest)*);
+                };
+            )*
+            ($$other:ident $$($$rest:tt)*) => {
+                compile_error!(concat!(
+                    "`",
+                    stringify!($$other),
+                    "` does not match any event or `EventHandler` method",
+                ));
+            };
+        }
     }
 }
 
 define_event_handlers! {
-    /// Called when the client is ready to receive events.
-    Ready(context) => on_ready(context: Context);
+    /// Called when the client is ready to receive events, with the initial state sent by the
+    /// gateway.
+    ready: Ready(context, ready) => on_ready(context: Context, ready: ReadyEvent);
 
     /// Called when a message is sent.
-    MessageCreate(message) => on_message(message: WithCtx<Message>);
+    message_create: MessageCreate(message) => on_message(message: WithCtx<Message>);
+
+    /// Called when a message is edited.
+    ///
+    /// `old` is the previous state of the message if it is known. See
+    /// [`CacheSettings::messages`](crate::cache::CacheSettings::messages).
+    message_update: MessageUpdate { old, new } => on_message_update(
+        old: Option<Message>,
+        new: WithCtx<Message>
+    );
+
+    /// Called when a message is deleted, with the state of the message before it was deleted if it
+    /// was still cached.
+    message_delete: MessageDelete { message, old } => on_message_delete(
+        message: WithCtx<PartialMessage>,
+        old: Option<Message>
+    );
+
+    /// Called when a reaction is added to a message.
+    reaction_add: ReactionAdd { message, user_id, emoji } => on_reaction_add(
+        message: WithCtx<PartialMessage>,
+        user_id: UserId,
+        emoji: ReactionType
+    );
+
+    /// Called when a reaction is removed from a message.
+    reaction_remove: ReactionRemove { message, user_id, emoji } => on_reaction_remove(
+        message: WithCtx<PartialMessage>,
+        user_id: UserId,
+        emoji: ReactionType
+    );
+
+    /// Called when a user's profile is updated, for example when they change their username or
+    /// avatar.
+    user_update: UserUpdate { old, new } => on_user_update(old: User, new: WithCtx<User>);
+
+    /// Called when a DM or group DM channel is created, or when the client user is added to a
+    /// group DM channel.
+    dm_channel_create: DmChannelCreate(channel) => on_dm_channel_create(
+        channel: WithCtx<DmChannel>
+    );
+
+    /// Called when a guild member is updated, for example when their roles or nickname change.
+    ///
+    /// `old` is the previous state of the member if it is known. Use [`Member::diff`] to compute
+    /// the roles that were added or removed and whether the nickname changed.
+    member_update: MemberUpdate { old, new } => on_member_update(
+        old: Option<Member>,
+        new: WithCtx<Member>
+    );
+
+    /// Called when the gateway closes the connection, with the close code and reason if the
+    /// gateway sent any. The client reconnects automatically afterwards.
+    disconnected: Disconnected(context, close) => on_disconnect(
+        context: Context,
+        close: Option<CloseInfo>
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Http;
+    use crate::models::Relationships;
+
+    fn ready_event() -> Event {
+        let ctx = Context::new(Arc::new(Http::from_token("")));
+        let user = User {
+            id: 1.into(),
+            username: "test".to_string(),
+            display_name: None,
+            avatar: None,
+            banner: None,
+            bio: None,
+        };
+        let ready = ReadyEvent {
+            session_id: "session".to_string(),
+            user: ctx.clone().with(user),
+            guilds: Vec::new(),
+            relationships: Relationships::default(),
+            dm_channels: Vec::new(),
+        };
+        Event::Ready(ctx, ready)
+    }
+
+    #[tokio::test]
+    async fn consumer_ordering() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut consumers = Vec::new();
+        for (id, priority) in [(0, 0), (1, 10), (2, 0), (3, 10), (4, -5)] {
+            let order = order.clone();
+            RegisteredConsumer {
+                consumer: Arc::new(from_fn(move |_| {
+                    order.lock().unwrap().push(id);
+                    async {}
+                })),
+                options: ConsumerOptions::new().priority(priority).sequential(),
+            }
+            .insert_into(&mut consumers);
+        }
+
+        run_consumers(&consumers, &ready_event(), None).await;
+        assert_eq!(*order.lock().unwrap(), [1, 3, 0, 2, 4]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watchdog() {
+        struct Hung;
+
+        impl EventConsumer for Hung {
+            async fn handle_event(&self, _event: Event) {
+                std::future::pending::<()>().await;
+            }
+        }
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let watchdog = Watchdog::new(Duration::from_millis(20)).on_slow_handler({
+            let reported = reported.clone();
+            move |slow| {
+                let report = (slow.handler, slow.event, slow.elapsed);
+                reported.lock().unwrap().push(report);
+            }
+        });
+
+        let mut consumers = Vec::new();
+        for consumer in [
+            Arc::new(from_fn(|_| async {})) as Arc<dyn EventConsumerErased>,
+            Arc::new(Hung),
+        ] {
+            RegisteredConsumer {
+                consumer,
+                options: ConsumerOptions::new(),
+            }
+            .insert_into(&mut consumers);
+        }
+
+        let run = tokio::spawn(async move {
+            run_consumers(&consumers, &ready_event(), Some(&watchdog)).await;
+        });
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        // Reported while the handler is still running
+        let reported = reported.lock().unwrap().clone();
+        assert_eq!(reported.len(), 1);
+        let (handler, event, elapsed) = reported[0];
+        assert!(handler.ends_with("Hung"));
+        assert_eq!(event, "ready");
+        assert_eq!(elapsed, Duration::from_millis(20));
+        assert!(!run.is_finished());
+        run.abort();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn worker_pool_bounds_concurrency() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let executor = DispatchExecutor::worker_pool(2);
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        for _ in 0..6 {
+            let (running, peak, tx) = (running.clone(), peak.clone(), tx.clone());
+            executor
+                .execute(
+                    None,
+                    Box::pin(async move {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        tx.send(()).unwrap();
+                    }),
+                )
+                .await;
+        }
+        for _ in 0..6 {
+            rx.recv().await.unwrap();
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn spawn_orders_events_per_channel() {
+        let executor = DispatchExecutor::spawn();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        for (channel_id, delay, label) in [(1, 20, "first"), (2, 0, "other"), (1, 0, "second")] {
+            let tx = tx.clone();
+            executor
+                .execute(
+                    Some(ChannelId::from(channel_id)),
+                    Box::pin(async move {
+                        tokio::time::sleep(Duration::from_millis(delay)).await;
+                        tx.send(label).unwrap();
+                    }),
+                )
+                .await;
+        }
+
+        let mut handled = Vec::new();
+        for _ in 0..3 {
+            handled.push(rx.recv().await.unwrap());
+        }
+        // The other channel is not held up, but the second event waits for the first
+        assert_eq!(handled, ["other", "first", "second"]);
+    }
 }
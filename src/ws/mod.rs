@@ -3,25 +3,43 @@
 mod config;
 mod connection;
 pub mod error;
-mod event;
+pub(crate) mod event;
+pub mod events;
 pub mod handler;
+pub mod publish;
+pub mod session;
+pub mod shard;
+pub mod source;
+pub mod stats;
+pub mod tap;
 
+use crate::client::ReadyState;
 use crate::Context;
 use essence::models::{Device, PresenceStatus};
-use handler::EventConsumerErased;
+use handler::{ConsumerOptions, RegisteredConsumer};
+use publish::EventPublisherErased;
 use secrecy::{ExposeSecret, SecretString};
+use serde::Serialize;
+use shard::ShardClaim;
+use stats::StatsRecorder;
 use std::sync::Arc;
 use tokio::sync::{
     mpsc::{channel, Sender},
     Mutex,
 };
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 
 pub use config::{ConnectOptions, IntoHarmonyUrl};
 pub use connection::Connection;
 pub use error::{Error, Result};
 pub use essence::ws::{InboundMessage as OutboundMessage, OutboundMessage as InboundMessage};
-pub use event::Event;
+pub use event::{CloseInfo, Event, ReadyEvent};
 pub use handler::{EventConsumer, EventHandler, FallibleEventHandler};
+pub use publish::{EventPublisher, PublishedEvent};
+pub use session::{FileSessionStore, SessionInfo, SessionStore};
+pub use shard::{ShardCoordinator, ShardInfo};
+pub use source::EventSource;
+pub use stats::{GatewayStats, LatencyStats};
 
 #[derive(Clone)]
 pub(super) struct PartialIdentify {
@@ -41,9 +59,33 @@ impl PartialIdentify {
     }
 }
 
+/// A request to resume a previous session, sent instead of an identify.
+///
+/// This is not part of Harmony's protocol, so it is only sent if
+/// [`ConnectOptions::resume_sessions`] is enabled.
+#[derive(Serialize)]
+#[serde(tag = "event", rename = "resume")]
+pub(super) struct Resume<'a> {
+    token: &'a str,
+    session_id: &'a str,
+    seq: u64,
+}
+
+impl<'a> Resume<'a> {
+    fn new(token: &'a SecretString, session: &'a SessionInfo) -> Self {
+        Self {
+            token: token.expose_secret(),
+            session_id: &session.session_id,
+            seq: session.sequence,
+        }
+    }
+}
+
 pub(crate) enum ClientAction {
     Reconnect,
-    Close,
+    /// Stops the client, either because the connection was closed through its messenger or with
+    /// the error that ended it.
+    Close(Option<Error>),
 }
 
 pub enum ConnectionAction {
@@ -51,25 +93,64 @@ pub enum ConnectionAction {
         status: PresenceStatus,
         custom_status: Option<String>,
     },
-    Close,
+    Reconnect,
+    Close(Option<CloseFrame<'static>>),
+}
+
+/// The latest presence requested through [`Messenger::update_presence`].
+#[derive(Clone)]
+pub(crate) struct Presence {
+    status: PresenceStatus,
+    custom_status: Option<String>,
 }
 
+/// The presence that is restored after reconnecting, shared across connections of a client.
+pub(crate) type PresenceState = Arc<std::sync::Mutex<Option<Presence>>>;
+
 /// A cloneable messenger for interacting with an ongoing connection to the gateway.
+///
+/// # Note
+/// The gateway does not support requesting the presences of specific users. Presences of all
+/// users the client shares a guild or relationship with are sent when the client becomes ready and
+/// whenever they change.
 #[derive(Clone)]
-pub struct Messenger(Sender<ConnectionAction>);
+pub struct Messenger {
+    tx: Sender<ConnectionAction>,
+    stats: Arc<StatsRecorder>,
+    presence: PresenceState,
+}
 
 impl Messenger {
     async fn send(&self, action: ConnectionAction) -> Result<()> {
-        self.0.send(action).await?;
+        self.tx.send(action).await?;
         Ok(())
     }
 
+    /// Returns a snapshot of the throughput statistics of the current connection.
+    #[must_use]
+    pub fn stats(&self) -> GatewayStats {
+        self.stats.snapshot()
+    }
+
+    /// Returns a summary of the recent heartbeat latencies of the current connection, for
+    /// diagnosing network degradation.
+    #[must_use]
+    pub fn latency_stats(&self) -> LatencyStats {
+        self.stats.latency_snapshot()
+    }
+
     /// Updates the presence of the client.
+    ///
+    /// The presence is remembered and restored when the client reconnects to the gateway.
     pub async fn update_presence(
         &self,
         status: PresenceStatus,
         custom_status: Option<String>,
     ) -> Result<()> {
+        *self.presence.lock().expect("poison") = Some(Presence {
+            status,
+            custom_status: custom_status.clone(),
+        });
         self.send(ConnectionAction::UpdatePresence {
             status,
             custom_status,
@@ -78,14 +159,35 @@ impl Messenger {
         Ok(())
     }
 
+    /// Closes the current connection to the gateway and establishes a new one, resuming the
+    /// session if a session store is configured.
+    pub async fn reconnect(&self) -> Result<()> {
+        self.send(ConnectionAction::Reconnect).await?;
+        Ok(())
+    }
+
     /// Closes the connection to the gateway.
     pub async fn close(&self) -> Result<()> {
-        self.send(ConnectionAction::Close).await?;
+        self.send(ConnectionAction::Close(None)).await?;
+        Ok(())
+    }
+
+    /// Closes the connection to the gateway with the given close code and reason.
+    pub async fn close_with(&self, code: u16, reason: impl Into<String>) -> Result<()> {
+        let frame = CloseFrame {
+            code: code.into(),
+            reason: reason.into().into(),
+        };
+        self.send(ConnectionAction::Close(Some(frame))).await?;
         Ok(())
     }
 }
 
-pub(super) type ConsumerVec = Arc<Mutex<Vec<Arc<dyn EventConsumerErased>>>>;
+/// The registered event consumers. Dispatching takes a snapshot of the list, so the lock is never
+/// held while consumers run.
+pub(crate) type ConsumerVec = Arc<Mutex<Arc<[RegisteredConsumer]>>>;
+/// The registered event publishers, snapshotted the same way as [`ConsumerVec`].
+pub(super) type PublisherVec = Arc<Mutex<Arc<[Arc<dyn EventPublisherErased>]>>>;
 
 /// A client for interacting with harmony, Adapt's gateway.
 #[derive(Clone)]
@@ -94,6 +196,12 @@ pub struct Client {
     options: ConnectOptions,
     /// Event consumers for incoming events.
     pub(crate) consumers: ConsumerVec,
+    /// Event publishers for incoming events.
+    pub(crate) publishers: PublisherVec,
+    /// The latest presence set during a session, restored when reconnecting.
+    presence: PresenceState,
+    /// The messenger for the current connection, if the client is running.
+    messenger: Arc<std::sync::Mutex<Option<Messenger>>>,
 }
 
 impl Client {
@@ -102,43 +210,144 @@ impl Client {
     pub fn new(options: ConnectOptions) -> Self {
         Self {
             options,
-            consumers: Arc::new(Mutex::new(Vec::new())),
+            consumers: Arc::new(Mutex::new(Arc::new([]))),
+            publishers: Arc::new(Mutex::new(Arc::new([]))),
+            presence: PresenceState::default(),
+            messenger: Arc::default(),
         }
     }
 
     /// Registers an event consumer to receive incoming events.
     pub fn add_consumer(&self, consumer: impl EventConsumer + 'static) {
-        self.consumers
-            .try_lock()
-            .expect("poison")
-            .push(Arc::new(consumer));
+        self.add_consumer_with(consumer, ConsumerOptions::default());
+    }
+
+    /// Registers an event consumer to receive incoming events, with the given options determining
+    /// its order of execution relative to other consumers.
+    pub fn add_consumer_with(
+        &self,
+        consumer: impl EventConsumer + 'static,
+        options: ConsumerOptions,
+    ) {
+        let registered = RegisteredConsumer {
+            consumer: Arc::new(consumer),
+            options,
+        };
+        let mut consumers = self.consumers.try_lock().expect("poison");
+        let mut updated = consumers.to_vec();
+        registered.insert_into(&mut updated);
+        *consumers = updated.into();
+    }
+
+    /// Registers an event publisher to forward incoming dispatch events to.
+    pub fn add_publisher(&self, publisher: impl EventPublisher + 'static) {
+        let mut publishers = self.publishers.try_lock().expect("poison");
+        let mut updated = publishers.to_vec();
+        updated.push(Arc::new(publisher));
+        *publishers = updated.into();
+    }
+
+    /// Closes the current connection to the gateway and establishes a new one.
+    ///
+    /// # Errors
+    /// * [`Error::NoConnection`] if the client is not running.
+    pub async fn reconnect(&self) -> Result<()> {
+        let messenger = self.messenger.lock().expect("poison").clone();
+        messenger.ok_or(Error::NoConnection)?.reconnect().await
+    }
+
+    /// Consumes serialized events from the given source instead of connecting to the gateway,
+    /// dispatching them to the registered event consumers until the source is exhausted.
+    ///
+    /// Events that cannot be decoded are logged and skipped.
+    pub async fn consume(&self, context: Context, mut source: impl EventSource) -> Result<()> {
+        while let Some(payload) = source.next_event().await {
+            match publish::decode(&payload) {
+                Ok(message) => {
+                    let replaced = context.cache.apply(&message).await;
+                    event::dispatch(
+                        &context,
+                        &self.consumers,
+                        message,
+                        replaced,
+                        self.options.watchdog.as_ref(),
+                    )
+                    .await;
+                }
+                Err(err) => warn!("Could not decode event from source: {err:?}"),
+            }
+        }
+        Ok(())
     }
 
     /// Starts and maintains a connection to the gateway.
+    ///
+    /// # Errors
+    /// * Any error which ends the connection, other than the gateway closing it, in which case
+    ///   the client reconnects instead.
     pub async fn start(&self, mut context: Context) -> Result<()> {
+        context.ready.send_replace(ReadyState::Pending);
+        let result = self.run(&mut context).await;
+        // Wake anyone waiting for the client to become ready
+        context.ready.send_replace(ReadyState::Stopped);
+        result
+    }
+
+    async fn run(&self, context: &mut Context) -> Result<()> {
         let (client_tx, mut client_rx) = channel(1024);
+        // The claim is released when it is dropped, including when an error is returned below
+        let claim = match &self.options.shard_coordinator {
+            Some(coordinator) => Some(ShardClaim::new(coordinator).await?),
+            None => None,
+        };
+        context.shard = claim.as_ref().map(ShardClaim::shard);
+
+        let result = 'a: loop {
+            if let Some(claim) = &claim {
+                claim.acquire_identify().await?;
+            }
 
-        'a: loop {
             let (runner_tx, runner_rx) = channel(1024);
-            let messenger = Messenger(runner_tx);
+            let stats = Arc::new(StatsRecorder::default());
+            let messenger = Messenger {
+                tx: runner_tx,
+                stats: stats.clone(),
+                presence: self.presence.clone(),
+            };
             context.ws = Some(messenger.clone());
+            *self.messenger.lock().expect("poison") = Some(messenger.clone());
+
+            // Identify with the latest presence rather than the initial one
+            let mut options = self.options.clone();
+            if let Some(presence) = self.presence.lock().expect("poison").clone() {
+                options.status = presence.status;
+                options.custom_status = presence.custom_status;
+            }
 
             let mut connection = Connection::new(
-                self.options.clone(),
+                options,
                 client_tx.clone(),
                 runner_rx,
                 self.consumers.clone(),
+                self.publishers.clone(),
                 context.clone(),
+                stats,
             )
             .await?;
 
             let tx = client_tx.clone();
             tokio::spawn(async move {
+                // Errors are logged by the connection along with its identifying fields
                 if let Err(err) = connection.run().await {
-                    warn!("Connection error: {:?}", err);
                     match err {
-                        Error::Closed(_) => tx.send(ClientAction::Reconnect).await,
-                        _ => tx.send(ClientAction::Close).await,
+                        Error::Closed(frame) => {
+                            connection.dispatch_disconnect(frame).await;
+                            tx.send(ClientAction::Reconnect).await
+                        }
+                        err => {
+                            let err = connection.error_context(err);
+                            tx.send(ClientAction::Close(Some(err))).await
+                        }
                     }
                     .ok();
                 }
@@ -148,18 +357,22 @@ impl Client {
             while let Some(action) = client_rx.recv().await {
                 match action {
                     ClientAction::Reconnect => {
-                        messenger.close().await?;
+                        // The connection may have already shut down by itself
+                        messenger.close().await.ok();
                         continue 'a;
                     }
-                    ClientAction::Close => {
-                        messenger.close().await?;
-                        break 'a;
-                    }
+                    // The connection has already shut down by the time it asks to close
+                    ClientAction::Close(None) => break 'a Ok(()),
+                    ClientAction::Close(Some(err)) => break 'a Err(err),
                 }
             }
-        }
+        };
 
         context.ws = None;
-        Ok(())
+        *self.messenger.lock().expect("poison") = None;
+        if let Some(claim) = claim {
+            claim.release().await?;
+        }
+        result
     }
 }
@@ -0,0 +1,49 @@
+//! Forwarding of gateway events to external systems, such as message brokers.
+//!
+//! This allows splitting large deployments into a gateway process, which maintains the connection
+//! to Harmony and publishes every dispatch event, and worker processes which consume them.
+
+use super::InboundMessage;
+use bytes::Bytes;
+use futures_util::future::BoxFuture;
+use std::future::Future;
+
+/// A dispatch event received from the gateway, ready to be published.
+#[derive(Clone, Debug)]
+pub struct PublishedEvent {
+    /// The raw event as received from the gateway.
+    pub message: InboundMessage,
+    /// The event serialized as MessagePack, in the same format it is sent by Harmony. Use
+    /// [`decode`] to deserialize it again.
+    pub payload: Bytes,
+}
+
+/// Publishes dispatch events received from the gateway to an external system.
+///
+/// Publishers are called with every dispatch event before it is resolved and dispatched to event
+/// consumers. Implementations for specific brokers (e.g. NATS, Kafka or Redis streams) are left to
+/// downstream crates.
+pub trait EventPublisher: Send + Sync {
+    /// Called when a dispatch event is received.
+    fn publish(&self, event: PublishedEvent) -> impl Future<Output = ()> + Send;
+}
+
+pub(crate) trait EventPublisherErased: Send + Sync {
+    fn dyn_publish(&self, event: PublishedEvent) -> BoxFuture<'_, ()>;
+}
+
+impl<T: EventPublisher> EventPublisherErased for T {
+    fn dyn_publish(&self, event: PublishedEvent) -> BoxFuture<'_, ()> {
+        Box::pin(EventPublisher::publish(self, event))
+    }
+}
+
+/// Serializes a raw gateway event into the format used by [`PublishedEvent::payload`].
+pub fn encode(message: &InboundMessage) -> super::Result<Bytes> {
+    Ok(rmp_serde::to_vec_named(message)?.into())
+}
+
+/// Deserializes a raw gateway event from the format used by [`PublishedEvent::payload`].
+pub fn decode(payload: &[u8]) -> super::Result<InboundMessage> {
+    Ok(rmp_serde::from_slice(payload)?)
+}
@@ -0,0 +1,129 @@
+//! Persistence of gateway sessions, allowing a restarted process to resume its previous session
+//! instead of identifying from scratch.
+//!
+//! Harmony does not currently support resuming sessions, so stored sessions are only resumed if
+//! [`ConnectOptions::resume_sessions`](super::ConnectOptions::resume_sessions) is enabled.
+
+use crate::utils::fs::write_atomic;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Debug};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Information about a gateway session required to resume it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionInfo {
+    /// The ID of the session, as received in the `ready` event.
+    pub session_id: String,
+    /// The sequence number of the last dispatch event received within the session. If the gateway
+    /// does not send sequence numbers, this is the number of dispatch events received instead.
+    pub sequence: u64,
+}
+
+/// A store in which gateway session information is persisted.
+///
+/// Sessions are saved when the session becomes ready, on every heartbeat, and when the connection
+/// is closed. These methods are called on a blocking thread, so implementations may block, for
+/// example on file I/O. Saves are not queued up: if a save is still in progress, only the latest
+/// session is saved once it completes.
+pub trait SessionStore: Send + Sync {
+    /// Loads the last saved session, if any.
+    fn load(&self) -> Option<SessionInfo>;
+
+    /// Saves the given session, replacing any previously saved session.
+    fn save(&self, session: &SessionInfo);
+
+    /// Clears the saved session. This is called when a session could not be resumed.
+    fn clear(&self);
+}
+
+/// A [`SessionStore`] that persists the session as JSON in a file. The file is replaced atomically,
+/// so that it is never left partially written.
+#[derive(Clone, Debug)]
+pub struct FileSessionStore {
+    path: PathBuf,
+}
+
+impl FileSessionStore {
+    /// Creates a new file session store which stores the session at the given path.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl SessionStore for FileSessionStore {
+    fn load(&self) -> Option<SessionInfo> {
+        let contents = std::fs::read(&self.path).ok()?;
+        serde_json::from_slice(&contents)
+            .map_err(|err| warn!("Could not parse stored session: {err}"))
+            .ok()
+    }
+
+    fn save(&self, session: &SessionInfo) {
+        let contents = serde_json::to_vec(session).expect("session should serialize");
+        if let Err(err) = write_atomic(&self.path, &contents) {
+            warn!("Could not save session to {}: {err}", self.path.display());
+        }
+    }
+
+    fn clear(&self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                warn!("Could not clear session at {}: {err}", self.path.display());
+            }
+        }
+    }
+}
+
+/// A cloneable handle to a [`SessionStore`].
+#[derive(Clone)]
+pub(crate) struct SessionStoreHandle(pub Arc<dyn SessionStore>);
+
+impl Debug for SessionStoreHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SessionStore").finish()
+    }
+}
+
+/// Saves and clears sessions on a blocking thread in the background, so that a slow store does not
+/// stall the connection. Updates are applied in order, skipping any which were superseded while
+/// the store was busy.
+#[derive(Debug)]
+pub(crate) struct SessionWriter {
+    /// The latest update, where `None` clears the stored session.
+    tx: watch::Sender<Option<SessionInfo>>,
+}
+
+impl SessionWriter {
+    /// Spawns a task writing updates to the given store until the writer is dropped. Pending
+    /// updates are still written after the writer is dropped.
+    pub fn spawn(store: SessionStoreHandle) -> Self {
+        let (tx, mut rx) = watch::channel(None);
+        tokio::spawn(async move {
+            while rx.changed().await.is_ok() {
+                let update = rx.borrow_and_update().clone();
+                let store = store.clone();
+                let write = tokio::task::spawn_blocking(move || match update {
+                    Some(session) => store.0.save(&session),
+                    None => store.0.clear(),
+                });
+                if let Err(err) = write.await {
+                    warn!("Session store failed to write the session: {err}");
+                }
+            }
+        });
+        Self { tx }
+    }
+
+    /// Saves the given session, replacing any previously saved session.
+    pub fn save(&self, session: &SessionInfo) {
+        self.tx.send_replace(Some(session.clone()));
+    }
+
+    /// Clears the saved session.
+    pub fn clear(&self) {
+        self.tx.send_replace(None);
+    }
+}
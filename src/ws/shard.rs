@@ -0,0 +1,458 @@
+//! Coordination of shards across processes.
+//!
+//! Once a bot outgrows a single process, the processes running its gateway connections must agree
+//! on which shard each of them runs and must not identify more often than allowed. A
+//! [`ShardCoordinator`] provides both of these. Two implementations are provided:
+//!
+//! * [`LocalShardCoordinator`], which coordinates shards within a single process.
+//! * [`TcpShardCoordinator`], which connects to a [`TcpCoordinatorServer`] over a simple
+//!   line-based TCP protocol and can be used as a reference for other implementations (e.g. one
+//!   backed by Redis).
+//!
+//! Harmony does not support partitioning events by shard: every connection receives the events
+//! of every guild, regardless of the shard it claimed. Coordinators only assign distinct shard
+//! IDs, exposed through [`Context::shard_id`](crate::Context::shard_id), and limit how often
+//! processes identify. Use the shard ID to divide work between processes, for example by only
+//! handling the events of guilds whose ID modulo the shard count equals the shard ID.
+
+use super::{Error, Result};
+use futures_util::future::BoxFuture;
+use std::collections::VecDeque;
+use std::fmt::{self, Debug};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::Mutex;
+
+/// Identifies a shard: a partition of the gateway connections of a client.
+///
+/// The shard is not sent to the gateway, which dispatches all events to every connection. See the
+/// [module documentation](self) for more information.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShardInfo {
+    /// The ID of the shard, between `0` and `count - 1`.
+    pub id: u32,
+    /// The total number of shards.
+    pub count: u32,
+}
+
+/// Coordinates shard assignment and identify concurrency across processes.
+pub trait ShardCoordinator: Send + Sync {
+    /// Claims an unassigned shard for this process. Returns [`Error::NoShardAvailable`] if all
+    /// shards are assigned.
+    fn claim(&self) -> impl Future<Output = Result<ShardInfo>> + Send;
+
+    /// Releases a previously claimed shard so that another process can claim it. Returns
+    /// [`Error::ShardNotClaimed`] if the shard was not claimed through this coordinator.
+    fn release(&self, shard: ShardInfo) -> impl Future<Output = Result<()>> + Send;
+
+    /// Waits until the given shard is allowed to identify with the gateway.
+    fn acquire_identify(&self, shard: ShardInfo) -> impl Future<Output = Result<()>> + Send;
+}
+
+pub(crate) trait ShardCoordinatorErased: Send + Sync {
+    fn dyn_claim(&self) -> BoxFuture<'_, Result<ShardInfo>>;
+    fn dyn_release(&self, shard: ShardInfo) -> BoxFuture<'_, Result<()>>;
+    fn dyn_acquire_identify(&self, shard: ShardInfo) -> BoxFuture<'_, Result<()>>;
+}
+
+impl<T: ShardCoordinator> ShardCoordinatorErased for T {
+    fn dyn_claim(&self) -> BoxFuture<'_, Result<ShardInfo>> {
+        Box::pin(self.claim())
+    }
+
+    fn dyn_release(&self, shard: ShardInfo) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.release(shard))
+    }
+
+    fn dyn_acquire_identify(&self, shard: ShardInfo) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.acquire_identify(shard))
+    }
+}
+
+/// A cloneable handle to a [`ShardCoordinator`].
+#[derive(Clone)]
+pub(crate) struct ShardCoordinatorHandle(pub Arc<dyn ShardCoordinatorErased>);
+
+impl Debug for ShardCoordinatorHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ShardCoordinator").finish()
+    }
+}
+
+/// A shard claimed from a coordinator, which is released when this is dropped.
+///
+/// Prefer [`Self::release`], which waits for the release to complete and reports errors. When
+/// dropped without it, the shard is released in the background.
+pub(crate) struct ShardClaim {
+    coordinator: ShardCoordinatorHandle,
+    shard: Option<ShardInfo>,
+}
+
+impl ShardClaim {
+    /// Claims an unassigned shard from the given coordinator.
+    pub async fn new(coordinator: &ShardCoordinatorHandle) -> Result<Self> {
+        let shard = coordinator.0.dyn_claim().await?;
+        Ok(Self {
+            coordinator: coordinator.clone(),
+            shard: Some(shard),
+        })
+    }
+
+    /// Returns the claimed shard.
+    pub fn shard(&self) -> ShardInfo {
+        self.shard.expect("shard is only taken on release")
+    }
+
+    /// Waits until the claimed shard is allowed to identify with the gateway.
+    pub async fn acquire_identify(&self) -> Result<()> {
+        self.coordinator.0.dyn_acquire_identify(self.shard()).await
+    }
+
+    /// Releases the claimed shard.
+    pub async fn release(mut self) -> Result<()> {
+        let shard = self.shard.take().expect("shard is only taken on release");
+        self.coordinator.0.dyn_release(shard).await
+    }
+}
+
+impl Drop for ShardClaim {
+    fn drop(&mut self) {
+        let Some(shard) = self.shard.take() else {
+            return;
+        };
+        let coordinator = self.coordinator.clone();
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => drop(runtime.spawn(async move {
+                if let Err(err) = coordinator.0.dyn_release(shard).await {
+                    warn!("Could not release shard {}: {err:?}", shard.id);
+                }
+            })),
+            Err(_) => warn!("Could not release shard {} outside of a runtime", shard.id),
+        }
+    }
+}
+
+/// The shared state of a coordinator: which shards are claimed and when identifies happened.
+#[derive(Debug)]
+struct CoordinatorState {
+    claimed: Vec<bool>,
+    identifies: VecDeque<Instant>,
+    max_concurrency: usize,
+}
+
+impl CoordinatorState {
+    fn new(shard_count: u32, max_concurrency: usize) -> Self {
+        Self {
+            claimed: vec![false; shard_count as usize],
+            identifies: VecDeque::with_capacity(max_concurrency),
+            max_concurrency: max_concurrency.max(1),
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn claim(&mut self) -> Option<ShardInfo> {
+        let id = self.claimed.iter().position(|claimed| !claimed)?;
+        self.claimed[id] = true;
+
+        Some(ShardInfo {
+            id: id as u32,
+            count: self.claimed.len() as u32,
+        })
+    }
+
+    fn is_claimed(&self, shard: ShardInfo) -> bool {
+        self.claimed
+            .get(shard.id as usize)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    fn release(&mut self, shard: ShardInfo) {
+        if let Some(claimed) = self.claimed.get_mut(shard.id as usize) {
+            *claimed = false;
+        }
+    }
+
+    /// Records an identify if one is allowed now, otherwise returns how long to wait.
+    fn try_identify(&mut self) -> Option<Duration> {
+        let now = Instant::now();
+        while self
+            .identifies
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= IDENTIFY_INTERVAL)
+        {
+            self.identifies.pop_front();
+        }
+
+        if self.identifies.len() < self.max_concurrency {
+            self.identifies.push_back(now);
+            return None;
+        }
+        self.identifies
+            .front()
+            .map(|at| IDENTIFY_INTERVAL.saturating_sub(now.duration_since(*at)))
+    }
+}
+
+/// The interval within which at most `max_concurrency` shards may identify.
+pub const IDENTIFY_INTERVAL: Duration = Duration::from_secs(5);
+
+async fn acquire_identify(state: &Mutex<CoordinatorState>) {
+    loop {
+        let wait = state.lock().await.try_identify();
+        match wait {
+            Some(wait) => tokio::time::sleep(wait).await,
+            None => return,
+        }
+    }
+}
+
+/// A [`ShardCoordinator`] which coordinates shards within a single process.
+#[derive(Clone, Debug)]
+pub struct LocalShardCoordinator {
+    state: Arc<Mutex<CoordinatorState>>,
+}
+
+impl LocalShardCoordinator {
+    /// Creates a new coordinator for the given number of shards, allowing at most
+    /// `max_concurrency` shards to identify within every [`IDENTIFY_INTERVAL`].
+    #[must_use]
+    pub fn new(shard_count: u32, max_concurrency: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(CoordinatorState::new(
+                shard_count,
+                max_concurrency,
+            ))),
+        }
+    }
+}
+
+impl ShardCoordinator for LocalShardCoordinator {
+    async fn claim(&self) -> Result<ShardInfo> {
+        self.state
+            .lock()
+            .await
+            .claim()
+            .ok_or(Error::NoShardAvailable)
+    }
+
+    async fn release(&self, shard: ShardInfo) -> Result<()> {
+        let mut state = self.state.lock().await;
+        if !state.is_claimed(shard) {
+            return Err(Error::ShardNotClaimed(shard));
+        }
+        state.release(shard);
+        Ok(())
+    }
+
+    async fn acquire_identify(&self, _shard: ShardInfo) -> Result<()> {
+        acquire_identify(&self.state).await;
+        Ok(())
+    }
+}
+
+/// A reference coordination server for [`TcpShardCoordinator`]s.
+///
+/// The protocol is line-based; each request is answered with a single line:
+///
+/// | Request            | Response                                   |
+/// |--------------------|--------------------------------------------|
+/// | `CLAIM`            | `<id> <count>`, or `NONE` if none are free |
+/// | `RELEASE <id>`     | `OK`, or `ERR` if not claimed by this connection |
+/// | `IDENTIFY <id>`    | `OK`, once the shard is allowed to identify |
+///
+/// Shards claimed over a connection are released when the connection closes.
+#[derive(Clone, Debug)]
+pub struct TcpCoordinatorServer {
+    state: Arc<Mutex<CoordinatorState>>,
+}
+
+impl TcpCoordinatorServer {
+    /// Creates a new coordination server for the given number of shards, allowing at most
+    /// `max_concurrency` shards to identify within every [`IDENTIFY_INTERVAL`].
+    #[must_use]
+    pub fn new(shard_count: u32, max_concurrency: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(CoordinatorState::new(
+                shard_count,
+                max_concurrency,
+            ))),
+        }
+    }
+
+    /// Accepts and serves connections from the given listener until an error occurs.
+    pub async fn serve(self, listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, addr) = listener.accept().await?;
+            let state = self.state.clone();
+
+            tokio::spawn(async move {
+                if let Err(err) = Self::handle(state, stream).await {
+                    warn!("Shard coordination connection from {addr} failed: {err:?}");
+                }
+            });
+        }
+    }
+
+    async fn handle(state: Arc<Mutex<CoordinatorState>>, stream: TcpStream) -> Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        let mut claimed = Vec::new();
+
+        let result = async {
+            while let Some(line) = lines.next_line().await? {
+                let mut parts = line.split_whitespace();
+                let response = match (parts.next(), parts.next().and_then(|id| id.parse().ok())) {
+                    (Some("CLAIM"), _) => match state.lock().await.claim() {
+                        Some(shard) => {
+                            claimed.push(shard);
+                            format!("{} {}\n", shard.id, shard.count)
+                        }
+                        None => "NONE\n".to_string(),
+                    },
+                    (Some("RELEASE"), Some(id)) => {
+                        // Only shards claimed over this connection may be released by it
+                        match claimed.iter().position(|shard: &ShardInfo| shard.id == id) {
+                            Some(index) => {
+                                state.lock().await.release(claimed.swap_remove(index));
+                                "OK\n".to_string()
+                            }
+                            None => "ERR\n".to_string(),
+                        }
+                    }
+                    (Some("IDENTIFY"), Some(_)) => {
+                        acquire_identify(&state).await;
+                        "OK\n".to_string()
+                    }
+                    _ => "ERR\n".to_string(),
+                };
+                writer.write_all(response.as_bytes()).await?;
+            }
+            Ok::<_, Error>(())
+        }
+        .await;
+
+        let mut state = state.lock().await;
+        for shard in claimed {
+            state.release(shard);
+        }
+        result
+    }
+}
+
+/// A [`ShardCoordinator`] which connects to a [`TcpCoordinatorServer`].
+#[derive(Debug)]
+pub struct TcpShardCoordinator {
+    stream: Mutex<BufReader<TcpStream>>,
+}
+
+impl TcpShardCoordinator {
+    /// Connects to the coordination server at the given address.
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Self> {
+        Ok(Self {
+            stream: Mutex::new(BufReader::new(TcpStream::connect(addr).await?)),
+        })
+    }
+
+    async fn request(&self, request: String) -> Result<String> {
+        let mut stream = self.stream.lock().await;
+        stream.get_mut().write_all(request.as_bytes()).await?;
+
+        let mut response = String::new();
+        if stream.read_line(&mut response).await? == 0 {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(response.trim_end().to_string())
+    }
+}
+
+impl ShardCoordinator for TcpShardCoordinator {
+    async fn claim(&self) -> Result<ShardInfo> {
+        let response = self.request("CLAIM\n".to_string()).await?;
+        let (id, count) = response.split_once(' ').ok_or(Error::NoShardAvailable)?;
+
+        match (id.parse(), count.parse()) {
+            (Ok(id), Ok(count)) => Ok(ShardInfo { id, count }),
+            _ => Err(Error::NoShardAvailable),
+        }
+    }
+
+    async fn release(&self, shard: ShardInfo) -> Result<()> {
+        let response = self.request(format!("RELEASE {}\n", shard.id)).await?;
+        match response.as_str() {
+            "OK" => Ok(()),
+            _ => Err(Error::ShardNotClaimed(shard)),
+        }
+    }
+
+    async fn acquire_identify(&self, shard: ShardInfo) -> Result<()> {
+        self.request(format!("IDENTIFY {}\n", shard.id)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn local_claim_and_release() {
+        let coordinator = LocalShardCoordinator::new(2, 1);
+
+        let first = coordinator.claim().await.unwrap();
+        let second = coordinator.claim().await.unwrap();
+        assert_eq!((first.id, second.id, second.count), (0, 1, 2));
+        assert!(coordinator.claim().await.is_err());
+
+        coordinator.release(first).await.unwrap();
+        assert!(coordinator.release(first).await.is_err());
+        assert_eq!(coordinator.claim().await.unwrap(), first);
+    }
+
+    #[tokio::test]
+    async fn tcp_release_requires_ownership() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(TcpCoordinatorServer::new(2, 1).serve(listener));
+
+        let owner = TcpShardCoordinator::connect(addr).await.unwrap();
+        let other = TcpShardCoordinator::connect(addr).await.unwrap();
+
+        let shard = owner.claim().await.unwrap();
+        assert!(matches!(
+            other.release(shard).await,
+            Err(Error::ShardNotClaimed(released)) if released == shard,
+        ));
+        // The shard is still claimed by its owner
+        assert_eq!(other.claim().await.unwrap().id, 1);
+
+        owner.release(shard).await.unwrap();
+        assert_eq!(owner.claim().await.unwrap(), shard);
+    }
+
+    #[tokio::test]
+    async fn dropped_claim_is_released() {
+        let coordinator = LocalShardCoordinator::new(1, 1);
+        let handle = ShardCoordinatorHandle(Arc::new(coordinator.clone()));
+
+        let claim = ShardClaim::new(&handle).await.unwrap();
+        assert!(coordinator.claim().await.is_err());
+
+        drop(claim);
+        tokio::task::yield_now().await;
+        assert_eq!(coordinator.claim().await.unwrap().id, 0);
+    }
+
+    #[tokio::test]
+    async fn released_claim_is_claimable() {
+        let coordinator = LocalShardCoordinator::new(1, 1);
+        let handle = ShardCoordinatorHandle(Arc::new(coordinator.clone()));
+
+        let claim = ShardClaim::new(&handle).await.unwrap();
+        claim.release().await.unwrap();
+        assert_eq!(coordinator.claim().await.unwrap().id, 0);
+    }
+}
@@ -0,0 +1,45 @@
+//! Consumption of gateway events from external sources, such as message brokers.
+//!
+//! This is the counterpart to [`publish`][super::publish]: worker processes can consume the
+//! events published by a gateway process and dispatch them to the same event consumers, without
+//! opening a connection to Harmony themselves.
+
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use std::future::Future;
+
+/// A source of serialized gateway events.
+pub trait EventSource: Send {
+    /// Receives the next serialized event, in the format of
+    /// [`PublishedEvent::payload`][super::PublishedEvent::payload]. Returns `None` when the source
+    /// is exhausted.
+    fn next_event(&mut self) -> impl Future<Output = Option<Bytes>> + Send;
+}
+
+struct StreamSource<S>(S);
+
+impl<S> EventSource for StreamSource<S>
+where
+    S: Stream<Item = Bytes> + Unpin + Send,
+{
+    async fn next_event(&mut self) -> Option<Bytes> {
+        self.0.next().await
+    }
+}
+
+/// Creates an event source from a stream of serialized events.
+///
+/// # Example
+/// ```no_run
+/// use adapt::ws::source;
+/// use bytes::Bytes;
+/// use futures_util::stream;
+///
+/// let source = source::from_stream(stream::iter(Vec::<Bytes>::new()));
+/// ```
+pub fn from_stream<S>(stream: S) -> impl EventSource
+where
+    S: Stream<Item = Bytes> + Unpin + Send,
+{
+    StreamSource(stream)
+}
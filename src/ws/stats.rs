@@ -0,0 +1,192 @@
+//! Throughput statistics for connections to the gateway.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A snapshot of the throughput statistics of a single connection to the gateway, obtained with
+/// [`Messenger::stats`](super::Messenger::stats).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GatewayStats {
+    /// The number of events received, keyed by their event type (e.g. `message_create`).
+    pub events_received: HashMap<String, u64>,
+    /// The total number of bytes received over the websocket.
+    pub bytes_received: u64,
+    /// The number of events currently being dispatched to event consumers.
+    ///
+    /// This is only meaningful with a [`DispatchExecutor`](super::handler::DispatchExecutor)
+    /// which handles events concurrently. The inline executor dispatches one event at a time on
+    /// the task receiving them, so the depth is at most `1`, and `0` whenever the connection is
+    /// waiting for the next event.
+    pub dispatch_queue_depth: usize,
+    /// The number of events that were dropped because the event consumers could not be acquired
+    /// in time.
+    pub dropped_events: u64,
+}
+
+impl GatewayStats {
+    /// Returns the total number of events received across all event types.
+    #[must_use]
+    pub fn total_events(&self) -> u64 {
+        self.events_received.values().sum()
+    }
+}
+
+/// A summary of the heartbeat latencies of a single connection to the gateway over the last
+/// [`LatencyStats::WINDOW`] heartbeats, obtained with
+/// [`Messenger::latency_stats`](super::Messenger::latency_stats).
+///
+/// All fields are `None` until the first heartbeat has been acknowledged.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LatencyStats {
+    /// The number of heartbeats the statistics are computed from.
+    pub samples: usize,
+    /// The latency of the most recent heartbeat.
+    pub last: Option<Duration>,
+    /// The median latency.
+    pub p50: Option<Duration>,
+    /// The 95th percentile latency.
+    pub p95: Option<Duration>,
+    /// The highest latency.
+    pub max: Option<Duration>,
+    /// The average difference in latency between consecutive heartbeats. This is `None` until two
+    /// heartbeats have been acknowledged.
+    pub jitter: Option<Duration>,
+}
+
+impl LatencyStats {
+    /// The number of most recent heartbeats the statistics are computed from.
+    pub const WINDOW: usize = 64;
+
+    fn from_samples(samples: &VecDeque<Duration>) -> Self {
+        let mut sorted = samples.iter().copied().collect::<Vec<_>>();
+        sorted.sort_unstable();
+        // Nearest-rank percentile
+        let percentile = |p: usize| {
+            let rank = (sorted.len() * p).div_ceil(100).max(1);
+            sorted.get(rank - 1).copied()
+        };
+
+        let deltas = samples
+            .iter()
+            .zip(samples.iter().skip(1))
+            .map(|(a, b)| a.abs_diff(*b))
+            .collect::<Vec<_>>();
+        let jitter = u32::try_from(deltas.len())
+            .ok()
+            .filter(|&n| n > 0)
+            .map(|n| deltas.iter().sum::<Duration>() / n);
+
+        Self {
+            samples: samples.len(),
+            last: samples.back().copied(),
+            p50: percentile(50),
+            p95: percentile(95),
+            max: sorted.last().copied(),
+            jitter,
+        }
+    }
+}
+
+/// Records statistics for a connection. This is shared between a [`Connection`](super::Connection)
+/// and its [`Messenger`](super::Messenger).
+#[derive(Debug, Default)]
+pub(crate) struct StatsRecorder {
+    events_received: Mutex<HashMap<String, u64>>,
+    bytes_received: AtomicU64,
+    dispatch_queue_depth: AtomicUsize,
+    dropped_events: AtomicU64,
+    latencies: Mutex<VecDeque<Duration>>,
+}
+
+impl StatsRecorder {
+    pub fn record_bytes(&self, bytes: usize) {
+        self.bytes_received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_event(&self, kind: &str) {
+        let mut events = self.events_received.lock().expect("poison");
+        match events.get_mut(kind) {
+            Some(count) => *count += 1,
+            None => {
+                events.insert(kind.to_string(), 1);
+            }
+        }
+    }
+
+    pub fn record_latency(&self, latency: Duration) {
+        let mut latencies = self.latencies.lock().expect("poison");
+        if latencies.len() == LatencyStats::WINDOW {
+            latencies.pop_front();
+        }
+        latencies.push_back(latency);
+    }
+
+    pub fn latency_snapshot(&self) -> LatencyStats {
+        LatencyStats::from_samples(&self.latencies.lock().expect("poison"))
+    }
+
+    pub fn record_dropped(&self) {
+        self.dropped_events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn enter_dispatch(&self) {
+        self.dispatch_queue_depth.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn exit_dispatch(&self) {
+        self.dispatch_queue_depth.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> GatewayStats {
+        GatewayStats {
+            events_received: self.events_received.lock().expect("poison").clone(),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            dispatch_queue_depth: self.dispatch_queue_depth.load(Ordering::Relaxed),
+            dropped_events: self.dropped_events.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot() {
+        let recorder = StatsRecorder::default();
+        recorder.record_bytes(128);
+        recorder.record_event("message_create");
+        recorder.record_event("message_create");
+        recorder.record_event("typing_start");
+        recorder.record_dropped();
+        recorder.enter_dispatch();
+
+        let stats = recorder.snapshot();
+        assert_eq!(stats.bytes_received, 128);
+        assert_eq!(stats.events_received["message_create"], 2);
+        assert_eq!(stats.total_events(), 3);
+        assert_eq!(stats.dropped_events, 1);
+        assert_eq!(stats.dispatch_queue_depth, 1);
+    }
+
+    #[test]
+    fn latency_window() {
+        let recorder = StatsRecorder::default();
+        assert_eq!(recorder.latency_snapshot(), LatencyStats::default());
+
+        for ms in (1..=100).rev() {
+            recorder.record_latency(Duration::from_millis(ms));
+        }
+        // Only the 64 most recent latencies (64ms down to 1ms) are kept
+        let stats = recorder.latency_snapshot();
+        assert_eq!(stats.samples, LatencyStats::WINDOW);
+        assert_eq!(stats.last, Some(Duration::from_millis(1)));
+        assert_eq!(stats.p50, Some(Duration::from_millis(32)));
+        assert_eq!(stats.p95, Some(Duration::from_millis(61)));
+        assert_eq!(stats.max, Some(Duration::from_millis(64)));
+        assert_eq!(stats.jitter, Some(Duration::from_millis(1)));
+    }
+}
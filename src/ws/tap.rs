@@ -0,0 +1,92 @@
+//! Inspection of the raw frames exchanged with the gateway, for protocol debugging and for
+//! recording traffic to attach to bug reports, and of frames that could not be decoded.
+
+use serde::Deserialize;
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+/// The direction in which a frame was sent.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FrameDirection {
+    /// The frame was received from the gateway.
+    Inbound,
+    /// The frame was sent to the gateway.
+    Outbound,
+}
+
+/// A raw frame exchanged with the gateway.
+#[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
+pub struct RawFrame<'a> {
+    /// The direction in which the frame was sent.
+    pub direction: FrameDirection,
+    /// The name of the operation or event carried by the frame, for example `identify` or
+    /// `message_create`, if it could be decoded.
+    pub op: Option<&'a str>,
+    /// The raw, msgpack-encoded contents of the frame.
+    pub bytes: &'a [u8],
+}
+
+/// A frame received from the gateway which could not be decoded, for example because it carries
+/// an event or field unknown to this version of the crate. Such frames are skipped without
+/// closing the connection.
+#[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
+pub struct UndecodableFrame<'a> {
+    /// The name of the event carried by the frame, if it could be decoded.
+    pub op: Option<&'a str>,
+    /// The raw, msgpack-encoded contents of the frame.
+    pub bytes: &'a [u8],
+    /// The error that occured while decoding the frame.
+    pub error: &'a rmp_serde::decode::Error,
+}
+
+/// A cloneable handle to a hook set with
+/// [`ConnectOptions::on_undecodable_frame`](super::ConnectOptions::on_undecodable_frame).
+#[derive(Clone)]
+pub(crate) struct UndecodableHook(pub Arc<dyn Fn(&UndecodableFrame) + Send + Sync>);
+
+impl Debug for UndecodableHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("UndecodableHook").finish()
+    }
+}
+
+/// A cloneable handle to a frame tap set with
+/// [`ConnectOptions::frame_tap`](super::ConnectOptions::frame_tap).
+#[derive(Clone)]
+pub(crate) struct FrameTap(pub Arc<dyn Fn(&RawFrame) + Send + Sync>);
+
+impl FrameTap {
+    /// Passes the given frame to the tap, along with its already decoded tag.
+    pub fn tap(&self, direction: FrameDirection, tag: Option<&EventTag>, bytes: &[u8]) {
+        (self.0)(&RawFrame {
+            direction,
+            op: tag.map(|tag| tag.event.as_str()),
+            bytes,
+        });
+    }
+}
+
+impl Debug for FrameTap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FrameTap").finish()
+    }
+}
+
+/// The type tag of a frame, decoded separately from its payload to record statistics and to name
+/// tapped frames, along with its sequence number if the gateway sent one.
+#[derive(Deserialize)]
+pub(super) struct EventTag {
+    pub event: String,
+    #[serde(default)]
+    pub seq: Option<u64>,
+}
+
+impl EventTag {
+    /// Returns whether the frame is a dispatch event, which counts towards the sequence of the
+    /// session.
+    pub fn is_dispatch(&self) -> bool {
+        !matches!(self.event.as_str(), "hello" | "ping" | "pong")
+    }
+}